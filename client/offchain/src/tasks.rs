@@ -0,0 +1,94 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Helpers to check runtime tasks concurrently, off-chain.
+
+/// Applies `check` to each of `items`, returning the results in the same order.
+///
+/// The items for which `can_run_in_parallel` holds are checked on scoped threads, at most one per
+/// available core, while the others are checked on the current thread. This is meant for the
+/// validity checks of tasks, e.g. with `Task::can_run_in_parallel` and `Task::validity` of
+/// `frame-support`, whose parallel checks have no access to the externalities. Running tasks must
+/// always happen serially.
+pub fn check_partitioned<T, R>(
+	items: &[T],
+	can_run_in_parallel: impl Fn(&T) -> bool,
+	check: impl Fn(&T) -> R + Sync,
+) -> Vec<R>
+where
+	T: Sync,
+	R: Send,
+{
+	let (parallel, serial): (Vec<_>, Vec<_>) =
+		items.iter().enumerate().partition(|(_, item)| can_run_in_parallel(item));
+	let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+	let chunk_size = parallel.len().div_ceil(threads).max(1);
+	let check = &check;
+
+	let mut results = std::iter::repeat_with(|| None).take(items.len()).collect::<Vec<_>>();
+	std::thread::scope(|scope| {
+		let handles = parallel
+			.chunks(chunk_size)
+			.map(|chunk| {
+				scope.spawn(move || {
+					chunk.iter().map(|(i, item)| (*i, check(item))).collect::<Vec<_>>()
+				})
+			})
+			.collect::<Vec<_>>();
+		for (i, item) in serial {
+			results[i] = Some(check(item));
+		}
+		for handle in handles {
+			let checked = handle.join().unwrap_or_else(|e| std::panic::resume_unwind(e));
+			for (i, result) in checked {
+				results[i] = Some(result);
+			}
+		}
+	});
+	results
+		.into_iter()
+		.map(|r| r.expect("each item is checked exactly once; qed"))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::thread;
+
+	#[test]
+	fn check_partitioned_matches_serial_checks() {
+		let items = (0..20u32).collect::<Vec<_>>();
+		let check = |id: &u32| (id % 3 != 0).then_some(*id);
+		let baseline = items.iter().map(check).collect::<Vec<_>>();
+
+		assert_eq!(check_partitioned(&items, |id| id % 2 == 0, check), baseline);
+		assert_eq!(check_partitioned(&items, |_| false, check), baseline);
+		assert_eq!(check_partitioned(&items, |_| true, check), baseline);
+		assert_eq!(check_partitioned(&[], |_: &u32| true, check), vec![]);
+	}
+
+	#[test]
+	fn only_parallel_items_leave_the_current_thread() {
+		let items = (0..20u32).collect::<Vec<_>>();
+		let current = thread::current().id();
+		let on_current =
+			check_partitioned(&items, |id| id % 2 == 0, |_| thread::current().id() == current);
+		assert!(items.iter().zip(on_current).all(|(id, on_current)| on_current == (id % 2 == 1)));
+	}
+}
@@ -390,6 +390,22 @@ impl<T: Config<I>, I: 'static> Pallet<T, I> {
 		Ok(maybe_movement)
 	}
 
+	/// Move many accounts to their appropriate bags at once, see
+	/// [`SortedListProvider::on_update_batch`].
+	///
+	/// Fails without changing anything if any of the accounts is not in the list.
+	pub fn do_rebag_batch(updates: Vec<(T::AccountId, T::Score)>) -> Result<(), ListError> {
+		let scores = updates.clone();
+		let moved = List::<T, I>::update_positions_for(updates)?;
+		for (who, from, to) in moved.into_iter().filter(|(_, from, to)| from != to) {
+			Self::deposit_event(Event::<T, I>::Rebagged { who, from, to });
+		}
+		for (who, new_score) in scores {
+			Self::deposit_event(Event::<T, I>::ScoreUpdated { who, new_score });
+		}
+		Ok(())
+	}
+
 	/// Equivalent to `ListBags::get`, but public. Useful for tests in outside of this crate.
 	#[cfg(feature = "std")]
 	pub fn list_bags_get(score: T::Score) -> Option<list::Bag<T, I>> {
@@ -428,10 +444,38 @@ impl<T: Config<I>, I: 'static> SortedListProvider<T::AccountId> for Pallet<T, I>
 		List::<T, I>::get_score(id)
 	}
 
+	fn rank_of(id: &T::AccountId) -> Option<u32> {
+		List::<T, I>::rank_of(id)
+	}
+
+	fn count_in_range(low: T::Score, high: T::Score) -> u32 {
+		List::<T, I>::count_in_range(low, high)
+	}
+
+	fn highest() -> Option<T::AccountId> {
+		List::<T, I>::highest()
+	}
+
+	fn lowest() -> Option<T::AccountId> {
+		List::<T, I>::lowest()
+	}
+
+	fn rebag(id: &T::AccountId) -> Result<bool, ListError> {
+		let node = list::Node::<T, I>::get(id).ok_or(ListError::NodeNotFound)?;
+		if !node.is_misplaced(node.score()) {
+			return Ok(false)
+		}
+		Self::do_rebag(id, node.score()).map(|moved| moved.is_some())
+	}
+
 	fn on_update(id: &T::AccountId, new_score: T::Score) -> Result<(), ListError> {
 		Pallet::<T, I>::do_rebag(id, new_score).map(|_| ())
 	}
 
+	fn on_update_batch(updates: Vec<(T::AccountId, T::Score)>) -> Result<(), ListError> {
+		Pallet::<T, I>::do_rebag_batch(updates)
+	}
+
 	fn on_remove(id: &T::AccountId) -> Result<(), ListError> {
 		List::<T, I>::remove(id)
 	}
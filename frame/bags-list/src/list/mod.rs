@@ -262,6 +262,100 @@ impl<T: Config<I>, I: 'static> List<T, I> {
 		iter.filter_map(Bag::get).flat_map(|bag| bag.iter())
 	}
 
+	/// The zero-based position of `id` in the list, or `None` if it is not in the list.
+	///
+	/// This is the number of nodes in the bags above the one of `id`, plus the number of nodes in
+	/// front of `id` within its own bag. Since bags do not keep track of their length, the nodes
+	/// in front of `id` are still walked through, but without reading any score.
+	pub(crate) fn rank_of(id: &T::AccountId) -> Option<u32> {
+		let node = Node::<T, I>::get(id)?;
+
+		let higher_bags = T::BagThresholds::get()
+			.iter()
+			.copied()
+			.chain(iter::once(T::Score::max_value()))
+			.filter(|bag_upper| *bag_upper > node.bag_upper)
+			// the final bound might have been included in the thresholds already.
+			.collect::<BTreeSet<_>>();
+		let in_higher_bags = higher_bags
+			.into_iter()
+			.filter_map(Bag::<T, I>::get)
+			.map(|bag| bag.iter().count())
+			.sum::<usize>();
+		let in_front = iter::successors(node.prev(), |prev| prev.prev()).count();
+
+		Some(in_higher_bags.saturating_add(in_front) as u32)
+	}
+
+	/// The number of nodes whose score lies within `[low, high]`.
+	///
+	/// This is a scan: bags do not keep track of their length, so every node of the bags
+	/// overlapping the range is read from storage. Only the bags outside of the range are skipped,
+	/// and only the nodes of the (at most two) boundary bags have their score checked. The cost is
+	/// thus linear in the number of nodes around the range, which makes this unsuitable for use
+	/// within blocks on large lists.
+	pub(crate) fn count_in_range(low: T::Score, high: T::Score) -> u32 {
+		if low > high {
+			return 0
+		}
+
+		let thresholds = Self::bag_uppers();
+
+		let mut count: u32 = 0;
+		// a bag holds the scores above the upper bound of the previous bag, up to its own.
+		let mut prev_upper: Option<T::Score> = None;
+		for bag_upper in thresholds {
+			let overlaps = bag_upper >= low && prev_upper.map_or(true, |prev| prev < high);
+			if let Some(bag) = overlaps.then(|| Bag::<T, I>::get(bag_upper)).flatten() {
+				let covered =
+					bag_upper <= high && prev_upper.map_or(low.is_zero(), |prev| prev >= low);
+				let in_bag = if covered {
+					bag.iter().count()
+				} else {
+					bag.iter().filter(|node| low <= node.score && node.score <= high).count()
+				};
+				count = count.saturating_add(in_bag as u32);
+			}
+			prev_upper = Some(bag_upper);
+		}
+		count
+	}
+
+	/// The id with the highest score, if any.
+	///
+	/// Only the nodes of the highest non-empty bag are looked at.
+	pub(crate) fn highest() -> Option<T::AccountId> {
+		Self::bag_uppers()
+			.into_iter()
+			.rev()
+			.find_map(Bag::<T, I>::get)
+			.and_then(|bag| {
+				bag.iter().reduce(|top, node| if node.score > top.score { node } else { top })
+			})
+			.map(|node| node.id)
+	}
+
+	/// The id with the lowest score, if any.
+	///
+	/// Only the nodes of the lowest non-empty bag are looked at.
+	pub(crate) fn lowest() -> Option<T::AccountId> {
+		Self::bag_uppers()
+			.into_iter()
+			.find_map(Bag::<T, I>::get)
+			.and_then(|bag| bag.iter().min_by_key(|node| node.score))
+			.map(|node| node.id)
+	}
+
+	/// The upper bound of every bag, in ascending order, including the final bound.
+	fn bag_uppers() -> Vec<T::Score> {
+		let thresholds = T::BagThresholds::get().iter().copied();
+		if thresholds.clone().last() == Some(T::Score::max_value()) {
+			thresholds.collect()
+		} else {
+			thresholds.chain(iter::once(T::Score::max_value())).collect()
+		}
+	}
+
 	/// Same as `iter`, but we start from a specific node.
 	///
 	/// All items after this node are returned, excluding `start` itself.
@@ -433,6 +527,78 @@ impl<T: Config<I>, I: 'static> List<T, I> {
 		}
 	}
 
+	/// Update the position of many nodes in the list at once.
+	///
+	/// The outcome is the same as calling [`Self::update_position_for`] for each of `updates`, in
+	/// order, but each affected bag is only written once. Within a bag, the nodes that were moved
+	/// into it end up at its tail, in the order of their last move.
+	///
+	/// Returns, in no particular order, `(id, old_idx, new_idx)` for each node that was moved, or
+	/// an error without changing anything if any of the ids is not in the list.
+	pub(crate) fn update_positions_for(
+		updates: Vec<(T::AccountId, T::Score)>,
+	) -> Result<Vec<(T::AccountId, T::Score, T::Score)>, ListError> {
+		// per updated id: its original bag, its latest score and bag, and the index of the update
+		// that last moved it, if any.
+		let mut outcomes: BTreeMap<T::AccountId, (T::Score, T::Score, T::Score, Option<usize>)> =
+			BTreeMap::new();
+		for (idx, (id, score)) in updates.into_iter().enumerate() {
+			let outcome = match outcomes.get_mut(&id) {
+				Some(outcome) => outcome,
+				None => {
+					let node = Node::<T, I>::get(&id).ok_or(ListError::NodeNotFound)?;
+					outcomes.entry(id).or_insert((node.bag_upper, node.score, node.bag_upper, None))
+				},
+			};
+			let notional_bag = notional_bag_for::<T, I>(score);
+			if notional_bag != outcome.2 {
+				outcome.2 = notional_bag;
+				outcome.3 = Some(idx);
+			}
+			outcome.1 = score;
+		}
+
+		let mut bags = BTreeMap::new();
+		let mut moved = Vec::new();
+		for (id, (old_bag, score, new_bag, maybe_idx)) in outcomes {
+			// nodes are read again, since their neighbours might have been moved already.
+			let mut node = Node::<T, I>::get(&id).defensive_ok_or(ListError::NodeNotFound)?;
+			node.score = score;
+			match maybe_idx {
+				Some(idx) => {
+					if !node.is_terminal() {
+						node.excise();
+					} else {
+						bags.entry(old_bag)
+							.or_insert_with(|| Bag::<T, I>::get_or_make(old_bag))
+							.remove_node_unchecked(&node);
+					}
+					moved.push((idx, node, old_bag, new_bag));
+				},
+				None => node.put(),
+			}
+		}
+
+		// re-insert the moved nodes in the order of their last move.
+		moved.sort_by_key(|(idx, _, _, _)| *idx);
+		let moved = moved
+			.into_iter()
+			.map(|(_, node, old_bag, new_bag)| {
+				let id = node.id.clone();
+				bags.entry(new_bag)
+					.or_insert_with(|| Bag::<T, I>::get_or_make(new_bag))
+					.insert_node_unchecked(node);
+				(id, old_bag, new_bag)
+			})
+			.collect();
+
+		for (_, bag) in bags {
+			bag.put();
+		}
+
+		Ok(moved)
+	}
+
 	/// Put `heavier_id` to the position directly in front of `lighter_id`. Both ids must be in the
 	/// same bag and the `score_of` `lighter_id` must be less than that of `heavier_id`.
 	pub(crate) fn put_in_front_of(
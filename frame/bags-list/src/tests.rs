@@ -560,6 +560,36 @@ mod sorted_list_provider {
 		});
 	}
 
+	#[test]
+	fn get_score_works() {
+		ExtBuilder::default().build_and_execute(|| {
+			assert_eq!(BagsList::get_score(&1), Ok(10));
+			assert_eq!(BagsList::get_score(&2), Ok(1_000));
+
+			// a missing id is reported as such, without touching storage.
+			assert_storage_noop!(assert_eq!(
+				BagsList::get_score(&42),
+				Err(ListError::NodeNotFound)
+			));
+		});
+	}
+
+	#[test]
+	fn rebag_moves_misplaced_ids() {
+		ExtBuilder::default().build_and_execute(|| {
+			// the thresholds change, leaving 1 in a bag that is not its notional one anymore.
+			BagThresholds::set(&[20, 1_000]);
+
+			assert_eq!(BagsList::rebag(&2), Ok(false));
+			assert_eq!(BagsList::rebag(&1), Ok(true));
+			assert_eq!(List::<Runtime>::get_bags(), vec![(20, vec![1]), (1_000, vec![2, 3, 4])]);
+			assert_eq!(BagsList::get_score(&1), Ok(10));
+
+			assert_eq!(BagsList::rebag(&1), Ok(false));
+			assert_eq!(BagsList::rebag(&42), Err(ListError::NodeNotFound));
+		});
+	}
+
 	#[test]
 	fn count_works() {
 		ExtBuilder::default().build_and_execute(|| {
@@ -583,6 +613,88 @@ mod sorted_list_provider {
 		});
 	}
 
+	#[test]
+	fn rank_of_works() {
+		ExtBuilder::default()
+			.add_ids(vec![(5, 5), (6, 15), (9, 2_000)])
+			.build_and_execute(|| {
+				assert_eq!(get_list_as_ids(), vec![9, 2, 3, 4, 6, 1, 5]);
+
+				for (rank, id) in get_list_as_ids().into_iter().enumerate() {
+					assert_eq!(BagsList::rank_of(&id), Some(rank as u32));
+				}
+				assert_eq!(BagsList::rank_of(&42), None);
+
+				// ranks follow the moves of a node.
+				assert_ok!(BagsList::on_update(&5, 1_000));
+				assert_eq!(BagsList::rank_of(&5), Some(4));
+				assert_eq!(BagsList::rank_of(&6), Some(5));
+			});
+	}
+
+	#[test]
+	fn count_in_range_works() {
+		ExtBuilder::default()
+			.add_ids(vec![(5, 5), (6, 15), (7, 15), (8, 25), (9, 2_000)])
+			.build_and_execute(|| {
+				let naive = |low, high| {
+					BagsList::iter()
+						.filter(|id| (low..=high).contains(&BagsList::get_score(id).unwrap()))
+						.count() as u32
+				};
+
+				for (low, high, expected) in [
+					// everything.
+					(0, VoteWeight::MAX, 9),
+					// whole bags only.
+					(0, 10, 2),
+					(11, 30, 3),
+					(31, 1_000, 3),
+					// boundaries within bags.
+					(6, 999, 4),
+					(11, 20, 2),
+					(15, 15, 2),
+					(1_000, 2_000, 4),
+					(0, 5, 1),
+					// nothing.
+					(16, 19, 0),
+					(2_001, VoteWeight::MAX, 0),
+					(20, 10, 0),
+				] {
+					assert_eq!(BagsList::count_in_range(low, high), expected);
+					assert_eq!(BagsList::count_in_range(low, high), naive(low, high));
+				}
+			});
+	}
+
+	#[test]
+	fn highest_and_lowest_work() {
+		ExtBuilder::default().build_and_execute(|| {
+			assert_eq!(BagsList::highest(), Some(2));
+			assert_eq!(BagsList::lowest(), Some(1));
+		});
+
+		ExtBuilder::default()
+			.add_ids(vec![(5, 15), (6, 12), (7, 2_000), (8, 1_500)])
+			.build_and_execute(|| {
+				// the order within a bag does not matter.
+				assert_eq!(get_list_as_ids(), vec![7, 8, 2, 3, 4, 5, 6, 1]);
+				assert_eq!(BagsList::highest(), Some(7));
+				assert_ok!(BagsList::on_update(&8, 1_800));
+				assert_ok!(BagsList::on_update(&7, 1_600));
+				assert_eq!(BagsList::highest(), Some(8));
+				assert_eq!(BagsList::lowest(), Some(1));
+
+				assert_ok!(BagsList::on_remove(&1));
+				assert_eq!(BagsList::lowest(), Some(6));
+			});
+
+		ExtBuilder::default().skip_genesis_ids().build_and_execute(|| {
+			assert_eq!(BagsList::highest(), None);
+			assert_eq!(BagsList::lowest(), None);
+		});
+	}
+
 	#[test]
 	fn on_insert_works() {
 		ExtBuilder::default().build_and_execute(|| {
@@ -684,6 +796,53 @@ mod sorted_list_provider {
 		});
 	}
 
+	#[test]
+	fn on_update_batch_matches_sequential_updates() {
+		let scenarios: Vec<Vec<(AccountId, VoteWeight)>> = vec![
+			// no movement at all.
+			vec![(2, 1_001), (1, 9)],
+			// moves into existing and new bags, from the head, middle and tail of a bag.
+			vec![(2, 15), (3, 2_000), (4, 15), (5, 60)],
+			// the same node moving several times, including back into its own bag.
+			vec![(3, 15), (2, 15), (3, 1_000), (6, 2_000), (3, 2_000)],
+			// emptying a bag entirely, and moving nodes back and forth.
+			vec![(1, 1_000), (5, 1_000), (2, 10), (1, 10), (6, 30)],
+		];
+
+		let ext_builder = || ExtBuilder::default().add_ids(vec![(5, 5), (6, 15), (7, 15)]);
+		let state = || {
+			let scores = ListNodes::<Runtime>::iter()
+				.map(|(id, node)| (id, node.score))
+				.collect::<std::collections::BTreeMap<_, _>>();
+			(List::<Runtime>::get_bags(), get_list_as_ids(), scores)
+		};
+
+		for updates in scenarios {
+			let mut sequential = None;
+			ext_builder().build_and_execute(|| {
+				for (id, score) in updates.clone() {
+					assert_ok!(BagsList::on_update(&id, score));
+				}
+				sequential = Some(state());
+			});
+
+			ext_builder().build_and_execute(|| {
+				assert_ok!(BagsList::on_update_batch(updates.clone()));
+				assert_eq!(Some(state()), sequential, "diverged for {:?}", updates);
+			});
+		}
+	}
+
+	#[test]
+	fn on_update_batch_fails_atomically() {
+		ExtBuilder::default().build_and_execute(|| {
+			assert_noop!(
+				BagsList::on_update_batch(vec![(2, 10), (42, 10), (3, 2_000)]),
+				ListError::NodeNotFound
+			);
+		});
+	}
+
 	#[test]
 	fn on_remove_works() {
 		let ensure_left = |id, counter| {
@@ -517,9 +517,85 @@ pub trait SortedListProvider<AccountId> {
 	/// Returns `Ok(())` iff it successfully updates an item, an `Err(_)` otherwise.
 	fn on_update(id: &AccountId, score: Self::Score) -> Result<(), Self::Error>;
 
+	/// Same as `on_update`, but for many ids at once.
+	///
+	/// The resulting order must be the same as calling `on_update` for each of `updates`, in
+	/// order. Implementations may override this to reduce the number of storage operations.
+	fn on_update_batch(updates: Vec<(AccountId, Self::Score)>) -> Result<(), Self::Error> {
+		updates.into_iter().try_for_each(|(id, score)| Self::on_update(&id, score))
+	}
+
 	/// Get the score of `id`.
 	fn get_score(id: &AccountId) -> Result<Self::Score, Self::Error>;
 
+	/// The zero-based position of `id` in the list, i.e. in descending score order, or `None` if
+	/// it is not in the list.
+	///
+	/// By default, this iterates over the list up to `id`. Implementations may override this to
+	/// do better.
+	fn rank_of(id: &AccountId) -> Option<u32>
+	where
+		AccountId: PartialEq,
+	{
+		Self::iter().position(|other| &other == id).map(|rank| rank as u32)
+	}
+
+	/// Moves `id` to the position its score calls for, if the list left it elsewhere, e.g. a
+	/// node of a bags list left in a bag whose thresholds changed since. Returns whether `id` was
+	/// moved.
+	///
+	/// By default, lists are assumed to always keep ids in place, hence nothing is moved.
+	fn rebag(id: &AccountId) -> Result<bool, Self::Error> {
+		Self::get_score(id).map(|_| false)
+	}
+
+	/// The number of ids whose score lies within `[low, high]`.
+	///
+	/// By default, this iterates over the whole list. Implementations may override this to only
+	/// look at the relevant part of the list.
+	fn count_in_range(low: Self::Score, high: Self::Score) -> u32
+	where
+		Self::Score: PartialOrd,
+	{
+		Self::iter()
+			.filter(|id| Self::get_score(id).is_ok_and(|score| low <= score && score <= high))
+			.count() as u32
+	}
+
+	/// The id with the highest score, if any.
+	///
+	/// By default, this iterates over the whole list. Implementations may override this to only
+	/// look at the relevant part of the list.
+	fn highest() -> Option<AccountId>
+	where
+		Self::Score: Ord,
+	{
+		Self::iter()
+			.filter_map(|id| Self::get_score(&id).ok().map(|s| (id, s)))
+			.fold(None, |best: Option<(AccountId, Self::Score)>, (id, score)| match best {
+				Some((_, ref top)) if *top >= score => best,
+				_ => Some((id, score)),
+			})
+			.map(|(id, _)| id)
+	}
+
+	/// The id with the lowest score, if any.
+	///
+	/// By default, this iterates over the whole list. Implementations may override this to only
+	/// look at the relevant part of the list.
+	fn lowest() -> Option<AccountId>
+	where
+		Self::Score: Ord,
+	{
+		Self::iter()
+			.filter_map(|id| Self::get_score(&id).ok().map(|s| (id, s)))
+			.fold(None, |best: Option<(AccountId, Self::Score)>, (id, score)| match best {
+				Some((_, ref bottom)) if *bottom <= score => best,
+				_ => Some((id, score)),
+			})
+			.map(|(id, _)| id)
+	}
+
 	/// Same as `on_update`, but incorporate some increased score.
 	fn on_increase(id: &AccountId, additional: Self::Score) -> Result<(), Self::Error> {
 		let old_score = Self::get_score(id)?;
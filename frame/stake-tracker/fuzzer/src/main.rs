@@ -0,0 +1,182 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Running
+//! Running this fuzzer can be done with `cargo hfuzz run stake-tracker`. `honggfuzz` CLI options
+//! can be used by setting `HFUZZ_RUN_ARGS`, such as `-n 4` to use 4 threads.
+//!
+//! # Debugging a panic
+//! Once a panic is found, it can be debugged with
+//! `cargo hfuzz run-debug stake-tracker hfuzz_workspace/stake-tracker/*.fuzz`.
+//!
+//! Before panicking, the fuzzer shrinks the failing sequence of operations by dropping them one
+//! at a time for as long as it still fails, and reports the minimal sequence in the panic message.
+//!
+//! # More information
+//! More information about `honggfuzz` can be found
+//! [here](https://docs.rs/honggfuzz/).
+
+use arbitrary::{Arbitrary, Unstructured};
+use frame_election_provider_support::SortedListProvider;
+use honggfuzz::fuzz;
+use pallet_stake_tracker::mock::*;
+use sp_staking::{StakerStatus, StakingInterface};
+use std::{
+	collections::BTreeSet,
+	panic::{self, AssertUnwindSafe},
+};
+
+/// The accounts of the mock, which are all bonded.
+const ACCOUNTS: [AccountId; 5] = [1, 10, 11, 20, 30];
+
+/// Staking operations, each firing the hooks that staking would fire.
+#[derive(Debug, Clone)]
+enum Op {
+	/// Changes the active stake of an account.
+	Bond(AccountId, Balance),
+	/// Makes an account nominate some of the accounts.
+	Nominate(AccountId, Vec<AccountId>),
+	/// Makes an account validate.
+	Validate(AccountId),
+	/// Makes an account neither validate nor nominate.
+	Chill(AccountId),
+}
+
+impl Arbitrary<'_> for Op {
+	fn arbitrary(u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+		let who = *u.choose(&ACCOUNTS)?;
+		Ok(match u.int_in_range(0..=3)? {
+			0 => Op::Bond(who, u.int_in_range(0..=1_000)?),
+			1 => {
+				let mut targets = Vec::new();
+				for target in ACCOUNTS {
+					if u.arbitrary()? {
+						targets.push(target);
+					}
+				}
+				Op::Nominate(who, targets)
+			},
+			2 => Op::Validate(who),
+			_ => Op::Chill(who),
+		})
+	}
+}
+
+impl Op {
+	fn exec(self) {
+		match self {
+			Op::Bond(who, active) => {
+				let prev = set_active_stake(who, active);
+				StakeTracker::on_stake_update(&who, Some(prev));
+			},
+			Op::Nominate(who, targets) => {
+				if StakingMock::is_validator(&who) {
+					chill(who);
+					StakeTracker::on_validator_remove(&who);
+				}
+				let prev = nominate(who, targets);
+				StakeTracker::on_nominator_update(&who, prev);
+			},
+			Op::Validate(who) => {
+				if let Some(nominations) = StakingMock::nominations(&who) {
+					chill(who);
+					StakeTracker::on_nominator_remove(&who, nominations);
+				}
+				if !StakingMock::is_validator(&who) {
+					validate(who);
+					StakeTracker::on_validator_add(&who);
+				}
+			},
+			Op::Chill(who) =>
+				if let Some(nominations) = StakingMock::nominations(&who) {
+					chill(who);
+					StakeTracker::on_nominator_remove(&who, nominations);
+				} else if StakingMock::is_validator(&who) {
+					chill(who);
+					StakeTracker::on_validator_remove(&who);
+				},
+		}
+	}
+}
+
+/// Checks that the lists are consistent with the approvals and with the staking mock.
+fn assert_consistent() {
+	if let Err(e) = StakeTracker::ensure_consistency() {
+		panic!("inconsistent lists and approvals: {}", e);
+	}
+
+	let targets = TargetBagsList::iter().collect::<BTreeSet<_>>();
+	assert_eq!(targets, Validators::get(), "targets do not match the validators");
+
+	let voters = VoterBagsList::iter().collect::<BTreeSet<_>>();
+	let stakers = Bonded::get()
+		.into_keys()
+		.filter(|who| !matches!(StakingMock::status(who), Ok(StakerStatus::Idle)))
+		.collect::<BTreeSet<_>>();
+	assert_eq!(voters, stakers, "voters do not match the validators and nominators");
+}
+
+/// Executes `ops` from the genesis of the mock, checking consistency after each of them.
+fn run(ops: &[Op]) {
+	// the staking mock is kept in thread-local statics, which outlive the externalities.
+	Bonded::set(Default::default());
+	Nominators::set(Default::default());
+	Validators::set(Default::default());
+
+	ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+		assert_consistent();
+		for op in ops {
+			op.clone().exec();
+			assert_consistent();
+		}
+	});
+}
+
+/// Whether `ops` fail, without reporting the panic.
+fn fails(ops: &[Op]) -> bool {
+	let hook = panic::take_hook();
+	panic::set_hook(Box::new(|_| {}));
+	let failed = panic::catch_unwind(AssertUnwindSafe(|| run(ops))).is_err();
+	panic::set_hook(hook);
+	failed
+}
+
+/// Drops operations from the failing `ops` for as long as they still fail.
+fn shrink(mut ops: Vec<Op>) -> Vec<Op> {
+	let mut i = 0;
+	while i < ops.len() {
+		let mut candidate = ops.clone();
+		candidate.remove(i);
+		if fails(&candidate) {
+			ops = candidate;
+		} else {
+			i += 1;
+		}
+	}
+	ops
+}
+
+fn main() {
+	loop {
+		fuzz!(|ops: Vec<Op>| {
+			if fails(&ops) {
+				let minimal = shrink(ops);
+				panic!("minimal failing sequence: {:?}", minimal);
+			}
+		});
+	}
+}
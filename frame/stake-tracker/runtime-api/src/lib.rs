@@ -0,0 +1,48 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime API definition for the stake tracker pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	pub trait StakeTrackerApi<AccountId, Balance>
+		where
+			AccountId: Codec,
+			Balance: Codec,
+	{
+		/// Returns one page of at most `page_size` targets with their approval stake, in
+		/// descending order of approval stake, starting at `start`, or from the top if `start` is
+		/// `None`.
+		///
+		/// Also returns the `start` of the next page, the first target not returned yet, or `None`
+		/// if this was the last one.
+		fn target_snapshot(
+			page_size: u32,
+			start: Option<AccountId>,
+		) -> (Vec<(AccountId, Balance)>, Option<AccountId>);
+
+		/// Returns up to `limit` targets whose stored approval stake differs from the one
+		/// recomputed from the staking system, as `(target, stored, recomputed)`.
+		///
+		/// Returns an empty list if all approvals are consistent.
+		fn inconsistent_targets(limit: u32) -> Vec<(AccountId, Balance, Balance)>;
+	}
+}
@@ -0,0 +1,144 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Benchmarks for the stake tracker pallet.
+
+use super::*;
+use crate::Pallet;
+use frame_benchmarking::v2::*;
+use frame_support::traits::EnsureOrigin;
+use frame_system::RawOrigin;
+use sp_std::vec;
+
+const SEED: u32 = 0;
+
+/// The approval stake the targets are moved to, far from the score they are created with, so
+/// that they change bag in [`Config::TargetList`].
+fn large_approval<T: Config>() -> BalanceOf<T> {
+	BalanceOf::<T>::max_value() / 2u32.into()
+}
+
+/// Creates the target `index` in [`Config::TargetList`], with an approval stake of `approval`
+/// and a score of one.
+fn create_target<T: Config>(index: u32, approval: BalanceOf<T>) -> T::AccountId {
+	let target: T::AccountId = account("target", index, SEED);
+	let approval = Approval { own: Zero::zero(), nominators: approval };
+	ApprovalStake::<T>::insert(Pallet::<T>::shard_of(&target), &target, approval);
+	T::TargetList::on_insert(target.clone(), 1u32.into()).expect("target is new; qed");
+	target
+}
+
+/// Bonds the voter `index` in [`Config::Staking`], making it nominate `targets`, and makes sure
+/// it is in [`Config::VoterList`], with a score of one.
+fn create_voter<T: Config>(
+	index: u32,
+	targets: Vec<T::AccountId>,
+) -> Result<(T::AccountId, BalanceOf<T>), BenchmarkError> {
+	let voter: T::AccountId = account("voter", index, SEED);
+	let stake = T::Staking::minimum_nominator_bond().max(T::Currency::minimum_balance());
+	T::Currency::make_free_balance_be(&voter, stake.saturating_mul(2u32.into()));
+	T::Staking::bond(&voter, stake, &voter)?;
+	T::Staking::nominate(&voter, targets)?;
+	if T::VoterList::contains(&voter) {
+		let _ = T::VoterList::on_update(&voter, 1u32.into());
+	} else {
+		let _ = T::VoterList::on_insert(voter.clone(), 1u32.into());
+	}
+	Ok((voter, stake))
+}
+
+#[benchmarks]
+mod benchmarks {
+	use super::*;
+
+	#[benchmark]
+	fn reconcile_approval() {
+		let target = create_target::<T>(0, large_approval::<T>());
+		PendingApprovalReconciliation::<T>::put(BoundedVec::truncate_from(vec![target.clone()]));
+
+		#[block]
+		{
+			Pallet::<T>::reconcile_pending(Weight::MAX);
+		}
+
+		assert_eq!(T::TargetList::get_score(&target), Ok(large_approval::<T>()));
+	}
+
+	#[benchmark]
+	fn force_reconcile_target(v: Linear<0, 1000>) -> Result<(), BenchmarkError> {
+		let target = create_target::<T>(0, Zero::zero());
+		let mut backing = BalanceOf::<T>::zero();
+		for i in 0..v {
+			let (_, stake) = create_voter::<T>(i, vec![target.clone()])?;
+			backing = backing.saturating_add(stake);
+		}
+		// forget the backing, in case the staking system reported the voters to this pallet.
+		ApprovalStake::<T>::insert(Pallet::<T>::shard_of(&target), &target, Approval::default());
+		NominatorCount::<T>::remove(&target);
+		let origin =
+			T::ReconcileOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, target.clone());
+
+		assert_eq!(NominatorCount::<T>::get(&target), v);
+		assert!(Pallet::<T>::approval(&target).unwrap_or_default().nominators >= backing);
+		Ok(())
+	}
+
+	#[benchmark]
+	fn force_set_approval() {
+		let target = create_target::<T>(0, Zero::zero());
+		// the target is the last one out of a full queue.
+		let queue = (1..T::MaxPendingReconciliations::get())
+			.map(|i| create_target::<T>(i, Zero::zero()))
+			.chain(Some(target.clone()))
+			.collect::<Vec<_>>();
+		PendingApprovalReconciliation::<T>::put(BoundedVec::truncate_from(queue));
+
+		#[extrinsic_call]
+		_(RawOrigin::Root, target.clone(), large_approval::<T>());
+
+		assert_eq!(Pallet::<T>::approval_stake(&target), Some(large_approval::<T>()));
+		assert_eq!(T::TargetList::get_score(&target), Ok(large_approval::<T>()));
+	}
+
+	#[benchmark]
+	fn flush_coalesced(n: Linear<0, 1000>) -> Result<(), BenchmarkError> {
+		for i in 0..n {
+			let target = create_target::<T>(i, large_approval::<T>());
+			let (voter, _) = create_voter::<T>(i, vec![target.clone()])?;
+			CoalescedTargets::<T>::insert(target, ());
+			CoalescedVoters::<T>::insert(voter, ());
+		}
+
+		#[block]
+		{
+			Pallet::<T>::flush_coalesced();
+		}
+
+		assert_eq!(CoalescedTargets::<T>::count(), 0);
+		assert_eq!(CoalescedVoters::<T>::count(), 0);
+		Ok(())
+	}
+
+	impl_benchmark_test_suite!(
+		Pallet,
+		crate::mock::ExtBuilder::default().build(),
+		crate::mock::Test
+	);
+}
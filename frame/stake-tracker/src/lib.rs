@@ -0,0 +1,2095 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Stake Tracker Pallet
+//!
+//! The stake-tracker pallet listens to staking events through the [`OnStakingUpdate`] interface
+//! and keeps a sorted list of voters ([`Config::VoterList`]) and targets ([`Config::TargetList`])
+//! up to date with the state of [`Config::Staking`].
+//!
+//! ## Overview
+//!
+//! * Every validator and nominator is a *voter*. Voters are scored by their active stake, converted
+//!   to a [`Config::Score`], e.g. a [`VoteWeight`], through the staking system's currency-to-vote
+//!   conversion.
+//! * Every validator is a *target*. Targets are scored by their *approval stake*, i.e. their own
+//!   stake plus the stake of all the nominators backing them, where the stake is either the active
+//!   or the total one, see [`Config::TargetStakeKind`]. The approval stake of each target is kept
+//!   in [`ApprovalStake`], with both parts kept apart (see [`Approval`]), and the number of
+//!   nominators backing it in [`NominatorCount`].
+//!
+//! [`ApprovalStake`] is split into [`Config::ApprovalStakeShards`] shards, each account being
+//! routed to one of them by the hash of its encoding (see [`Pallet::shard_of`]). This allows
+//! maintenance sweeps over the approvals to be paginated per shard on chains with huge validator
+//! sets. Use [`Pallet::approval_stake`] to read the approval of a single account.
+//!
+//! The pallet does not read staking storage directly, it relies on [`Config::Staking`] to learn
+//! about the current stake and status of an account, and on the caller of the
+//! [`OnStakingUpdate`] hooks to pass any pre-action data that can no longer be fetched.
+//!
+//! Each change to an approval stake emits [`Event::ApprovalStakeUpdated`], tagged with the
+//! [`ApprovalChangeReason`] that caused it. Whenever the approval stake of a target drops below
+//! [`Config::MinApprovalThreshold`], [`Event::ApprovalBelowThreshold`] is emitted as well.
+//! Other pallets can react to these changes through [`Config::OnApprovalChange`].
+//!
+//! ## Voters
+//!
+//! Runtimes that only need the approvals and [`Config::TargetList`] can unset
+//! [`Config::TrackVoters`], in which case [`Config::VoterList`] is never touched and the voters
+//! whose stake is counted are only remembered in [`UntrackedVoters`]. Approvals are then never
+//! recomputed from the voters, hence [`Config::RecomputeApprovalsEachEra`] can't be set.
+//!
+//! Either way, a validator keeps its [`ApprovalStake`] entry while it is not validating, so that
+//! the backing of its nominators is not lost in the meantime and does not have to be recomputed
+//! from all voters once it validates again.
+//!
+//! ## Eviction
+//!
+//! If [`Config::MaxTargets`] is set, at most that many targets are kept in [`Config::TargetList`].
+//! The lowest-scored targets beyond that are *evicted*: they are kept in [`Config::EvictedList`]
+//! and their approval stake is still tracked, so that they can be promoted back once they outscore
+//! a retained target. To avoid thrashing around the boundary, an evicted target replaces the lowest
+//! retained one only once its approval stake exceeds it by more than [`Config::EvictionMargin`],
+//! and the same holds for a retained target falling below the highest evicted one.
+//!
+//! ## Reordering
+//!
+//! On busy chains, tiny fluctuations of the approval stake would keep on moving targets around
+//! [`Config::TargetList`]. If [`Config::ReorderThreshold`] is set, a target is only reordered
+//! when its approval stake moves away from its score in the list by more than that fraction of
+//! the score. Smaller changes are still tracked in [`ApprovalStake`], but the target is queued
+//! in [`PendingApprovalReconciliation`] and reordered later on. The queue is drained in FIFO order
+//! from `on_idle`, within the remaining weight of the block, and all at once from `on_initialize`
+//! while an election is ongoing. Once [`Config::MaxPendingReconciliations`] targets are queued,
+//! further targets are reordered right away.
+//!
+//! ## Coalescing
+//!
+//! Staking may report several stake updates for the same account within a block, e.g. when it
+//! bonds and then bonds extra. If [`Config::CoalesceStakeUpdates`] is set, the approvals are still
+//! updated right away, but the list updates caused by [`OnStakingUpdate::on_stake_update`] are
+//! only recorded in [`CoalescedVoters`] and [`CoalescedTargets`], and applied once per account
+//! from `on_finalize`. The lists are then the same at the end of the block as if each update had
+//! been applied right away. Each map holds at most [`Config::MaxCoalesced`] accounts, the updates
+//! of further accounts are applied right away, so that the weight reserved for `on_finalize` in
+//! `on_initialize` is bounded.
+//!
+//! ## Genesis
+//!
+//! The lists and approvals can be seeded at genesis from a set of stakers known to
+//! [`Config::Staking`]. By default, this happens eagerly while building the genesis state. If
+//! [`GenesisConfig::lazy`] is set, the stakers are instead queued in [`LazyGenesisQueue`] and
+//! seeded in batches of [`Config::LazyGenesisBatch`] from `on_initialize`, starting at the first
+//! block. Hooks fired for a staker that is still queued are ignored, since seeding always reads
+//! the latest state from [`Config::Staking`].
+//!
+//! ## Per-era recompute
+//!
+//! If [`Config::RecomputeApprovalsEachEra`] is set, all approvals are recomputed from scratch at
+//! the start of each era, eliminating any drift accumulated over the previous one. Every voter is
+//! queued in [`LazyGenesisQueue`] and the approvals are cleared, both in pages of
+//! [`Config::LazyGenesisBatch`] per block, see [`RecomputeProgress`]. The voters are then
+//! reseeded in batches just like a lazy genesis. Stakers touched by a hook in the meantime are
+//! queued as well. An election that starts before all voters are reseeded sees partial
+//! approvals, hence eras must leave enough blocks for the recompute to finish.
+//!
+//! ## Snapshots
+//!
+//! The whole [`Config::TargetList`] can be exported with the approval stake of each target, one
+//! page at a time, through [`Pallet::target_snapshot`], which backs the `StakeTrackerApi` runtime
+//! API of crate `pallet-stake-tracker-runtime-api`. The top targets with a minimum approval
+//! stake can be fetched through [`Pallet::eligible_targets`]. Targets whose approval stake
+//! drifted from [`Config::Staking`] can be listed through [`Pallet::inconsistent_targets`], for
+//! monitoring, and fixed one at a time by [`Config::ReconcileOrigin`] through
+//! [`Pallet::force_reconcile_target`]. Root can also set an approval stake directly through
+//! [`Pallet::force_set_approval`].
+//!
+//! Targets that [`Config::TargetList`] left out of place, e.g. after a migration, can be moved
+//! back through [`Pallet::rebag_targets`].
+//!
+//! Every [`Config::ConsistencyReportInterval`] blocks, up to [`Config::ConsistencyReportSize`]
+//! targets are checked from `on_finalize`, picking up where the previous report stopped, and
+//! [`Event::ConsistencyReport`] tells how many of them have a score in [`Config::TargetList`]
+//! that diverged from their approval stake.
+//!
+//! ## Fuzzing
+//!
+//! A fuzzer checking the consistency of the lists and approvals is provided in crate
+//! `pallet-stake-tracker-fuzzer`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+pub use weights::WeightInfo;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+pub mod migrations;
+#[cfg(any(test, feature = "fuzz"))]
+pub mod mock;
+#[cfg(test)]
+mod tests;
+pub mod weights;
+
+use codec::{Decode, Encode, FullCodec, MaxEncodedLen};
+use frame_election_provider_support::{ScoreProvider, SortedListProvider, VoteWeight};
+use frame_support::{
+	defensive,
+	traits::{Currency, Defensive, Get},
+	weights::Weight,
+	BoundedVec,
+};
+use scale_info::TypeInfo;
+use sp_runtime::{
+	traits::{AtLeast32BitUnsigned, Bounded, UniqueSaturatedFrom, Zero},
+	Perbill, RuntimeDebug, Saturating,
+};
+use sp_staking::{
+	currency_to_vote::CurrencyToVote, EraIndex, OnStakingUpdate, Stake, StakerStatus,
+	StakingInterface,
+};
+use sp_std::{boxed::Box, collections::btree_map::BTreeMap, fmt::Debug, vec::Vec};
+
+/// The logging target of this pallet.
+pub const LOG_TARGET: &str = "runtime::stake-tracker";
+
+/// The number of low bits of a voter score used to break ties, see [`Pallet::voter_score`].
+pub const TIE_BREAK_BITS: u32 = 16;
+
+#[macro_export]
+macro_rules! log {
+	($level:tt, $patter:expr $(, $values:expr)* $(,)?) => {
+		log::$level!(
+			target: $crate::LOG_TARGET,
+			concat!("[{:?}] 📚 ", $patter), <frame_system::Pallet<T>>::block_number() $(, $values)*
+		)
+	};
+}
+
+/// The balance type of this pallet, as defined by the staking system.
+pub type BalanceOf<T> = <<T as Config>::Staking as StakingInterface>::Balance;
+
+/// The approval stake of a target, split by where it comes from.
+#[derive(
+	Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+pub struct Approval<Balance> {
+	/// The stake of the target itself, while it is a validator.
+	pub own: Balance,
+	/// The stake of all the nominators backing the target.
+	pub nominators: Balance,
+}
+
+impl<Balance: Saturating + Copy> Approval<Balance> {
+	/// The total approval stake, used to score the target.
+	pub fn total(&self) -> Balance {
+		self.own.saturating_add(self.nominators)
+	}
+}
+
+/// A step of recomputing all approvals, see [`Config::RecomputeApprovalsEachEra`].
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum RecomputePhase<AccountId> {
+	/// The voters are being queued in [`LazyGenesisQueue`], resuming right after the given voter,
+	/// or from the top of [`Config::VoterList`] if `None`.
+	Queueing(Option<AccountId>),
+	/// [`ApprovalStake`] and [`NominatorCount`] are being cleared.
+	Clearing,
+}
+
+/// The part of the stake of a staker that counts towards approval stakes.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum StakeKind {
+	/// Only the active stake.
+	Active,
+	/// The total stake, including the stake being unbonded.
+	Total,
+}
+
+impl StakeKind {
+	/// This part of `stake`.
+	pub fn of<Balance: Copy>(&self, stake: &Stake<Balance>) -> Balance {
+		match self {
+			StakeKind::Active => stake.active,
+			StakeKind::Total => stake.total,
+		}
+	}
+}
+
+/// The cause of a change to an approval stake.
+///
+/// Every change is caused by exactly one staking hook or operation of this pallet, hence a change
+/// never has more than one reason.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum ApprovalChangeReason {
+	/// The active stake of the target or of one of its nominators changed.
+	StakeUpdate,
+	/// A nominator changed its nominations.
+	NominationChange,
+	/// The target or one of its nominators was slashed.
+	Slash,
+	/// The approval stake was changed directly, rather than through a staking hook.
+	Manual,
+	/// The approval stake was seeded from [`Config::Staking`], at genesis or while being
+	/// recomputed.
+	Migration,
+}
+
+/// Something that reacts to changes of approval stakes, see [`Config::OnApprovalChange`].
+pub trait OnApprovalChange<AccountId, Balance> {
+	/// The approval stake of `who` changed from `old` to `new`. Never called if `old == new`.
+	fn on_approval_change(who: &AccountId, old: Balance, new: Balance);
+}
+
+#[impl_trait_for_tuples::impl_for_tuples(10)]
+impl<AccountId, Balance: Copy> OnApprovalChange<AccountId, Balance> for Tuple {
+	fn on_approval_change(who: &AccountId, old: Balance, new: Balance) {
+		for_tuples!( #( Tuple::on_approval_change(who, old, new); )* );
+	}
+}
+
+/// A type in which voters are scored in [`Config::VoterList`], see [`Config::Score`].
+pub trait VoterScore<Balance>:
+	AtLeast32BitUnsigned + Copy + Default + Debug + FullCodec + MaxEncodedLen + TypeInfo
+{
+	/// Converts `balance` into a score through `C`, or `None` if it exceeds the maximum score.
+	fn from_balance<C: CurrencyToVote<Balance>>(
+		balance: Balance,
+		issuance: Balance,
+	) -> Option<Self>;
+
+	/// Converts `self` back into an approximate balance through `C`.
+	fn to_balance<C: CurrencyToVote<Balance>>(self, issuance: Balance) -> Balance;
+}
+
+/// Scales balances down through `C` to fit in a [`VoteWeight`], as the staking system does.
+impl<Balance: AtLeast32BitUnsigned + Copy> VoterScore<Balance> for VoteWeight {
+	fn from_balance<C: CurrencyToVote<Balance>>(
+		balance: Balance,
+		issuance: Balance,
+	) -> Option<Self> {
+		let max = C::to_currency(VoteWeight::MAX.into(), issuance);
+		(balance <= max).then(|| C::to_vote(balance, issuance))
+	}
+
+	fn to_balance<C: CurrencyToVote<Balance>>(self, issuance: Balance) -> Balance {
+		C::to_currency(self.into(), issuance)
+	}
+}
+
+/// Scores balances as they are, without going through `C`, which would scale them down to a
+/// [`VoteWeight`]. Meant for runtimes whose balances exceed [`VoteWeight::MAX`].
+impl<Balance: AtLeast32BitUnsigned + Copy> VoterScore<Balance> for u128 {
+	fn from_balance<C: CurrencyToVote<Balance>>(
+		balance: Balance,
+		_issuance: Balance,
+	) -> Option<Self> {
+		balance.try_into().ok()
+	}
+
+	fn to_balance<C: CurrencyToVote<Balance>>(self, _issuance: Balance) -> Balance {
+		Balance::unique_saturated_from(self)
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
+	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// The currency of the staking system, used to fetch the total issuance.
+		type Currency: Currency<Self::AccountId, Balance = BalanceOf<Self>>;
+
+		/// The staking system this pallet tracks.
+		type Staking: StakingInterface<AccountId = Self::AccountId>;
+
+		/// The type in which voters are scored, usually [`VoteWeight`]. Runtimes whose balances
+		/// exceed [`VoteWeight::MAX`] can use `u128` instead, see [`VoterScore`].
+		///
+		/// Targets are always scored in balances, hence this only applies to
+		/// [`Config::VoterList`].
+		type Score: VoterScore<BalanceOf<Self>>;
+
+		/// The conversion of balances to [`Config::Score`], in which voters are scored. Use
+		/// [`StakingCurrencyToVote`] to convert them as the staking system does.
+		///
+		/// Balances beyond the maximum score are clamped to it, whatever the conversion.
+		type CurrencyToVote: sp_staking::currency_to_vote::CurrencyToVote<BalanceOf<Self>>;
+
+		/// The minimum score any non-zero balance is converted to, so that no bonded voter is
+		/// scored zero. Should be at least 1.
+		#[pallet::constant]
+		type MinVoteScore: Get<Self::Score>;
+
+		/// Something that provides a sorted list of voters, scored by their active stake.
+		type VoterList: SortedListProvider<Self::AccountId, Score = Self::Score>;
+
+		/// Something that provides a sorted list of targets, scored by their approval stake.
+		type TargetList: SortedListProvider<Self::AccountId, Score = BalanceOf<Self>>;
+
+		/// Something that provides a sorted list of the targets kept out of
+		/// [`Config::TargetList`] because of [`Config::MaxTargets`], scored by their approval
+		/// stake. Never touched unless [`Config::MaxTargets`] is set.
+		type EvictedList: SortedListProvider<Self::AccountId, Score = BalanceOf<Self>>;
+
+		/// The part of the stake of each staker that counts towards approval stakes. Voters are
+		/// always scored by their active stake.
+		///
+		/// Changing this value changes every approval stake, hence it must not be changed without
+		/// recomputing [`ApprovalStake`].
+		#[pallet::constant]
+		type TargetStakeKind: Get<StakeKind>;
+
+		/// Whether to maintain [`Config::VoterList`] at all. Set it to `false` in runtimes that
+		/// only need the approvals and [`Config::TargetList`], see the [crate] docs.
+		///
+		/// Must be `true` if [`Config::RecomputeApprovalsEachEra`] is set.
+		#[pallet::constant]
+		type TrackVoters: Get<bool>;
+
+		/// Whether to defer the list updates caused by stake updates to the end of the block, see
+		/// the [crate] docs.
+		#[pallet::constant]
+		type CoalesceStakeUpdates: Get<bool>;
+
+		/// The maximum number of voters, and of targets, whose list updates are deferred to the
+		/// end of a block, see [`Config::CoalesceStakeUpdates`]. Further updates are applied right
+		/// away.
+		///
+		/// Weight for this many updates is reserved in every block while coalescing is enabled.
+		#[pallet::constant]
+		type MaxCoalesced: Get<u32>;
+
+		/// Whether to break ties between voters of equal active stake, see
+		/// [`Pallet::voter_score`].
+		///
+		/// Changing this value changes the score of every voter, hence it must not be changed
+		/// without rescoring [`Config::VoterList`].
+		#[pallet::constant]
+		type VoterTieBreak: Get<bool>;
+
+		/// The maximum number of stakers seeded per block when genesis is lazy. Also the number of
+		/// voters queued, and of approvals cleared, per block while approvals are recomputed.
+		#[pallet::constant]
+		type LazyGenesisBatch: Get<u32>;
+
+		/// The number of shards [`ApprovalStake`] is split into. A value of 0 or 1 means no
+		/// sharding.
+		///
+		/// Changing this value re-routes accounts to different shards, hence it must not be
+		/// changed without migrating [`ApprovalStake`].
+		#[pallet::constant]
+		type ApprovalStakeShards: Get<u32>;
+
+		/// The maximum number of targets kept in [`Config::TargetList`], or `None` to never
+		/// evict any target.
+		#[pallet::constant]
+		type MaxTargets: Get<Option<u32>>;
+
+		/// The approval stake by which an evicted target has to outscore a retained one before
+		/// they are swapped.
+		#[pallet::constant]
+		type EvictionMargin: Get<BalanceOf<Self>>;
+
+		/// Whether to recompute all approvals from [`Config::Staking`] at the start of each era.
+		#[pallet::constant]
+		type RecomputeApprovalsEachEra: Get<bool>;
+
+		/// The approval stake below which a target is reported through
+		/// [`Event::ApprovalBelowThreshold`].
+		#[pallet::constant]
+		type MinApprovalThreshold: Get<BalanceOf<Self>>;
+
+		/// Notified of every change of an approval stake in [`ApprovalStake`], including entries
+		/// being removed, which are seen as dropping to zero.
+		type OnApprovalChange: OnApprovalChange<Self::AccountId, BalanceOf<Self>>;
+
+		/// The fraction of its score in [`Config::TargetList`] by which the approval stake of a
+		/// target has to change before it is reordered right away. Zero reorders on every change.
+		#[pallet::constant]
+		type ReorderThreshold: Get<Perbill>;
+
+		/// The fraction of the larger of the two by which the approval stake of a target may
+		/// differ from its exposure in the active era, as reported by
+		/// [`StakingInterface::active_era_exposure`], before `try_state` fails.
+		///
+		/// Exposures are fixed at the start of an era while approvals follow every stake update,
+		/// so the two are expected to diverge as the era goes on.
+		#[pallet::constant]
+		type ExposureTolerance: Get<Perbill>;
+
+		/// The maximum number of nominations processed by
+		/// [`OnStakingUpdate::on_nominator_update`], which bounds its cost. Further nominations
+		/// are ignored.
+		///
+		/// Should be at least the maximum number of nominations of [`Config::Staking`], so that
+		/// only malformed updates are truncated.
+		#[pallet::constant]
+		type MaxApprovalTargets: Get<u32>;
+
+		/// The maximum number of targets queued in [`PendingApprovalReconciliation`].
+		#[pallet::constant]
+		type MaxPendingReconciliations: Get<u32>;
+
+		/// The number of blocks between two [`Event::ConsistencyReport`]s. Set it to the maximum
+		/// block number to effectively disable the reports.
+		#[pallet::constant]
+		type ConsistencyReportInterval: Get<BlockNumberFor<Self>>;
+
+		/// The maximum number of targets checked for each [`Event::ConsistencyReport`].
+		#[pallet::constant]
+		type ConsistencyReportSize: Get<u32>;
+
+		/// The origin allowed to recompute the approval stake of a target through
+		/// [`Pallet::force_reconcile_target`].
+		type ReconcileOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Weight information for the operations of this pallet.
+		type WeightInfo: WeightInfo;
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The account is neither a validator nor has an approval stake.
+		NotATarget,
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The approval stake of a target was updated.
+		ApprovalStakeUpdated {
+			who: T::AccountId,
+			approval: Approval<BalanceOf<T>>,
+			reason: ApprovalChangeReason,
+		},
+		/// The approval stake of a target dropped below [`Config::MinApprovalThreshold`].
+		///
+		/// Only emitted when crossing the threshold, not for further updates below it.
+		ApprovalBelowThreshold { who: T::AccountId, approval: BalanceOf<T> },
+		/// Out of the `checked` targets, `diverged` have a score in [`Config::TargetList`] that
+		/// differs from their approval stake, see [`Config::ConsistencyReportInterval`].
+		ConsistencyReport { checked: u32, diverged: u32 },
+	}
+
+	/// The approval stake of each target, keyed by the shard of the target first.
+	///
+	/// This is the stake of the target itself and the stake of all nominators backing it, whose
+	/// sum is the score of the target. An entry exists for every validator that has been backed,
+	/// even if it is not (yet) part of [`Config::TargetList`]. Nominations of accounts that are not
+	/// validators are not counted. When a validator stops validating, only its own stake is
+	/// removed from its entry, the backing of its nominators keeps on being tracked.
+	///
+	/// An account is always stored in the shard returned by [`Pallet::shard_of`].
+	// Hasher: Twox safe since `AccountId` is a secure hash.
+	#[pallet::storage]
+	pub type ApprovalStake<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		u32,
+		Twox64Concat,
+		T::AccountId,
+		Approval<BalanceOf<T>>,
+		OptionQuery,
+	>;
+
+	/// The number of nominators backing each target, kept alongside [`ApprovalStake`].
+	///
+	/// Nominators are counted once per target, however many times their stake changes. An entry is
+	/// removed once its count drops to zero.
+	// Hasher: Twox safe since `AccountId` is a secure hash.
+	#[pallet::storage]
+	pub type NominatorCount<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, u32, ValueQuery>;
+
+	/// The voters whose stake is counted in the approvals, only kept if [`Config::TrackVoters`] is
+	/// unset, since [`Config::VoterList`] tells otherwise.
+	// Hasher: Twox safe since `AccountId` is a secure hash.
+	#[pallet::storage]
+	pub type UntrackedVoters<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, ()>;
+
+	/// Stakers that are still waiting to be seeded from [`Config::Staking`], either because
+	/// genesis is lazy or because approvals are being recomputed.
+	// Hasher: Twox safe since `AccountId` is a secure hash.
+	#[pallet::storage]
+	pub type LazyGenesisQueue<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, ()>;
+
+	/// Voters whose score in [`Config::VoterList`] is yet to be updated at the end of the block,
+	/// see [`Config::CoalesceStakeUpdates`]. Always empty between blocks.
+	// Hasher: Twox safe since `AccountId` is a secure hash.
+	#[pallet::storage]
+	pub type CoalescedVoters<T: Config> = CountedStorageMap<_, Twox64Concat, T::AccountId, ()>;
+
+	/// Targets whose score in [`Config::TargetList`] is yet to be updated at the end of the
+	/// block, see [`Config::CoalesceStakeUpdates`]. Always empty between blocks.
+	// Hasher: Twox safe since `AccountId` is a secure hash.
+	#[pallet::storage]
+	pub type CoalescedTargets<T: Config> = CountedStorageMap<_, Twox64Concat, T::AccountId, ()>;
+
+	/// Targets whose approval stake changed by less than [`Config::ReorderThreshold`] and which
+	/// are yet to be reordered in [`Config::TargetList`], oldest first.
+	#[pallet::storage]
+	pub type PendingApprovalReconciliation<T: Config> =
+		StorageValue<_, BoundedVec<T::AccountId, T::MaxPendingReconciliations>, ValueQuery>;
+
+	/// The last era for which approvals were recomputed, see
+	/// [`Config::RecomputeApprovalsEachEra`].
+	#[pallet::storage]
+	pub type LastRecomputedEra<T: Config> = StorageValue<_, EraIndex, OptionQuery>;
+
+	/// The current step of recomputing the approvals for [`LastRecomputedEra`], or `None` once
+	/// all voters are queued and all approvals are cleared.
+	#[pallet::storage]
+	pub type RecomputeProgress<T: Config> =
+		StorageValue<_, RecomputePhase<T::AccountId>, OptionQuery>;
+
+	/// The last target walked by the previous call to [`Pallet::rebag_targets`], or `None` to
+	/// start from the top of [`Config::TargetList`].
+	#[pallet::storage]
+	pub type RebagCursor<T: Config> = StorageValue<_, T::AccountId, OptionQuery>;
+
+	/// The last target checked by the previous [`Event::ConsistencyReport`], or `None` to start
+	/// from the top of [`Config::TargetList`].
+	#[pallet::storage]
+	pub type ConsistencyCursor<T: Config> = StorageValue<_, T::AccountId, OptionQuery>;
+
+	#[pallet::genesis_config]
+	#[derive(frame_support::DefaultNoBound)]
+	pub struct GenesisConfig<T: Config> {
+		/// The stakers to seed the lists and approvals from. Their stake and status are read from
+		/// [`Config::Staking`], which must already know about them.
+		pub stakers: Vec<T::AccountId>,
+		/// Whether to defer seeding `stakers` to `on_initialize`, starting at the first block.
+		pub lazy: bool,
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+		fn build(&self) {
+			for who in &self.stakers {
+				if self.lazy {
+					LazyGenesisQueue::<T>::insert(who, ());
+				} else {
+					Pallet::<T>::seed(who);
+				}
+			}
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Recomputes the approval stake of `target` from [`Config::Staking`] and writes it to
+		/// [`ApprovalStake`] and [`Config::TargetList`], if it drifted.
+		///
+		/// Succeeds without changing anything if the stored approval stake is already correct.
+		/// Without [`Config::TrackVoters`], only the own stake of `target` is recomputed.
+		///
+		/// The dispatch origin must be [`Config::ReconcileOrigin`].
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::force_reconcile_target(T::VoterList::count()))]
+		pub fn force_reconcile_target(
+			origin: OriginFor<T>,
+			target: T::AccountId,
+		) -> DispatchResult {
+			T::ReconcileOrigin::ensure_origin(origin)?;
+
+			let own =
+				T::Staking::stake(&target).map(|s| Self::approval_part(&s)).unwrap_or_default();
+			let (approval, count) = Self::compute_approval(&target, own);
+			if Self::approval(&target).unwrap_or_default() == approval &&
+				NominatorCount::<T>::get(&target) == count
+			{
+				return Ok(())
+			}
+
+			log!(info, "reconciling the approval stake of {:?} to {:?}", target, approval);
+			Self::set_nominator_count(&target, count);
+			Self::force_approval(&target, approval);
+			Ok(())
+		}
+
+		/// Sets the approval stake of `target` to `value`, e.g. to a known-correct value while
+		/// recovering from an incident, and updates its score in [`Config::TargetList`].
+		///
+		/// The own stake of `target` is kept, unless it exceeds `value`, and the rest of `value` is
+		/// attributed to its nominators. Fails with [`Error::NotATarget`] unless `target` is a
+		/// validator or already has an approval stake.
+		///
+		/// Can only be called by root.
+		#[pallet::call_index(1)]
+		#[pallet::weight(T::WeightInfo::force_set_approval())]
+		pub fn force_set_approval(
+			origin: OriginFor<T>,
+			target: T::AccountId,
+			value: BalanceOf<T>,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(Self::is_backable(&target), Error::<T>::NotATarget);
+
+			let own = Self::approval(&target).map(|a| a.own).unwrap_or_default().min(value);
+			let approval = Approval { own, nominators: value.saturating_sub(own) };
+			log!(warn, "forcing the approval stake of {:?} to {:?}", target, approval);
+			Self::force_approval(&target, approval);
+			Ok(())
+		}
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			let mut weight = Self::maybe_start_recompute();
+			weight.saturating_accrue(Self::do_recompute_step(T::LazyGenesisBatch::get()));
+			if Self::is_report_due(n) {
+				// the cursor, then the score and approval of each checked target.
+				let reads = T::ConsistencyReportSize::get().saturating_mul(2).saturating_add(1);
+				weight.saturating_accrue(T::DbWeight::get().reads_writes(reads.into(), 2));
+			}
+
+			let election_ongoing = T::Staking::election_ongoing();
+			weight.saturating_accrue(T::DbWeight::get().reads(1));
+			if election_ongoing {
+				// the election must see the actual approvals, the queue is bounded so this is too.
+				let max = T::WeightInfo::reconcile_approval()
+					.saturating_mul(T::MaxPendingReconciliations::get().into())
+					.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+				weight.saturating_accrue(Self::reconcile_pending(max));
+			}
+
+			weight.saturating_accrue(Self::do_lazy_genesis(T::LazyGenesisBatch::get()));
+			if T::CoalesceStakeUpdates::get() {
+				// for `on_finalize`, which can't know how many updates will be coalesced.
+				weight.saturating_accrue(T::WeightInfo::flush_coalesced(T::MaxCoalesced::get()));
+			}
+			if election_ongoing && LazyGenesisQueue::<T>::iter_keys().next().is_some() {
+				log!(warn, "election started before all stakers were seeded");
+			}
+			weight
+		}
+
+		fn on_idle(_n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			Self::reconcile_pending(remaining_weight)
+		}
+
+		fn on_finalize(n: BlockNumberFor<T>) {
+			Self::flush_coalesced();
+			if Self::is_report_due(n) {
+				Self::report_consistency();
+			}
+		}
+
+		fn integrity_test() {
+			assert!(
+				T::TrackVoters::get() || !T::RecomputeApprovalsEachEra::get(),
+				"approvals can only be recomputed from the voter list"
+			);
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_: BlockNumberFor<T>) -> Result<(), sp_runtime::TryRuntimeError> {
+			Self::ensure_consistency()?;
+			Self::ensure_exposure_consistency().map_err(Into::into)
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// One page of at most `page_size` targets of [`Config::TargetList`] with their approval
+	/// stake, in the order of the list, starting at `start`, or from the top if `start` is `None`.
+	/// Also returns the `start` of the next page, if any.
+	///
+	/// The `start` of a page is the first target that was not returned yet, so that no target is
+	/// skipped or returned twice if any of the returned ones leaves the list. Only if `start`
+	/// itself left the list, its position is lost and the page starts from the top again,
+	/// returning some targets twice.
+	pub fn target_snapshot(
+		page_size: u32,
+		start: Option<T::AccountId>,
+	) -> (Vec<(T::AccountId, BalanceOf<T>)>, Option<T::AccountId>) {
+		if page_size == 0 {
+			return (Vec::new(), start)
+		}
+
+		let targets: Box<dyn Iterator<Item = T::AccountId>> = match start {
+			Some(start) if T::TargetList::contains(&start) => Box::new(
+				sp_std::iter::once(start.clone())
+					.chain(T::TargetList::iter_from(&start).into_iter().flatten()),
+			),
+			Some(start) => {
+				log!(warn, "snapshot cursor {:?} is not a target anymore, restarting", start);
+				T::TargetList::iter()
+			},
+			None => T::TargetList::iter(),
+		};
+
+		// one more target tells whether there is a next page, and where it starts.
+		let mut page = targets
+			.take(page_size.saturating_add(1) as usize)
+			.map(|t| {
+				let approval = Self::approval_stake(&t).unwrap_or_default();
+				(t, approval)
+			})
+			.collect::<Vec<_>>();
+		let next = if page.len() > page_size as usize { page.pop().map(|(t, _)| t) } else { None };
+
+		(page, next)
+	}
+
+	/// Up to `limit` targets of [`Config::TargetList`] with their approval stake, in the order of
+	/// the list, leaving out those whose approval stake is below `min_approval`, e.g. for an
+	/// election provider to leave them out of its snapshot.
+	///
+	/// The list is iterated from the top and the iteration stops at the first target scored below
+	/// `min_approval`, hence it never reads past the eligible targets. Note that a list might only
+	/// be sorted on a best-effort basis, e.g. a bags-list does not sort the targets within a bag,
+	/// in which case eligible targets sharing a bag with that first one are left out too.
+	pub fn eligible_targets(
+		min_approval: BalanceOf<T>,
+		limit: u32,
+	) -> Vec<(T::AccountId, BalanceOf<T>)> {
+		T::TargetList::iter()
+			.take_while(|t| T::TargetList::get_score(t).is_ok_and(|s| s >= min_approval))
+			.map(|t| {
+				let approval = Self::approval_stake(&t).unwrap_or_default();
+				(t, approval)
+			})
+			// a target whose reorder is pending might be scored above its approval stake.
+			.filter(|(_, approval)| *approval >= min_approval)
+			.take(limit as usize)
+			.collect()
+	}
+
+	/// All the approval stakes of [`ApprovalStake`], evicted targets and validators that are not
+	/// targets (yet) included, sorted by descending approval stake, then by account id.
+	///
+	/// Unlike the iteration order of [`ApprovalStake`], which depends on the hashes of its keys,
+	/// this order only depends on the approvals, so that two nodes on the same state produce
+	/// byte-identical snapshots. This collects the whole map, hence it is only available outside
+	/// of the runtime, e.g. to offchain workers and tests.
+	#[cfg(feature = "std")]
+	pub fn approval_snapshot_sorted() -> Vec<(T::AccountId, BalanceOf<T>)> {
+		let mut snapshot = ApprovalStake::<T>::iter()
+			.map(|(_, who, approval)| (who, approval.total()))
+			.collect::<Vec<_>>();
+		snapshot.sort_by(|(a, a_approval), (b, b_approval)| {
+			b_approval.cmp(a_approval).then_with(|| a.cmp(b))
+		});
+		snapshot
+	}
+
+	/// The median approval stake of the targets in [`Config::TargetList`], each target counting
+	/// once whatever its approval, or `None` if there are no targets. With an even number of
+	/// targets, this is the average of the two middle approvals, rounded down.
+	///
+	/// This collects and sorts the approvals of all targets, i.e. costs `O(n log n)` time and
+	/// `O(n)` memory for `n` targets, hence it is only available outside of the runtime, e.g. to
+	/// offchain workers and tests.
+	#[cfg(feature = "std")]
+	pub fn approval_median() -> Option<BalanceOf<T>> {
+		let mut approvals = T::TargetList::iter()
+			.map(|target| Self::approval_stake(&target).unwrap_or_default())
+			.collect::<Vec<_>>();
+		approvals.sort();
+
+		let middle = approvals.len() / 2;
+		match approvals.len() {
+			0 => None,
+			len if len % 2 == 1 => Some(approvals[middle]),
+			_ => {
+				let (lower, upper) = (approvals[middle - 1], approvals[middle]);
+				// halving each first, as their sum might overflow.
+				let two = BalanceOf::<T>::from(2u32);
+				Some(lower / two + upper / two + (lower % two + upper % two) / two)
+			},
+		}
+	}
+
+	/// Up to `limit` targets whose stored approval stake differs from the one recomputed from
+	/// [`Config::Staking`], with both of them, retained targets first.
+	///
+	/// This is meant for monitoring, since it goes through every voter and target once.
+	pub fn inconsistent_targets(limit: u32) -> Vec<(T::AccountId, BalanceOf<T>, BalanceOf<T>)> {
+		let backings = Self::compute_backings();
+		T::TargetList::iter()
+			.chain(T::EvictedList::iter())
+			.filter_map(|target| {
+				let own =
+					T::Staking::stake(&target).map(|s| Self::approval_part(&s)).unwrap_or_default();
+				let recomputed = match &backings {
+					Some(backings) =>
+						own.saturating_add(backings.get(&target).copied().unwrap_or_default()),
+					None => Self::compute_approval(&target, own).0.total(),
+				};
+				let stored = Self::approval_stake(&target).unwrap_or_default();
+				(stored != recomputed).then_some((target, stored, recomputed))
+			})
+			.take(limit as usize)
+			.collect()
+	}
+
+	/// Walks up to `max` targets of [`Config::TargetList`], starting after [`RebagCursor`] and
+	/// wrapping around at the end of the list, and moves any that the list left out of place,
+	/// see [`SortedListProvider::rebag`]. Returns the number of targets moved.
+	///
+	/// This is meant for operators to fix the placement of targets after a migration, e.g. one
+	/// changing the bag thresholds of the list.
+	pub fn rebag_targets(max: u32) -> u32 {
+		let targets = match RebagCursor::<T>::get() {
+			Some(cursor) => T::TargetList::iter_from(&cursor).unwrap_or_else(|_| {
+				log!(debug, "rebag cursor {:?} is not a target anymore, restarting", cursor);
+				T::TargetList::iter()
+			}),
+			None => T::TargetList::iter(),
+		};
+		// collected first, since rebagging changes the order of the list.
+		let targets = targets.take(max as usize).collect::<Vec<_>>();
+		match targets.last() {
+			Some(last) if targets.len() == max as usize => RebagCursor::<T>::put(last),
+			_ => RebagCursor::<T>::kill(),
+		}
+
+		let moved = targets
+			.iter()
+			.filter(|t| T::TargetList::rebag(t).defensive_unwrap_or(false))
+			.count() as u32;
+		log!(info, "rebagged {} out of {} walked targets", moved, targets.len());
+		moved
+	}
+
+	/// Whether an [`Event::ConsistencyReport`] is due at block `n`.
+	fn is_report_due(n: BlockNumberFor<T>) -> bool {
+		let interval = T::ConsistencyReportInterval::get();
+		!interval.is_zero() && (n % interval).is_zero()
+	}
+
+	/// Checks the next [`Config::ConsistencyReportSize`] targets of [`Config::TargetList`],
+	/// starting after [`ConsistencyCursor`] and wrapping around at the end of the list, and emits
+	/// [`Event::ConsistencyReport`].
+	///
+	/// Targets in [`PendingApprovalReconciliation`] are not counted as diverged, since they are
+	/// known to be reordered later on.
+	fn report_consistency() {
+		let size = T::ConsistencyReportSize::get();
+		let targets = match ConsistencyCursor::<T>::get() {
+			Some(cursor) => T::TargetList::iter_from(&cursor).unwrap_or_else(|_| {
+				log!(debug, "report cursor {:?} is not a target anymore, restarting", cursor);
+				T::TargetList::iter()
+			}),
+			None => T::TargetList::iter(),
+		};
+		let mut targets = targets.take(size as usize).collect::<Vec<_>>();
+		if targets.is_empty() {
+			// the previous report ended right at the end of the list.
+			targets = T::TargetList::iter().take(size as usize).collect();
+		}
+
+		let pending = PendingApprovalReconciliation::<T>::get();
+		let diverged = targets
+			.iter()
+			.filter(|t| !pending.contains(t))
+			.filter(|t| T::TargetList::get_score(t).ok() != Self::approval_stake(t))
+			.count() as u32;
+		let checked = targets.len() as u32;
+
+		// a short page means the end of the list was reached.
+		match targets.last() {
+			Some(last) if checked == size => ConsistencyCursor::<T>::put(last),
+			_ => ConsistencyCursor::<T>::kill(),
+		}
+		if diverged > 0 {
+			log!(
+				warn,
+				"{} out of {} checked targets diverged from their approval",
+				diverged,
+				checked
+			);
+		}
+		Self::deposit_event(Event::<T>::ConsistencyReport { checked, diverged });
+	}
+
+	/// Checks that the lists and approvals are consistent with each other and with
+	/// [`Config::Staking`]:
+	///
+	/// * the score of each target in [`Config::TargetList`] is its approval stake, unless it is in
+	///   [`PendingApprovalReconciliation`] or [`CoalescedTargets`],
+	/// * each target is a validator, and is not in [`Config::EvictedList`],
+	/// * [`Config::TargetList`] respects [`Config::MaxTargets`],
+	/// * each voter in [`Config::VoterList`] is a validator or a nominator, scored by its active
+	///   stake unless it is in [`CoalescedVoters`]. Idle voters are logged.
+	///
+	/// Accounts still waiting in [`LazyGenesisQueue`] are not checked.
+	#[cfg(any(feature = "std", feature = "try-runtime"))]
+	pub fn ensure_consistency() -> Result<(), &'static str> {
+		use frame_support::ensure;
+
+		let pending = PendingApprovalReconciliation::<T>::get();
+		for target in T::TargetList::iter().filter(|t| !Self::is_pending_genesis(t)) {
+			ensure!(
+				matches!(T::Staking::status(&target), Ok(StakerStatus::Validator)),
+				"target is not a validator"
+			);
+			ensure!(!T::EvictedList::contains(&target), "target is both retained and evicted");
+			if !pending.contains(&target) && !CoalescedTargets::<T>::contains_key(&target) {
+				ensure!(
+					T::TargetList::get_score(&target).ok() == Self::approval_stake(&target),
+					"target score does not match its approval stake"
+				);
+			}
+		}
+		if let Some(max) = T::MaxTargets::get() {
+			ensure!(T::TargetList::count() <= max, "too many targets");
+		}
+
+		for voter in T::VoterList::iter().filter(|v| !Self::is_pending_genesis(v)) {
+			match T::Staking::status(&voter) {
+				Ok(StakerStatus::Validator | StakerStatus::Nominator(_)) => (),
+				Ok(StakerStatus::Idle) => {
+					log!(error, "{:?} is idle but still in the voter list", voter);
+					return Err("voter is idle")
+				},
+				Err(_) => return Err("voter is not bonded"),
+			}
+			let stake = T::Staking::stake(&voter).map_err(|_| "voter is not bonded")?;
+			if CoalescedVoters::<T>::contains_key(&voter) {
+				continue
+			}
+			ensure!(
+				T::VoterList::get_score(&voter).ok() ==
+					Some(Self::voter_score(&voter, stake.active)),
+				"voter score does not match its active stake"
+			);
+		}
+
+		Ok(())
+	}
+
+	/// Checks that the approval stake of each target in [`Config::TargetList`] is within
+	/// [`Config::ExposureTolerance`] of its exposure in the active era, for the targets that
+	/// [`Config::Staking`] reports one for.
+	///
+	/// This is not part of [`Self::ensure_consistency`], as approvals drift away from exposures
+	/// during an era without anything being wrong.
+	#[cfg(any(feature = "std", feature = "try-runtime"))]
+	pub fn ensure_exposure_consistency() -> Result<(), &'static str> {
+		let tolerance = T::ExposureTolerance::get();
+		for target in T::TargetList::iter().filter(|t| !Self::is_pending_genesis(t)) {
+			let Some(exposure) = T::Staking::active_era_exposure(&target) else { continue };
+			let approval = Self::approval_stake(&target).unwrap_or_default();
+			let difference = approval.max(exposure).saturating_sub(approval.min(exposure));
+			if difference > tolerance * approval.max(exposure) {
+				log!(
+					error,
+					"approval {:?} of {:?} diverged from its exposure {:?}",
+					approval,
+					target,
+					exposure
+				);
+				return Err("target approval diverged from its exposure")
+			}
+		}
+		Ok(())
+	}
+
+	/// Converts `balance` into a [`Config::Score`] through [`Config::CurrencyToVote`], clamping it
+	/// to the maximum score, and to [`Config::MinVoteScore`] unless `balance` is zero.
+	pub(crate) fn to_vote(balance: BalanceOf<T>) -> T::Score {
+		let total_issuance = T::Currency::total_issuance();
+		let Some(vote) = T::Score::from_balance::<T::CurrencyToVote>(balance, total_issuance)
+		else {
+			log!(warn, "balance {:?} exceeds the maximum vote weight, clamping it", balance);
+			return T::Score::max_value()
+		};
+		if balance.is_zero() {
+			vote
+		} else {
+			vote.max(T::MinVoteScore::get())
+		}
+	}
+
+	/// Converts a [`Config::Score`], e.g. a score of [`Config::VoterList`], back into an
+	/// approximate balance: the inverse of [`Self::to_vote`].
+	///
+	/// For balances that [`Self::to_vote`] neither clamped nor floored, `from_vote(to_vote(x))` is
+	/// within one scaling unit of `x`, i.e. `from_vote(1)`. Clamped balances come back as the
+	/// balance of the maximum score, and floored ones as the balance of
+	/// [`Config::MinVoteScore`]. Scores with tie-break bits, see [`Self::voter_score`], are off by
+	/// up to `2^TIE_BREAK_BITS` scaling units.
+	pub fn from_vote(score: T::Score) -> BalanceOf<T> {
+		score.to_balance::<T::CurrencyToVote>(T::Currency::total_issuance())
+	}
+
+	/// The score of the voter `who` in [`Config::VoterList`], given its `active` stake.
+	///
+	/// This is `active` converted to a [`Config::Score`], unless [`Config::VoterTieBreak`] is set,
+	/// in which case the lowest [`TIE_BREAK_BITS`] bits of that are replaced by the hash of `who`.
+	/// Voters of equal stake then have distinct scores that are the same on every node, at the
+	/// cost of ordering voters whose stakes are that close arbitrarily. Note that a list only
+	/// orders voters of distinct scores if its bags are fine enough to tell these scores apart.
+	///
+	/// Scores below `2^TIE_BREAK_BITS` are kept as they are, since the low bits are then most of
+	/// the score, and tie-broken scores are still at least [`Config::MinVoteScore`].
+	pub fn voter_score(who: &T::AccountId, active: BalanceOf<T>) -> T::Score {
+		let vote = Self::to_vote(active);
+		if !T::VoterTieBreak::get() || vote < T::Score::from(1u32 << TIE_BREAK_BITS) {
+			return vote
+		}
+		let mask = (1 << TIE_BREAK_BITS) - 1;
+		let hash = sp_io::hashing::blake2_256(&who.encode());
+		let tie_break = u32::from_le_bytes(hash[..4].try_into().expect("32 > 4; qed")) & mask;
+		// the low bits are cleared first, hence adding them back cannot overflow.
+		let score = ((vote >> TIE_BREAK_BITS) << TIE_BREAK_BITS) + tie_break.into();
+		score.max(T::MinVoteScore::get())
+	}
+
+	/// The shard of [`ApprovalStake`] in which the approval of `who` is kept.
+	pub fn shard_of(who: &T::AccountId) -> u32 {
+		let shards = T::ApprovalStakeShards::get().max(1);
+		let hash = u64::from_le_bytes(sp_io::hashing::twox_64(&who.encode()));
+		(hash % shards as u64) as u32
+	}
+
+	/// The approval of `who`, split between its own stake and its nominators', if it was ever
+	/// backed.
+	pub fn approval(who: &T::AccountId) -> Option<Approval<BalanceOf<T>>> {
+		ApprovalStake::<T>::get(Self::shard_of(who), who)
+	}
+
+	/// The number of nominators backing `who`.
+	pub fn nominator_count(who: &T::AccountId) -> u32 {
+		NominatorCount::<T>::get(who)
+	}
+
+	/// The total approval stake of `who`, if it was ever backed.
+	pub fn approval_stake(who: &T::AccountId) -> Option<BalanceOf<T>> {
+		Self::approval(who).map(|a| a.total())
+	}
+
+	/// The sum of all approval stakes kept in `shard`.
+	pub fn shard_approval_stake(shard: u32) -> BalanceOf<T> {
+		ApprovalStake::<T>::iter_prefix_values(shard)
+			.fold(Default::default(), |acc: BalanceOf<T>, a| acc.saturating_add(a.total()))
+	}
+
+	/// The sum of all approval stakes, across all shards.
+	pub fn total_approval_stake() -> BalanceOf<T> {
+		(0..T::ApprovalStakeShards::get().max(1)).fold(Default::default(), |acc, shard| {
+			acc.saturating_add(Self::shard_approval_stake(shard))
+		})
+	}
+
+	/// Whether `who` is in [`Config::VoterList`].
+	pub fn is_voter(who: &T::AccountId) -> bool {
+		T::VoterList::contains(who)
+	}
+
+	/// Whether `who` is in [`Config::TargetList`]. Evicted targets are not.
+	pub fn is_target(who: &T::AccountId) -> bool {
+		T::TargetList::contains(who)
+	}
+
+	/// The number of voters in [`Config::VoterList`].
+	pub fn voter_count() -> u32 {
+		T::VoterList::count()
+	}
+
+	/// The number of targets in [`Config::TargetList`].
+	pub fn target_count() -> u32 {
+		T::TargetList::count()
+	}
+
+	/// The part of `stake` that counts towards approval stakes, see [`Config::TargetStakeKind`].
+	pub(crate) fn approval_part(stake: &Stake<BalanceOf<T>>) -> BalanceOf<T> {
+		T::TargetStakeKind::get().of(stake)
+	}
+
+	/// Returns the current stake of `who`, which is expected to be bonded.
+	fn stake_of(who: &T::AccountId) -> Option<Stake<BalanceOf<T>>> {
+		match T::Staking::stake(who) {
+			Ok(stake) => Some(stake),
+			Err(_) => {
+				defensive!("staking hook fired for an account that is not bonded");
+				None
+			},
+		}
+	}
+
+	/// Resolves `who` into the stash it controls, if it is not a stash itself.
+	///
+	/// Accounts that are neither a stash nor a controller are returned as they are.
+	fn stash_of(who: &T::AccountId) -> T::AccountId {
+		if T::Staking::stake(who).is_ok() {
+			return who.clone()
+		}
+		T::Staking::stash_by_ctrl(who).unwrap_or_else(|_| who.clone())
+	}
+
+	/// Whether `who` is still waiting to be seeded by the lazy genesis.
+	fn is_pending_genesis(who: &T::AccountId) -> bool {
+		LazyGenesisQueue::<T>::contains_key(who)
+	}
+
+	/// Whether a hook fired for `who` is to be ignored, because `who` is still to be seeded.
+	///
+	/// While approvals are being recomputed, anything `who` changes might be cleared, hence it is
+	/// queued to be seeded as well.
+	fn defer_if_pending(who: &T::AccountId) -> bool {
+		if Self::is_pending_genesis(who) {
+			return true
+		}
+		if RecomputeProgress::<T>::exists() {
+			LazyGenesisQueue::<T>::insert(who, ());
+			return true
+		}
+		false
+	}
+
+	/// Takes `who` out of [`LazyGenesisQueue`], returning whether nothing it backs was added to
+	/// the approvals, or whatever it added is about to be cleared.
+	fn take_pending(who: &T::AccountId) -> bool {
+		LazyGenesisQueue::<T>::take(who).is_some() || RecomputeProgress::<T>::exists()
+	}
+
+	/// Adds the nominator `stake` to the approval stake of `target`, keeping its `TargetList`
+	/// score in sync.
+	///
+	/// Returns the new approval stake of `target`.
+	pub(crate) fn add_approval(
+		target: &T::AccountId,
+		stake: BalanceOf<T>,
+		reason: ApprovalChangeReason,
+	) -> BalanceOf<T> {
+		Self::update_approval(target, reason, |approval| {
+			approval.nominators = approval.nominators.saturating_add(stake)
+		})
+	}
+
+	/// Removes the nominator `stake` from the approval stake of `target`, keeping its
+	/// `TargetList` score in sync.
+	///
+	/// Returns the new approval stake of `target`.
+	pub(crate) fn sub_approval(
+		target: &T::AccountId,
+		stake: BalanceOf<T>,
+		reason: ApprovalChangeReason,
+	) -> BalanceOf<T> {
+		Self::update_approval(target, reason, |approval| {
+			approval.nominators = approval.nominators.saturating_sub(stake)
+		})
+	}
+
+	/// Sets the own stake of the validator `target` in its approval stake, keeping its
+	/// `TargetList` score in sync.
+	///
+	/// Returns the new approval stake of `target`.
+	pub(crate) fn set_own_approval(
+		target: &T::AccountId,
+		own: BalanceOf<T>,
+		reason: ApprovalChangeReason,
+	) -> BalanceOf<T> {
+		Self::update_approval(target, reason, |approval| approval.own = own)
+	}
+
+	fn update_approval(
+		target: &T::AccountId,
+		reason: ApprovalChangeReason,
+		f: impl FnOnce(&mut Approval<BalanceOf<T>>),
+	) -> BalanceOf<T> {
+		let (prev_approval, approval) =
+			ApprovalStake::<T>::mutate(Self::shard_of(target), target, |maybe_approval| {
+				let approval = maybe_approval.get_or_insert_with(Default::default);
+				let prev_approval = approval.total();
+				f(approval);
+				(prev_approval, *approval)
+			});
+		let new_approval = approval.total();
+		Self::notify_approval_change(target, prev_approval, new_approval);
+		Self::deposit_event(Event::<T>::ApprovalStakeUpdated {
+			who: target.clone(),
+			approval,
+			reason,
+		});
+
+		let threshold = T::MinApprovalThreshold::get();
+		if prev_approval >= threshold &&
+			new_approval < threshold &&
+			(T::TargetList::contains(target) || T::EvictedList::contains(target))
+		{
+			log!(debug, "approval stake of {:?} dropped below threshold", target);
+			Self::deposit_event(Event::<T>::ApprovalBelowThreshold {
+				who: target.clone(),
+				approval: new_approval,
+			});
+		}
+
+		let coalesced = Self::coalesces(reason) &&
+			(CoalescedTargets::<T>::contains_key(target) ||
+				CoalescedTargets::<T>::count() < T::MaxCoalesced::get());
+		if coalesced {
+			CoalescedTargets::<T>::insert(target, ());
+		} else {
+			Self::maybe_reorder(target, new_approval);
+		}
+		Self::maybe_swap_targets(target, new_approval);
+
+		new_approval
+	}
+
+	/// Sets the approval stake of `target` to `approval`, and its score in [`Config::TargetList`]
+	/// right away, regardless of [`Config::ReorderThreshold`].
+	fn force_approval(target: &T::AccountId, approval: Approval<BalanceOf<T>>) {
+		let approval =
+			Self::update_approval(target, ApprovalChangeReason::Manual, |a| *a = approval);
+		if T::TargetList::contains(target) {
+			PendingApprovalReconciliation::<T>::mutate(|queue| queue.retain(|t| t != target));
+			let _ = T::TargetList::on_update(target, approval)
+				.defensive_proof("target is in the list, so it can be updated; qed");
+		}
+	}
+
+	/// Notifies [`Config::OnApprovalChange`] that the approval stake of `who` changed from `old`
+	/// to `new`, unless it did not change at all.
+	fn notify_approval_change(who: &T::AccountId, old: BalanceOf<T>, new: BalanceOf<T>) {
+		if old != new {
+			T::OnApprovalChange::on_approval_change(who, old, new);
+		}
+	}
+
+	/// Removes the approval stake of `who` from [`ApprovalStake`].
+	fn remove_approval(who: &T::AccountId) {
+		if let Some(approval) = ApprovalStake::<T>::take(Self::shard_of(who), who) {
+			Self::notify_approval_change(who, approval.total(), Zero::zero());
+		}
+	}
+
+	/// Truncates the `nominations` of `who` to [`Config::MaxApprovalTargets`], logging the ones
+	/// dropped.
+	fn bounded_nominations(
+		who: &T::AccountId,
+		mut nominations: Vec<T::AccountId>,
+	) -> Vec<T::AccountId> {
+		let max = T::MaxApprovalTargets::get() as usize;
+		if nominations.len() > max {
+			log!(
+				error,
+				"{:?} has {} nominations, ignoring all but the first {}",
+				who,
+				nominations.len(),
+				max
+			);
+			nominations.truncate(max);
+		}
+		nominations
+	}
+
+	/// Whether the list updates of an approval change caused by `reason` are deferred to the end
+	/// of the block, see [`Config::CoalesceStakeUpdates`].
+	fn coalesces(reason: ApprovalChangeReason) -> bool {
+		reason == ApprovalChangeReason::StakeUpdate && T::CoalesceStakeUpdates::get()
+	}
+
+	/// Applies the list updates recorded in [`CoalescedVoters`] and [`CoalescedTargets`], once per
+	/// account.
+	fn flush_coalesced() {
+		for voter in CoalescedVoters::<T>::drain().map(|(v, _)| v) {
+			// the voter might have been unbonded since.
+			if let Ok(stake) = T::Staking::stake(&voter) {
+				Self::update_voter_score(&voter, stake.active);
+			}
+		}
+		for target in CoalescedTargets::<T>::drain().map(|(t, _)| t) {
+			let approval = Self::approval_stake(&target).unwrap_or_default();
+			Self::maybe_reorder(&target, approval);
+		}
+	}
+
+	/// Updates the score of `target` in [`Config::TargetList`] to `approval`, unless the change
+	/// is within [`Config::ReorderThreshold`], in which case it is queued in
+	/// [`PendingApprovalReconciliation`]. Evicted targets are always rescored in
+	/// [`Config::EvictedList`].
+	fn maybe_reorder(target: &T::AccountId, approval: BalanceOf<T>) {
+		if T::EvictedList::contains(target) {
+			// evicted targets are only ever compared to the lowest retained one.
+			let _ = T::EvictedList::on_update(target, approval)
+				.defensive_proof("target is in the list, so it can be updated; qed");
+			return
+		}
+		let Ok(score) = T::TargetList::get_score(target) else { return };
+
+		let threshold = T::ReorderThreshold::get();
+		if !threshold.is_zero() {
+			let within_threshold = score.max(approval) - score.min(approval) <= threshold * score;
+			let deferred = PendingApprovalReconciliation::<T>::mutate(|queue| {
+				match queue.iter().position(|t| t == target) {
+					// keeps its place in the queue.
+					Some(_) if within_threshold => true,
+					Some(index) => {
+						queue.remove(index);
+						false
+					},
+					// the queue being full, `target` is reordered right away.
+					None => within_threshold && queue.try_push(target.clone()).is_ok(),
+				}
+			});
+			if deferred {
+				return
+			}
+		}
+
+		let _ = T::TargetList::on_update(target, approval)
+			.defensive_proof("target is in the list, so it can be updated; qed");
+	}
+
+	/// Reorders the targets in [`PendingApprovalReconciliation`] in [`Config::TargetList`], oldest
+	/// first, as long as they fit in `max_weight`. Returns the weight consumed.
+	pub fn reconcile_pending(max_weight: Weight) -> Weight {
+		let mut consumed = T::DbWeight::get().reads_writes(1, 1);
+		if consumed.any_gt(max_weight) {
+			return Weight::zero()
+		}
+
+		let mut queue = PendingApprovalReconciliation::<T>::get().into_inner();
+		if queue.is_empty() {
+			return T::DbWeight::get().reads(1)
+		}
+
+		let per_target = T::WeightInfo::reconcile_approval();
+		let mut count = 0;
+		while count < queue.len() && !consumed.saturating_add(per_target).any_gt(max_weight) {
+			consumed.saturating_accrue(per_target);
+			count += 1;
+		}
+
+		let rest = queue.split_off(count);
+		PendingApprovalReconciliation::<T>::put(BoundedVec::truncate_from(rest));
+		for target in queue {
+			// the target might have left the list since.
+			if T::TargetList::contains(&target) {
+				let approval = Self::approval_stake(&target).unwrap_or_default();
+				let _ = T::TargetList::on_update(&target, approval)
+					.defensive_proof("target is in the list, so it can be updated; qed");
+			}
+		}
+		log!(trace, "reconciled {} pending approvals", count);
+
+		consumed
+	}
+
+	/// The lowest-scored target retained in [`Config::TargetList`], with its approval stake.
+	fn lowest_target() -> Option<(T::AccountId, BalanceOf<T>)> {
+		T::TargetList::lowest().map(|t| {
+			let approval = Self::approval_stake(&t).unwrap_or_default();
+			(t, approval)
+		})
+	}
+
+	/// The highest-scored target in [`Config::EvictedList`], with its approval stake.
+	fn highest_evicted() -> Option<(T::AccountId, BalanceOf<T>)> {
+		T::EvictedList::highest().map(|t| {
+			let approval = Self::approval_stake(&t).unwrap_or_default();
+			(t, approval)
+		})
+	}
+
+	/// Moves `target` out of [`Config::TargetList`] and into [`Config::EvictedList`].
+	fn evict(target: &T::AccountId) {
+		let _ = T::TargetList::on_remove(target).defensive();
+		let approval = Self::approval_stake(target).unwrap_or_default();
+		let _ = T::EvictedList::on_insert(target.clone(), approval).defensive();
+	}
+
+	/// Moves `retained` out of [`Config::TargetList`] and `evicted` into it.
+	fn swap_targets(retained: &T::AccountId, evicted: &T::AccountId) {
+		Self::evict(retained);
+		Self::promote(evicted);
+		log!(debug, "target {:?} evicted in favour of {:?}", retained, evicted);
+	}
+
+	/// Moves `evicted` out of [`Config::EvictedList`] and back into [`Config::TargetList`].
+	fn promote(evicted: &T::AccountId) {
+		let _ = T::EvictedList::on_remove(evicted).defensive();
+		let approval = Self::approval_stake(evicted).unwrap_or_default();
+		let _ = T::TargetList::on_insert(evicted.clone(), approval).defensive();
+	}
+
+	/// Swaps `target`, whose approval stake just changed to `approval`, with the boundary target
+	/// on the other side of the eviction line, if it outscores it by more than the margin.
+	fn maybe_swap_targets(target: &T::AccountId, approval: BalanceOf<T>) {
+		if T::MaxTargets::get().is_none() {
+			return
+		}
+		let margin = T::EvictionMargin::get();
+
+		if T::EvictedList::contains(target) {
+			match Self::lowest_target() {
+				Some((lowest, lowest_approval))
+					if approval > lowest_approval.saturating_add(margin) =>
+					Self::swap_targets(&lowest, target),
+				_ => (),
+			}
+		} else if T::TargetList::contains(target) {
+			match Self::highest_evicted() {
+				Some((highest, highest_approval))
+					if highest_approval > approval.saturating_add(margin) =>
+					Self::swap_targets(target, &highest),
+				_ => (),
+			}
+		}
+	}
+
+	/// Evicts the lowest-scored targets until [`Config::TargetList`] respects
+	/// [`Config::MaxTargets`].
+	fn enforce_max_targets() {
+		let Some(max) = T::MaxTargets::get() else { return };
+		while T::TargetList::count() > max {
+			let Some((lowest, _)) = Self::lowest_target() else { break };
+			Self::evict(&lowest);
+			log!(debug, "target {:?} evicted, the target list is full", lowest);
+		}
+	}
+
+	/// Computes the approval stake of the validator `who` from scratch, based on the current state
+	/// of [`Config::Staking`], along with the number of its nominators.
+	///
+	/// Voters still waiting in [`LazyGenesisQueue`] are not accounted for, since they will add
+	/// their stake once seeded.
+	fn compute_approval(who: &T::AccountId, own: BalanceOf<T>) -> (Approval<BalanceOf<T>>, u32) {
+		if !T::TrackVoters::get() {
+			// without voters to go through, the backing tracked so far is all there is.
+			let nominators = Self::approval(who).map(|a| a.nominators).unwrap_or_default();
+			return (Approval { own, nominators }, NominatorCount::<T>::get(who))
+		}
+		let (nominators, count) = T::VoterList::iter()
+			.filter(|v| !Self::is_pending_genesis(v))
+			.filter(|v| T::Staking::nominations(v).is_some_and(|targets| targets.contains(who)))
+			.filter_map(|v| T::Staking::stake(&v).ok())
+			.fold((BalanceOf::<T>::default(), 0u32), |(acc, count), stake| {
+				(acc.saturating_add(Self::approval_part(&stake)), count.saturating_add(1))
+			});
+		(Approval { own, nominators }, count)
+	}
+
+	/// The backing of every target by its nominators, computed from scratch in a single pass over
+	/// [`Config::VoterList`], as [`Self::compute_approval`] does for a single target.
+	///
+	/// `None` if voters are not tracked, in which case there is nothing to compute it from.
+	fn compute_backings() -> Option<BTreeMap<T::AccountId, BalanceOf<T>>> {
+		if !T::TrackVoters::get() {
+			return None
+		}
+		let mut backings = BTreeMap::<T::AccountId, BalanceOf<T>>::new();
+		for voter in T::VoterList::iter().filter(|v| !Self::is_pending_genesis(v)) {
+			let Some(mut targets) = T::Staking::nominations(&voter) else { continue };
+			let Ok(stake) = T::Staking::stake(&voter) else { continue };
+			let part = Self::approval_part(&stake);
+			// a target nominated twice is still backed once.
+			targets.sort();
+			targets.dedup();
+			for target in targets {
+				let backing = backings.entry(target).or_default();
+				*backing = backing.saturating_add(part);
+			}
+		}
+		Some(backings)
+	}
+
+	/// The nominations of `who` reported by [`StakingInterface::nominations`], which the previous
+	/// nominations supplied to [`OnStakingUpdate::on_nominator_update`] are diffed against.
+	///
+	/// When the hook fires, [`Config::Staking`] already reports the new nominations of `who`, hence
+	/// `who` must be a nominator. Otherwise, the hook was fired in error, e.g. instead of
+	/// [`OnStakingUpdate::on_nominator_remove`], and the previous nominations must not be removed.
+	fn current_nominations(who: &T::AccountId) -> Result<Vec<T::AccountId>, &'static str> {
+		let nominations = T::Staking::nominations(who).ok_or("not a nominator")?;
+		Ok(Self::bounded_nominations(who, nominations))
+	}
+
+	/// Checks that `prev_nominations`, as supplied by the caller of
+	/// [`OnStakingUpdate::on_nominator_update`], match the nominations `who` is recorded with.
+	///
+	/// When the hook fires, [`Config::Staking`] already reports the new nominations of `who`, hence
+	/// the previous ones are checked against the approvals instead: each of them that is a tracked
+	/// target must be backed by nominators with at least the approval `part` of `who`.
+	#[cfg(any(debug_assertions, feature = "try-runtime"))]
+	fn check_prev_nominations(
+		who: &T::AccountId,
+		prev_nominations: &[T::AccountId],
+		part: BalanceOf<T>,
+	) {
+		for target in prev_nominations {
+			if !T::TargetList::contains(target) && !T::EvictedList::contains(target) {
+				continue
+			}
+			let backing = Self::approval(target).map(|a| a.nominators).unwrap_or_default();
+			if backing < part {
+				log!(
+					error,
+					"{:?} is said to have nominated {:?}, which is only backed by {:?}",
+					who,
+					target,
+					backing,
+				);
+				defensive!("previous nominations do not match the approvals");
+			}
+		}
+	}
+
+	/// Applies the change from `prev` to `current` to the approval stake of each of `targets`.
+	fn apply_stake_delta(
+		targets: &[T::AccountId],
+		prev: BalanceOf<T>,
+		current: BalanceOf<T>,
+		reason: ApprovalChangeReason,
+	) {
+		let targets = targets.iter().filter(|t| Self::is_backable(t));
+		if current >= prev {
+			let delta = current - prev;
+			targets.for_each(|t| {
+				Self::add_approval(t, delta, reason);
+			});
+		} else {
+			let delta = prev - current;
+			targets.for_each(|t| {
+				Self::sub_approval(t, delta, reason);
+			});
+		}
+	}
+
+	/// Moves the `stake` of the nominator `who` from the `removed` targets to the `added` ones,
+	/// leaving any other target untouched. Stale targets are skipped, see [`Self::is_backable`].
+	fn apply_nomination_delta(
+		who: &T::AccountId,
+		added: &[T::AccountId],
+		removed: &[T::AccountId],
+		stake: BalanceOf<T>,
+	) {
+		log!(trace, "{:?} nominates {} more and {} fewer targets", who, added.len(), removed.len());
+		for target in removed.iter().filter(|t| Self::is_backable(t)) {
+			Self::sub_approval(target, stake, ApprovalChangeReason::NominationChange);
+			Self::dec_nominator_count(target);
+			Self::prune_approval(target);
+		}
+		for target in added.iter().filter(|t| Self::is_backable(t)) {
+			Self::add_approval(target, stake, ApprovalChangeReason::NominationChange);
+			Self::inc_nominator_count(target);
+		}
+	}
+
+	/// Drops the approval stake of `target` once nothing backs it anymore, which only happens to
+	/// targets that are not validating.
+	fn prune_approval(target: &T::AccountId) {
+		ApprovalStake::<T>::mutate_exists(Self::shard_of(target), target, |maybe_approval| {
+			if maybe_approval.as_ref().is_some_and(|approval| approval.total().is_zero()) {
+				*maybe_approval = None;
+			}
+		});
+	}
+
+	/// Sets the number of nominators of `target` in [`NominatorCount`] to `count`.
+	fn set_nominator_count(target: &T::AccountId, count: u32) {
+		if count == 0 {
+			NominatorCount::<T>::remove(target);
+		} else {
+			NominatorCount::<T>::insert(target, count);
+		}
+	}
+
+	/// Counts one more nominator of `target` in [`NominatorCount`].
+	fn inc_nominator_count(target: &T::AccountId) {
+		NominatorCount::<T>::mutate(target, |count| *count = count.saturating_add(1));
+	}
+
+	/// Counts one nominator fewer of `target` in [`NominatorCount`].
+	fn dec_nominator_count(target: &T::AccountId) {
+		NominatorCount::<T>::mutate_exists(target, |count| {
+			*count = count.map(|c| c.saturating_sub(1)).filter(|c| *c > 0);
+		});
+	}
+
+	/// Whether nominations of `target` count towards its approval stake, i.e. whether it is a
+	/// validator or its approval stake is tracked already.
+	///
+	/// Nominations of any other account are stale, counting them would only create phantom
+	/// approvals. Should `target` validate later on, its approval is computed from scratch anyway.
+	fn is_backable(target: &T::AccountId) -> bool {
+		ApprovalStake::<T>::contains_key(Self::shard_of(target), target) ||
+			T::Staking::is_validator(target)
+	}
+
+	/// Reflects the change of the stake of `who` from `prev` to `current` in the lists and
+	/// approvals.
+	fn apply_stake(
+		who: &T::AccountId,
+		prev: Stake<BalanceOf<T>>,
+		current: Stake<BalanceOf<T>>,
+		reason: ApprovalChangeReason,
+	) {
+		let (prev_part, current_part) = (Self::approval_part(&prev), Self::approval_part(&current));
+		// nothing to write to any approval if the part of the stake that counts did not change.
+		let changed = prev_part != current_part;
+		match T::Staking::status(who) {
+			Ok(StakerStatus::Nominator(targets)) => {
+				Self::update_or_coalesce_voter(who, current.active, reason);
+				if changed {
+					Self::apply_stake_delta(&targets, prev_part, current_part, reason);
+				}
+			},
+			Ok(StakerStatus::Validator) => {
+				Self::update_or_coalesce_voter(who, current.active, reason);
+				if changed {
+					Self::set_own_approval(who, current_part, reason);
+				}
+			},
+			_ => (),
+		}
+	}
+
+	/// Inserts `who` into `VoterList` with `active` as its score, unless it is already in the list,
+	/// or into [`UntrackedVoters`] if voters are not tracked.
+	fn insert_voter(who: &T::AccountId, active: BalanceOf<T>) {
+		if !T::TrackVoters::get() {
+			UntrackedVoters::<T>::insert(who, ());
+		} else if !T::VoterList::contains(who) {
+			let _ =
+				T::VoterList::on_insert(who.clone(), Self::voter_score(who, active)).defensive();
+		}
+	}
+
+	/// Whether the stake of `who` is counted in the approvals as a voter.
+	fn is_counted_voter(who: &T::AccountId) -> bool {
+		if T::TrackVoters::get() {
+			T::VoterList::contains(who)
+		} else {
+			UntrackedVoters::<T>::contains_key(who)
+		}
+	}
+
+	/// Removes `who` from the voters, returning whether its stake was counted until now.
+	fn remove_voter(who: &T::AccountId) -> bool {
+		if !T::TrackVoters::get() {
+			return UntrackedVoters::<T>::take(who).is_some()
+		}
+		let voting = T::VoterList::contains(who);
+		if voting {
+			let _ = T::VoterList::on_remove(who).defensive();
+		}
+		voting
+	}
+
+	/// Same as [`Self::update_voter_score`], unless `reason` is coalesced, in which case `who` is
+	/// only recorded in [`CoalescedVoters`], if it is not full yet.
+	fn update_or_coalesce_voter(
+		who: &T::AccountId,
+		active: BalanceOf<T>,
+		reason: ApprovalChangeReason,
+	) {
+		if !Self::coalesces(reason) {
+			Self::update_voter_score(who, active);
+		} else if T::TrackVoters::get() && T::VoterList::contains(who) {
+			if CoalescedVoters::<T>::contains_key(who) ||
+				CoalescedVoters::<T>::count() < T::MaxCoalesced::get()
+			{
+				CoalescedVoters::<T>::insert(who, ());
+			} else {
+				Self::update_voter_score(who, active);
+			}
+		}
+	}
+
+	/// Updates the `VoterList` score of `who` to reflect `active`, if they are in the list and
+	/// their score changed.
+	fn update_voter_score(who: &T::AccountId, active: BalanceOf<T>) {
+		if !T::TrackVoters::get() {
+			return
+		}
+		let score = Self::voter_score(who, active);
+		match T::VoterList::get_score(who) {
+			Ok(current) if current != score => {
+				let _ = T::VoterList::on_update(who, score)
+					.defensive_proof("voter is in the list, so it can be updated; qed");
+			},
+			_ => (),
+		}
+	}
+
+	/// Seeds the lists and approvals with `who`, based on its current state in
+	/// [`Config::Staking`].
+	///
+	/// Returns the number of targets whose approval stake was updated.
+	pub(crate) fn seed(who: &T::AccountId) -> u32 {
+		let (Ok(stake), Ok(status)) = (T::Staking::stake(who), T::Staking::status(who)) else {
+			return 0
+		};
+
+		match status {
+			StakerStatus::Nominator(targets) => {
+				// a voter queued while approvals are recomputed is already in the list.
+				Self::insert_voter(who, stake.active);
+				Self::update_voter_score(who, stake.active);
+				targets.iter().filter(|t| Self::is_backable(t)).fold(0, |touched, t| {
+					Self::add_approval(
+						t,
+						Self::approval_part(&stake),
+						ApprovalChangeReason::Migration,
+					);
+					Self::inc_nominator_count(t);
+					touched + 1
+				})
+			},
+			StakerStatus::Validator => {
+				Self::insert_voter(who, stake.active);
+				Self::update_voter_score(who, stake.active);
+				let approval = Self::set_own_approval(
+					who,
+					Self::approval_part(&stake),
+					ApprovalChangeReason::Migration,
+				);
+				if !T::TargetList::contains(who) {
+					let _ = T::TargetList::on_insert(who.clone(), approval).defensive();
+				}
+				1
+			},
+			StakerStatus::Idle => 0,
+		}
+	}
+
+	/// Starts recomputing all approvals if [`Config::RecomputeApprovalsEachEra`] is set and this
+	/// was not done yet for the current era, returning the consumed weight.
+	///
+	/// Nothing is cleared or queued yet, see [`Self::do_recompute_step`].
+	pub(crate) fn maybe_start_recompute() -> Weight {
+		if !T::RecomputeApprovalsEachEra::get() {
+			return Weight::zero()
+		}
+
+		let era = T::Staking::current_era();
+		if LastRecomputedEra::<T>::get().is_some_and(|last| last >= era) {
+			return T::DbWeight::get().reads(2)
+		}
+		if RecomputeProgress::<T>::exists() {
+			log!(warn, "approvals for era {} are recomputed before the previous era was done", era);
+		}
+		LastRecomputedEra::<T>::put(era);
+		RecomputeProgress::<T>::put(RecomputePhase::Queueing(None));
+		log!(info, "recomputing approvals for era {}", era);
+
+		T::DbWeight::get().reads_writes(3, 2)
+	}
+
+	/// Moves the recompute started by [`Self::maybe_start_recompute`] forward by queueing or
+	/// clearing up to `limit` entries, returning the consumed weight.
+	///
+	/// Voters are all queued before anything is cleared. Hooks fired for any staker in the meantime
+	/// are deferred until it is seeded again, see [`Self::defer_if_pending`].
+	pub(crate) fn do_recompute_step(limit: u32) -> Weight {
+		let Some(phase) = RecomputeProgress::<T>::get() else { return T::DbWeight::get().reads(1) };
+
+		match phase {
+			RecomputePhase::Queueing(last) => {
+				let voters = match last {
+					Some(last) => T::VoterList::iter_from(&last).unwrap_or_else(|_| {
+						// queueing a voter twice is harmless, skipping one is not.
+						log!(debug, "{:?} left the voter list, queueing from the top again", last);
+						T::VoterList::iter()
+					}),
+					None => T::VoterList::iter(),
+				};
+				let mut queued: u32 = 0;
+				let mut last = None;
+				for voter in voters.take(limit as usize) {
+					LazyGenesisQueue::<T>::insert(&voter, ());
+					last = Some(voter);
+					queued += 1;
+				}
+				let next = if queued < limit {
+					RecomputePhase::Clearing
+				} else {
+					RecomputePhase::Queueing(last)
+				};
+				log!(debug, "queued {} voters to recompute approvals", queued);
+				RecomputeProgress::<T>::put(next);
+
+				T::DbWeight::get().reads_writes(queued as u64 + 2, queued as u64 + 1)
+			},
+			RecomputePhase::Clearing => {
+				// called at most once per block, keys cleared in previous blocks are gone already
+				// so there is no need for a cursor.
+				let counts = NominatorCount::<T>::clear(limit, None);
+				let mut cleared: u32 = 0;
+				for (_, who, approval) in ApprovalStake::<T>::drain().take(limit as usize) {
+					Self::notify_approval_change(&who, approval.total(), Zero::zero());
+					cleared += 1;
+				}
+				if counts.maybe_cursor.is_none() && cleared < limit {
+					RecomputeProgress::<T>::kill();
+				}
+				log!(debug, "cleared {} approvals and {} nominator counts", cleared, counts.unique);
+
+				T::DbWeight::get().reads_writes(
+					(counts.loops + cleared) as u64 + 2,
+					(counts.unique + cleared) as u64 + 1,
+				)
+			},
+		}
+	}
+
+	/// Seeds up to `max` stakers from [`LazyGenesisQueue`], returning the consumed weight.
+	///
+	/// Nothing is seeded while approvals are still being cleared.
+	pub(crate) fn do_lazy_genesis(max: u32) -> Weight {
+		let mut weight = T::DbWeight::get().reads(2);
+		if RecomputeProgress::<T>::exists() {
+			return weight
+		}
+		for who in LazyGenesisQueue::<T>::drain().map(|(who, _)| who).take(max as usize) {
+			let touched = Self::seed(&who);
+			log!(trace, "lazily seeded {:?}, touching {} approvals", who, touched);
+			// queue removal, stake and status reads, voter list and approval updates.
+			weight.saturating_accrue(T::DbWeight::get().reads_writes(4, 2));
+			weight.saturating_accrue(
+				T::DbWeight::get().reads_writes(2 * touched as u64, 2 * touched as u64),
+			);
+		}
+		weight
+	}
+}
+
+/// Converts balances to votes with the [`CurrencyToVote`] of [`Config::Staking`].
+pub struct StakingCurrencyToVote<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: Config> CurrencyToVote<BalanceOf<T>> for StakingCurrencyToVote<T> {
+	fn to_vote(value: BalanceOf<T>, issuance: BalanceOf<T>) -> u64 {
+		<T::Staking as StakingInterface>::CurrencyToVote::to_vote(value, issuance)
+	}
+
+	fn to_currency(value: u128, issuance: BalanceOf<T>) -> BalanceOf<T> {
+		<T::Staking as StakingInterface>::CurrencyToVote::to_currency(value, issuance)
+	}
+}
+
+/// Scores voters the same way as they are scored in [`Config::VoterList`].
+///
+/// This allows this pallet to be the [`ScoreProvider`] of the voter list itself.
+impl<T: Config> ScoreProvider<T::AccountId> for Pallet<T> {
+	type Score = T::Score;
+
+	fn score(who: &T::AccountId) -> Self::Score {
+		match T::Staking::stake(who) {
+			Ok(stake) if T::VoterList::contains(who) && !Self::is_pending_genesis(who) =>
+				Self::voter_score(who, stake.active),
+			// not tracked (yet), fall back to whatever the list knows.
+			_ => T::VoterList::get_score(who).unwrap_or_default(),
+		}
+	}
+}
+
+impl<T: Config> OnStakingUpdate<T::AccountId, BalanceOf<T>> for Pallet<T> {
+	fn on_stake_update(who: &T::AccountId, prev_stake: Option<Stake<BalanceOf<T>>>) {
+		// legacy bonds might still report their controller.
+		let who = &Self::stash_of(who);
+		if Self::defer_if_pending(who) {
+			return
+		}
+		let Some(current) = Self::stake_of(who) else { return };
+		let prev = prev_stake.unwrap_or_default();
+
+		Self::apply_stake(who, prev, current, ApprovalChangeReason::StakeUpdate);
+	}
+
+	fn on_nominator_add(who: &T::AccountId) {
+		if Self::defer_if_pending(who) {
+			return
+		}
+		let Some(stake) = Self::stake_of(who) else { return };
+
+		// a nominator that is already tracked already backs its targets, re-adding it must not
+		// count it twice.
+		if Self::is_counted_voter(who) {
+			return
+		}
+		Self::insert_voter(who, stake.active);
+
+		// a fresh nominator has no previous nominations to diff against.
+		let nominations = T::Staking::nominations(who).unwrap_or_default();
+		Self::apply_nomination_delta(who, &nominations, &[], Self::approval_part(&stake));
+	}
+
+	fn on_nominator_update(who: &T::AccountId, prev_nominations: Vec<T::AccountId>) {
+		if Self::defer_if_pending(who) {
+			return
+		}
+		let prev_nominations = Self::bounded_nominations(who, prev_nominations);
+		let Some(stake) = Self::stake_of(who) else { return };
+
+		// NOTE: the previous nominations cannot be fetched from staking anymore, it is the
+		// caller's problem to pass the right ones. They are cross-checked against the approvals in
+		// debug and try-runtime builds.
+		let nominations = match Self::current_nominations(who) {
+			Ok(nominations) => nominations,
+			Err(e) => {
+				log!(error, "ignoring the nominations update of {:?}: {}", who, e);
+				return
+			},
+		};
+		let part = Self::approval_part(&stake);
+		#[cfg(any(debug_assertions, feature = "try-runtime"))]
+		Self::check_prev_nominations(who, &prev_nominations, part);
+		Self::insert_voter(who, stake.active);
+
+		// targets that are still nominated are left untouched.
+		let added = nominations
+			.iter()
+			.filter(|t| !prev_nominations.contains(t))
+			.cloned()
+			.collect::<Vec<_>>();
+		let removed = prev_nominations
+			.iter()
+			.filter(|t| !nominations.contains(t))
+			.cloned()
+			.collect::<Vec<_>>();
+		Self::apply_nomination_delta(who, &added, &removed, part);
+	}
+
+	fn on_nominator_remove(who: &T::AccountId, nominations: Vec<T::AccountId>) {
+		// a queued staker might already be in the lists if approvals are being recomputed, but
+		// nothing was added on its behalf.
+		let pending = Self::take_pending(who);
+
+		// a voter that is gone already, e.g. withdrawn, does not back anything anymore.
+		let voting = Self::remove_voter(who);
+		if voting && !pending {
+			if let Some(stake) = Self::stake_of(who) {
+				// targets that are gone already are skipped.
+				let nominations = Self::bounded_nominations(who, nominations);
+				Self::apply_nomination_delta(who, &[], &nominations, Self::approval_part(&stake));
+			}
+		}
+	}
+
+	fn on_validator_add(who: &T::AccountId) {
+		if Self::defer_if_pending(who) {
+			return
+		}
+		let Some(stake) = Self::stake_of(who) else { return };
+
+		Self::insert_voter(who, stake.active);
+		// an already tracked target keeps its approval, re-adding it must not count it twice.
+		if !T::TargetList::contains(who) && !T::EvictedList::contains(who) {
+			// nominations of `who` were skipped while nothing backed it, its approval is
+			// recomputed from its current nominators.
+			let (computed, count) = Self::compute_approval(who, Self::approval_part(&stake));
+			Self::set_nominator_count(who, count);
+			let approval =
+				Self::update_approval(who, ApprovalChangeReason::Migration, |approval| {
+					*approval = computed
+				});
+			let _ = T::TargetList::on_insert(who.clone(), approval).defensive();
+			Self::enforce_max_targets();
+		}
+	}
+
+	fn on_validator_remove(who: &T::AccountId) {
+		// a queued staker might already be in the lists if approvals are being recomputed.
+		LazyGenesisQueue::<T>::remove(who);
+
+		Self::remove_voter(who);
+		if T::EvictedList::contains(who) {
+			let _ = T::EvictedList::on_remove(who).defensive();
+		}
+		if T::TargetList::contains(who) {
+			let _ = T::TargetList::on_remove(who).defensive();
+			// the vacancy goes to the best evicted target, regardless of the margin.
+			if let Some((highest, _)) = Self::highest_evicted() {
+				Self::promote(&highest);
+			}
+		}
+
+		// nominators might keep on backing `who` while it is chilled, keep on tracking their
+		// backing for as long as there is any. It is recomputed anyway if `who` validates again.
+		let changed =
+			ApprovalStake::<T>::mutate_exists(Self::shard_of(who), who, |maybe_approval| {
+				let approval = maybe_approval.as_mut()?;
+				let old = approval.total();
+				approval.own = Zero::zero();
+				let new = approval.total();
+				if new.is_zero() {
+					*maybe_approval = None;
+				}
+				Some((old, new))
+			});
+		if !ApprovalStake::<T>::contains_key(Self::shard_of(who), who) {
+			NominatorCount::<T>::remove(who);
+		}
+		if let Some((old, new)) = changed {
+			Self::notify_approval_change(who, old, new);
+		}
+	}
+
+	fn on_withdraw_unbonded(
+		who: &T::AccountId,
+		nominations: Vec<T::AccountId>,
+		prev_stake: Stake<BalanceOf<T>>,
+	) {
+		// nothing was added on behalf of a staker that is still queued.
+		let pending = Self::take_pending(who);
+
+		// a voter that is gone already, e.g. chilled, does not back anything anymore.
+		let voting = Self::remove_voter(who);
+		if voting && !pending {
+			for target in nominations.iter().filter(|t| Self::is_backable(t)) {
+				Self::sub_approval(
+					target,
+					Self::approval_part(&prev_stake),
+					ApprovalChangeReason::StakeUpdate,
+				);
+				Self::dec_nominator_count(target);
+				Self::prune_approval(target);
+			}
+		}
+		Self::remove_approval(who);
+		NominatorCount::<T>::remove(who);
+	}
+
+	fn on_slash(
+		stash: &T::AccountId,
+		slashed_active: BalanceOf<T>,
+		_slashed_unlocking: &BTreeMap<EraIndex, BalanceOf<T>>,
+	) {
+		if Self::defer_if_pending(stash) {
+			return
+		}
+		// the slashed ledger is not written yet, so staking still reports the previous stake.
+		let Some(prev) = Self::stake_of(stash) else { return };
+		// NOTE: the unlocking chunks are only passed after the slash, hence only the active part
+		// of the slash is reflected in the total stake. The rest is only corrected when approvals
+		// are recomputed.
+		let slashed = prev.active.saturating_sub(slashed_active);
+		let current = Stake { total: prev.total.saturating_sub(slashed), active: slashed_active };
+
+		Self::apply_stake(stash, prev, current, ApprovalChangeReason::Slash);
+	}
+}
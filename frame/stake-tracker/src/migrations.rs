@@ -0,0 +1,91 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage migrations for the stake-tracker pallet.
+
+use super::*;
+use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade};
+
+#[cfg(feature = "try-runtime")]
+use sp_runtime::TryRuntimeError;
+
+pub mod v1 {
+	use super::*;
+
+	/// Splits each entry of [`ApprovalStake`] into an [`Approval`].
+	///
+	/// The own part of a validator is seeded from its current active stake in
+	/// [`Config::Staking`], capped to its previous approval stake, and the rest is attributed to
+	/// its nominators. Accounts that are not validating anymore are entirely attributed to their
+	/// nominators.
+	pub struct MigrateToV1<T>(sp_std::marker::PhantomData<T>);
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() == 0,
+				"Required v0 before upgrading to v1"
+			);
+
+			let entries = ApprovalStake::<T>::iter_keys().count() as u32;
+			Ok(entries.encode())
+		}
+
+		fn on_runtime_upgrade() -> Weight {
+			let current = Pallet::<T>::current_storage_version();
+			let onchain = Pallet::<T>::on_chain_storage_version();
+
+			if current == 1 && onchain == 0 {
+				let mut translated: u64 = 0;
+				ApprovalStake::<T>::translate::<BalanceOf<T>, _>(|_shard, who, approval| {
+					translated += 1;
+					let own = if T::Staking::is_validator(&who) {
+						T::Staking::stake(&who)
+							.map(|s| Pallet::<T>::approval_part(&s))
+							.unwrap_or_default()
+							.min(approval)
+					} else {
+						Default::default()
+					};
+					Some(Approval { own, nominators: approval.saturating_sub(own) })
+				});
+				current.put::<Pallet<T>>();
+
+				log!(info, "v1 applied successfully, {} approvals split", translated);
+				// the approval itself, and the status and stake of its account.
+				T::DbWeight::get().reads_writes(translated.saturating_mul(3), translated + 1)
+			} else {
+				log!(warn, "Skipping v1, should be removed");
+				T::DbWeight::get().reads(1)
+			}
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			frame_support::ensure!(Pallet::<T>::on_chain_storage_version() == 1, "v1 not applied");
+
+			let entries: u32 = Decode::decode(&mut &state[..])
+				.map_err(|_| "failed to decode pre-upgrade state")?;
+			frame_support::ensure!(
+				ApprovalStake::<T>::iter_values().count() as u32 == entries,
+				"some approvals were lost"
+			);
+
+			Ok(())
+		}
+	}
+}
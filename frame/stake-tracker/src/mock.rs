@@ -0,0 +1,501 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test utilities.
+
+use crate::{self as pallet_stake_tracker, *};
+use frame_election_provider_support::{ScoreProvider, VoteWeight};
+use frame_support::{
+	parameter_types,
+	traits::{ConstU32, Hooks},
+};
+use sp_runtime::{traits::IdentityLookup, BuildStorage, DispatchError, DispatchResult, Perbill};
+use sp_staking::{currency_to_vote::SaturatingCurrencyToVote, EraIndex};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub type AccountId = u64;
+pub type Balance = u128;
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime!(
+	pub enum Test
+	{
+		System: frame_system,
+		Balances: pallet_balances,
+		StakeTracker: pallet_stake_tracker,
+		VoterBagsList: pallet_bags_list::<Instance1>,
+		TargetBagsList: pallet_bags_list::<Instance2>,
+		EvictedBagsList: pallet_bags_list::<Instance3>,
+	}
+);
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type RuntimeOrigin = RuntimeOrigin;
+	type Nonce = u64;
+	type RuntimeCall = RuntimeCall;
+	type Hash = sp_core::H256;
+	type Hashing = sp_runtime::traits::BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Block = Block;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = ();
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<Balance>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ConstU32<1024>;
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = Balance;
+	type RuntimeEvent = RuntimeEvent;
+	type DustRemoval = ();
+	type ExistentialDeposit = frame_support::traits::ConstU128<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type FreezeIdentifier = ();
+	type MaxFreezes = ();
+	type RuntimeHoldReason = ();
+	type MaxHolds = ();
+}
+
+const VOTER_THRESHOLDS: [VoteWeight; 9] = [10, 20, 30, 40, 50, 60, 1_000, 2_000, 10_000];
+const TARGET_THRESHOLDS: [Balance; 9] = [10, 20, 30, 40, 50, 60, 1_000, 2_000, 10_000];
+
+parameter_types! {
+	pub static VoterBagThresholds: &'static [VoteWeight] = &VOTER_THRESHOLDS;
+	pub static TargetBagThresholds: &'static [Balance] = &TARGET_THRESHOLDS;
+}
+
+/// Scores targets by their tracked approval stake.
+pub struct TargetScoreProvider;
+impl ScoreProvider<AccountId> for TargetScoreProvider {
+	type Score = Balance;
+
+	fn score(who: &AccountId) -> Self::Score {
+		StakeTracker::approval_stake(who).unwrap_or_default()
+	}
+}
+
+type VoterBagsListInstance = pallet_bags_list::Instance1;
+impl pallet_bags_list::Config<VoterBagsListInstance> for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type ScoreProvider = StakeTracker;
+	type BagThresholds = VoterBagThresholds;
+	type Score = VoteWeight;
+}
+
+type TargetBagsListInstance = pallet_bags_list::Instance2;
+impl pallet_bags_list::Config<TargetBagsListInstance> for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type ScoreProvider = TargetScoreProvider;
+	type BagThresholds = TargetBagThresholds;
+	type Score = Balance;
+}
+
+type EvictedBagsListInstance = pallet_bags_list::Instance3;
+impl pallet_bags_list::Config<EvictedBagsListInstance> for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type ScoreProvider = TargetScoreProvider;
+	type BagThresholds = TargetBagThresholds;
+	type Score = Balance;
+}
+
+parameter_types! {
+	pub static LazyGenesisBatch: u32 = 2;
+	pub static ApprovalStakeShards: u32 = 4;
+	pub static MaxTargets: Option<u32> = None;
+	pub static EvictionMargin: Balance = 0;
+	pub static RecomputeApprovalsEachEra: bool = false;
+	pub static MinApprovalThreshold: Balance = 0;
+	pub static ReorderThreshold: Perbill = Perbill::zero();
+	pub static ExposureTolerance: Perbill = Perbill::from_percent(10);
+	pub static MaxPendingReconciliations: u32 = 16;
+	pub static MaxApprovalTargets: u32 = 16;
+	pub static TrackVoters: bool = true;
+	pub static VoterTieBreak: bool = false;
+	pub static MinVoteScore: VoteWeight = 1;
+	pub static TargetStakeKind: StakeKind = StakeKind::Active;
+	pub static ConsistencyReportInterval: u64 = u64::MAX;
+	pub static ConsistencyReportSize: u32 = 16;
+	pub static CoalesceStakeUpdates: bool = false;
+	pub static MaxCoalesced: u32 = 16;
+	pub static ApprovalChanges: Vec<(AccountId, Balance, Balance)> = vec![];
+}
+
+/// Converts balances to votes with a plain cast, which wraps around beyond `u64::MAX`.
+pub struct WrappingCurrencyToVote;
+
+impl CurrencyToVote<Balance> for WrappingCurrencyToVote {
+	fn to_vote(value: Balance, _issuance: Balance) -> u64 {
+		value as u64
+	}
+
+	fn to_currency(value: u128, _issuance: Balance) -> Balance {
+		value
+	}
+}
+
+/// Records every approval change in [`ApprovalChanges`].
+pub struct RecordApprovalChanges;
+impl OnApprovalChange<AccountId, Balance> for RecordApprovalChanges {
+	fn on_approval_change(who: &AccountId, old: Balance, new: Balance) {
+		ApprovalChanges::mutate(|c| c.push((*who, old, new)));
+	}
+}
+
+impl pallet_stake_tracker::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type Staking = StakingMock;
+	type Score = VoteWeight;
+	type CurrencyToVote = WrappingCurrencyToVote;
+	type MinVoteScore = MinVoteScore;
+	type VoterList = VoterBagsList;
+	type TargetList = TargetBagsList;
+	type EvictedList = EvictedBagsList;
+	type TargetStakeKind = TargetStakeKind;
+	type TrackVoters = TrackVoters;
+	type CoalesceStakeUpdates = CoalesceStakeUpdates;
+	type MaxCoalesced = MaxCoalesced;
+	type VoterTieBreak = VoterTieBreak;
+	type LazyGenesisBatch = LazyGenesisBatch;
+	type ApprovalStakeShards = ApprovalStakeShards;
+	type MaxTargets = MaxTargets;
+	type EvictionMargin = EvictionMargin;
+	type RecomputeApprovalsEachEra = RecomputeApprovalsEachEra;
+	type MinApprovalThreshold = MinApprovalThreshold;
+	type OnApprovalChange = RecordApprovalChanges;
+	type ReorderThreshold = ReorderThreshold;
+	type ExposureTolerance = ExposureTolerance;
+	type MaxApprovalTargets = MaxApprovalTargets;
+	type MaxPendingReconciliations = MaxPendingReconciliations;
+	type ConsistencyReportInterval = ConsistencyReportInterval;
+	type ConsistencyReportSize = ConsistencyReportSize;
+	type ReconcileOrigin = frame_system::EnsureRoot<AccountId>;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub static Bonded: BTreeMap<AccountId, Stake<Balance>> = Default::default();
+	pub static Nominators: BTreeMap<AccountId, Vec<AccountId>> = Default::default();
+	pub static Validators: BTreeSet<AccountId> = Default::default();
+	/// The stash of each controller that is distinct from its stash.
+	pub static Controllers: BTreeMap<AccountId, AccountId> = Default::default();
+	pub static CurrentEra: EraIndex = 0;
+	pub static ElectionOngoing: bool = false;
+	/// The total exposure of validators in the active era.
+	pub static Exposures: BTreeMap<AccountId, Balance> = Default::default();
+}
+
+/// A minimal staking system, which only knows about stakes and statuses.
+pub struct StakingMock;
+
+impl StakingInterface for StakingMock {
+	type Balance = Balance;
+	type AccountId = AccountId;
+	type CurrencyToVote = SaturatingCurrencyToVote;
+
+	fn minimum_nominator_bond() -> Self::Balance {
+		1
+	}
+
+	fn minimum_validator_bond() -> Self::Balance {
+		unimplemented!("method currently not used in testing")
+	}
+
+	fn stash_by_ctrl(controller: &Self::AccountId) -> Result<Self::AccountId, DispatchError> {
+		Controllers::get()
+			.get(controller)
+			.copied()
+			.ok_or(DispatchError::Other("not a controller"))
+	}
+
+	fn bonding_duration() -> EraIndex {
+		unimplemented!("method currently not used in testing")
+	}
+
+	fn current_era() -> EraIndex {
+		CurrentEra::get()
+	}
+
+	fn stake(who: &Self::AccountId) -> Result<Stake<Self::Balance>, DispatchError> {
+		Bonded::get().get(who).copied().ok_or(DispatchError::Other("not bonded"))
+	}
+
+	fn bond(
+		who: &Self::AccountId,
+		value: Self::Balance,
+		_payee: &Self::AccountId,
+	) -> DispatchResult {
+		bond(*who, value);
+		Ok(())
+	}
+
+	fn nominate(who: &Self::AccountId, validators: Vec<Self::AccountId>) -> DispatchResult {
+		nominate(*who, validators);
+		Ok(())
+	}
+
+	fn chill(_who: &Self::AccountId) -> DispatchResult {
+		unimplemented!("method currently not used in testing")
+	}
+
+	fn bond_extra(_who: &Self::AccountId, _extra: Self::Balance) -> DispatchResult {
+		unimplemented!("method currently not used in testing")
+	}
+
+	fn unbond(_stash: &Self::AccountId, _value: Self::Balance) -> DispatchResult {
+		unimplemented!("method currently not used in testing")
+	}
+
+	fn withdraw_unbonded(
+		_stash: Self::AccountId,
+		_num_slashing_spans: u32,
+	) -> Result<bool, DispatchError> {
+		unimplemented!("method currently not used in testing")
+	}
+
+	fn desired_validator_count() -> u32 {
+		unimplemented!("method currently not used in testing")
+	}
+
+	fn election_ongoing() -> bool {
+		ElectionOngoing::get()
+	}
+
+	fn force_unstake(_who: Self::AccountId) -> DispatchResult {
+		unimplemented!("method currently not used in testing")
+	}
+
+	fn is_exposed_in_era(_who: &Self::AccountId, _era: &EraIndex) -> bool {
+		unimplemented!("method currently not used in testing")
+	}
+
+	fn active_era_exposure(validator: &Self::AccountId) -> Option<Self::Balance> {
+		Exposures::get().get(validator).copied()
+	}
+
+	fn status(who: &Self::AccountId) -> Result<StakerStatus<Self::AccountId>, DispatchError> {
+		if Validators::get().contains(who) {
+			Ok(StakerStatus::Validator)
+		} else if let Some(targets) = Nominators::get().get(who) {
+			Ok(StakerStatus::Nominator(targets.clone()))
+		} else if Bonded::get().contains_key(who) {
+			Ok(StakerStatus::Idle)
+		} else {
+			Err(DispatchError::Other("not bonded"))
+		}
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn add_era_stakers(
+		_current_era: &EraIndex,
+		_stash: &Self::AccountId,
+		_exposures: Vec<(Self::AccountId, Self::Balance)>,
+	) {
+		unimplemented!("method currently not used in testing")
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn set_current_era(_era: EraIndex) {
+		unimplemented!("method currently not used in testing")
+	}
+}
+
+/// Bonds `who` in the staking mock with `active` stake, without firing any hook.
+pub fn bond(who: AccountId, active: Balance) {
+	Bonded::mutate(|b| b.insert(who, Stake { total: active, active }));
+}
+
+/// Sets the active stake of `who` in the staking mock, without firing any hook.
+///
+/// Returns the stake of `who` prior to the update.
+pub fn set_active_stake(who: AccountId, active: Balance) -> Stake<Balance> {
+	let prev = StakingMock::stake(&who).expect("account must be bonded");
+	Bonded::mutate(|b| b.insert(who, Stake { total: prev.total - prev.active + active, active }));
+	prev
+}
+
+/// Makes `who` nominate `targets` in the staking mock, without firing any hook.
+///
+/// Returns the nominations of `who` prior to the update.
+pub fn nominate(who: AccountId, targets: Vec<AccountId>) -> Vec<AccountId> {
+	Validators::mutate(|v| v.remove(&who));
+	Nominators::mutate(|n| n.insert(who, targets)).unwrap_or_default()
+}
+
+/// Makes `who` a validator in the staking mock, without firing any hook.
+pub fn validate(who: AccountId) {
+	Nominators::mutate(|n| n.remove(&who));
+	Validators::mutate(|v| v.insert(who));
+}
+
+/// Chills `who` in the staking mock, without firing any hook.
+pub fn chill(who: AccountId) {
+	Nominators::mutate(|n| n.remove(&who));
+	Validators::mutate(|v| v.remove(&who));
+}
+
+/// Fully withdraws `who` from the staking mock, without firing any hook.
+///
+/// Returns the stake of `who` prior to the withdrawal.
+pub fn withdraw(who: AccountId) -> Stake<Balance> {
+	chill(who);
+	Bonded::mutate(|b| b.remove(&who)).expect("account must be bonded")
+}
+
+/// All the voters in the `VoterList`, in iteration order, with their scores.
+pub fn voter_scores() -> Vec<(AccountId, VoteWeight)> {
+	<VoterBagsList as SortedListProvider<AccountId>>::iter()
+		.map(|v| (v, VoterBagsList::get_score(&v).unwrap()))
+		.collect()
+}
+
+/// All the targets in the `TargetList`, in iteration order, with their scores.
+pub fn target_scores() -> Vec<(AccountId, Balance)> {
+	<TargetBagsList as SortedListProvider<AccountId>>::iter()
+		.map(|t| (t, TargetBagsList::get_score(&t).unwrap()))
+		.collect()
+}
+
+/// All the events of this pallet deposited so far.
+pub fn stake_tracker_events() -> Vec<Event<Test>> {
+	System::events()
+		.into_iter()
+		.map(|r| r.event)
+		.filter_map(|e| if let RuntimeEvent::StakeTracker(inner) = e { Some(inner) } else { None })
+		.collect()
+}
+
+pub fn run_to_block(n: u64) {
+	while System::block_number() < n {
+		StakeTracker::on_idle(System::block_number(), Weight::MAX);
+		StakeTracker::on_finalize(System::block_number());
+		System::set_block_number(System::block_number() + 1);
+		StakeTracker::on_initialize(System::block_number());
+	}
+}
+
+pub struct ExtBuilder {
+	stakers: Vec<(AccountId, Balance, StakerStatus<AccountId>)>,
+	genesis: Option<bool>,
+	/// Stakers that are seeded into the lists and approvals at genesis, even if `genesis` is not
+	/// set.
+	seeded: Vec<AccountId>,
+}
+
+impl Default for ExtBuilder {
+	fn default() -> Self {
+		Self {
+			stakers: vec![
+				// a usual user, bonded but neither validating nor nominating.
+				(1, 10, StakerStatus::Idle),
+				// validators.
+				(10, 100, StakerStatus::Validator),
+				(11, 200, StakerStatus::Validator),
+				// nominators.
+				(20, 50, StakerStatus::Nominator(vec![10, 11])),
+				(30, 30, StakerStatus::Nominator(vec![11])),
+			],
+			genesis: None,
+			seeded: vec![],
+		}
+	}
+}
+
+/// The active stake of the stakers added through [`ExtBuilder::with_validators`].
+pub const FIXTURE_VALIDATOR_STAKE: Balance = 100;
+/// The active stake of the stakers added through [`ExtBuilder::with_nominator`].
+pub const FIXTURE_NOMINATOR_STAKE: Balance = 50;
+
+impl ExtBuilder {
+	/// Seed the lists and approvals from the default stakers at genesis, lazily or not.
+	pub fn seed_genesis(mut self, lazy: bool) -> Self {
+		self.genesis = Some(lazy);
+		self
+	}
+
+	/// Add `validators` as validators with [`FIXTURE_VALIDATOR_STAKE`], already seeded into the
+	/// lists and approvals.
+	pub fn with_validators(mut self, validators: Vec<AccountId>) -> Self {
+		for who in validators {
+			self.stakers.push((who, FIXTURE_VALIDATOR_STAKE, StakerStatus::Validator));
+			self.seeded.push(who);
+		}
+		self
+	}
+
+	/// Add `who` as a nominator of `targets` with [`FIXTURE_NOMINATOR_STAKE`], already seeded into
+	/// the lists and approvals.
+	pub fn with_nominator(mut self, who: AccountId, targets: Vec<AccountId>) -> Self {
+		self.stakers
+			.push((who, FIXTURE_NOMINATOR_STAKE, StakerStatus::Nominator(targets)));
+		self.seeded.push(who);
+		self
+	}
+
+	pub fn build(self) -> sp_io::TestExternalities {
+		sp_tracing::try_init_simple();
+
+		for (who, active, status) in self.stakers.iter().cloned() {
+			bond(who, active);
+			match status {
+				StakerStatus::Validator => validate(who),
+				StakerStatus::Nominator(targets) => {
+					nominate(who, targets);
+				},
+				StakerStatus::Idle => (),
+			}
+		}
+
+		let mut storage = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+		let genesis = match self.genesis {
+			Some(lazy) => Some((self.stakers.iter().map(|(who, _, _)| *who).collect(), lazy)),
+			None if !self.seeded.is_empty() => Some((self.seeded, false)),
+			None => None,
+		};
+		if let Some((stakers, lazy)) = genesis {
+			let _ = pallet_stake_tracker::GenesisConfig::<Test> { stakers, lazy }
+				.assimilate_storage(&mut storage);
+		}
+
+		let mut ext = sp_io::TestExternalities::from(storage);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+
+	pub fn build_and_execute(self, test: impl FnOnce()) {
+		self.build().execute_with(test)
+	}
+}
@@ -0,0 +1,2341 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for pallet-stake-tracker.
+
+use super::*;
+use crate::mock::*;
+use frame_support::{assert_noop, assert_ok, assert_storage_noop, traits::Hooks};
+
+/// Checks that the lists and approvals reflect the state of the staking mock.
+fn assert_consistent() {
+	assert_eq!(StakeTracker::ensure_consistency(), Ok(()));
+
+	// every validator and nominator is a voter, scored by its active stake.
+	let expected_voters = Bonded::get()
+		.into_iter()
+		.filter(|(who, _)| !matches!(StakingMock::status(who), Ok(StakerStatus::Idle)))
+		.map(|(who, stake)| (who, StakeTracker::to_vote(stake.active)))
+		.collect::<Vec<_>>();
+	let mut voters = voter_scores();
+	voters.sort();
+	assert_eq!(voters, expected_voters);
+
+	// every validator is a target, scored by its own stake plus the stake of its nominators.
+	let expected_targets = Validators::get()
+		.into_iter()
+		.map(|v| {
+			let backing = Nominators::get()
+				.into_iter()
+				.filter(|(_, targets)| targets.contains(&v))
+				.map(|(n, _)| Bonded::get()[&n].active)
+				.sum::<Balance>();
+			(v, Bonded::get()[&v].active + backing)
+		})
+		.collect::<Vec<_>>();
+	let mut targets = target_scores();
+	targets.sort();
+	assert_eq!(targets, expected_targets);
+
+	for (target, approval) in expected_targets {
+		assert_eq!(StakeTracker::approval_stake(&target), Some(approval));
+	}
+}
+
+mod on_stake_update {
+	use super::*;
+
+	#[test]
+	fn works_for_validators_and_nominators() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_validator_add(&11);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			assert_eq!(voter_scores(), vec![(10, 100), (11, 200), (20, 50)]);
+			assert_eq!(target_scores(), vec![(10, 150), (11, 250)]);
+
+			// nominator bonds more, both of its targets are updated.
+			let prev = set_active_stake(20, 60);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			assert_eq!(voter_scores(), vec![(10, 100), (11, 200), (20, 60)]);
+			assert_eq!(target_scores(), vec![(10, 160), (11, 260)]);
+
+			// validator unbonds some, only its own approval is updated.
+			let prev = set_active_stake(10, 90);
+			StakeTracker::on_stake_update(&10, Some(prev));
+			assert_eq!(voter_scores(), vec![(10, 90), (11, 200), (20, 60)]);
+			assert_eq!(target_scores(), vec![(10, 150), (11, 260)]);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(150));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(260));
+		});
+	}
+
+	#[test]
+	fn noop_without_stake_change() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_validator_add(&11);
+			StakeTracker::on_nominator_update(&20, vec![]);
+
+			for who in [10, 20] {
+				let current = StakingMock::stake(&who).unwrap();
+				assert_storage_noop!(StakeTracker::on_stake_update(&who, Some(current)));
+			}
+
+			// a stale voter score is still fixed, but the approvals are left untouched.
+			assert_ok!(VoterBagsList::on_update(&20, 40));
+			StakeTracker::on_stake_update(&20, Some(StakingMock::stake(&20).unwrap()));
+			assert_eq!(voter_scores(), vec![(10, 100), (11, 200), (20, 50)]);
+			assert_eq!(target_scores(), vec![(10, 150), (11, 250)]);
+		});
+	}
+
+	#[test]
+	fn branches_on_status() {
+		ExtBuilder::default().build_and_execute(|| {
+			assert!(matches!(StakingMock::status(&1), Ok(StakerStatus::Idle)));
+			assert!(StakingMock::is_validator(&10) && !StakingMock::is_nominator(&10));
+			assert!(StakingMock::is_nominator(&20) && !StakingMock::is_validator(&20));
+
+			// an idle staker backs no one.
+			assert_storage_noop!(StakeTracker::on_stake_update(&1, None));
+
+			// a validator only backs itself.
+			StakeTracker::on_stake_update(&10, None);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 0 }));
+			assert_eq!(StakeTracker::approval(&11), None);
+
+			// a nominator only backs its targets.
+			StakeTracker::on_stake_update(&20, None);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 50 }));
+			assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 0, nominators: 50 }));
+			assert_eq!(StakeTracker::approval(&20), None);
+		});
+	}
+
+	#[test]
+	fn resolves_controller_to_stash() {
+		ExtBuilder::default().build_and_execute(|| {
+			Controllers::mutate(|c| c.insert(100, 10));
+			StakeTracker::on_validator_add(&10);
+
+			let prev = set_active_stake(10, 120);
+			StakeTracker::on_stake_update(&100, Some(prev));
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 120, nominators: 0 }));
+			assert_eq!(StakeTracker::approval(&100), None);
+			assert_eq!(voter_scores(), vec![(10, 120)]);
+		});
+	}
+
+	#[test]
+	fn empty_lists() {
+		ExtBuilder::default().build_and_execute(|| {
+			// approvals are tracked even if nothing is in the lists yet.
+			let prev = set_active_stake(20, 60);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			assert_eq!(StakeTracker::approval_stake(&10), Some(10));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(10));
+
+			// without a previous stake, the whole active stake is counted.
+			StakeTracker::on_stake_update(&10, None);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(110));
+
+			assert!(voter_scores().is_empty());
+			assert!(target_scores().is_empty());
+		});
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "Defensive failure has been triggered!")]
+	fn panics_when_not_bonded() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_stake_update(&2, None);
+		});
+	}
+}
+
+mod on_nominator_add {
+	use super::*;
+
+	#[test]
+	fn works_for_a_fresh_nominator() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_validator_add(&11);
+
+			StakeTracker::on_nominator_add(&20);
+			assert_eq!(voter_scores(), vec![(10, 100), (11, 200), (20, 50)]);
+			assert_eq!(target_scores(), vec![(10, 150), (11, 250)]);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 50 }));
+			assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 200, nominators: 50 }));
+		});
+	}
+
+	#[test]
+	fn noop_when_in_the_list() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_nominator_add(&20);
+			assert_storage_noop!(StakeTracker::on_nominator_add(&20));
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 50 }));
+		});
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "Defensive failure has been triggered!")]
+	fn panics_when_not_bonded() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_nominator_add(&2);
+		});
+	}
+}
+
+mod nominator_count {
+	use super::*;
+
+	#[test]
+	fn tracks_nominators_in_and_out() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_validator_add(&11);
+			assert_eq!(StakeTracker::nominator_count(&11), 0);
+
+			StakeTracker::on_nominator_add(&20);
+			assert_eq!(StakeTracker::nominator_count(&11), 1);
+			StakeTracker::on_nominator_add(&30);
+			assert_eq!(StakeTracker::nominator_count(&11), 2);
+
+			// stake changes do not count a nominator again.
+			let prev = set_active_stake(30, 40);
+			StakeTracker::on_stake_update(&30, Some(prev));
+			assert_eq!(StakeTracker::nominator_count(&11), 2);
+
+			// 30 swaps 11 for 10.
+			let prev = nominate(30, vec![10]);
+			StakeTracker::on_nominator_update(&30, prev);
+			assert_eq!(StakeTracker::nominator_count(&11), 1);
+			assert_eq!(StakeTracker::nominator_count(&10), 2);
+
+			// the count drops to zero along with the approval from nominators.
+			let prev = nominate(20, vec![10]);
+			StakeTracker::on_nominator_update(&20, prev);
+			assert_eq!(StakeTracker::nominator_count(&11), 0);
+			assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 200, nominators: 0 }));
+			assert!(!NominatorCount::<Test>::contains_key(11));
+		});
+	}
+
+	#[test]
+	fn kept_while_chilled() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_eq!(StakeTracker::nominator_count(&10), 1);
+			assert_eq!(StakeTracker::nominator_count(&11), 2);
+
+			chill(11);
+			StakeTracker::on_validator_remove(&11);
+			assert_eq!(StakeTracker::nominator_count(&11), 2);
+
+			validate(11);
+			StakeTracker::on_validator_add(&11);
+			assert_eq!(StakeTracker::nominator_count(&11), 2);
+		});
+	}
+}
+
+mod on_nominator_update {
+	use super::*;
+
+	#[test]
+	fn works_for_everyone() {
+		ExtBuilder::default().build_and_execute(|| {
+			for id in [1, 10, 20] {
+				StakeTracker::on_nominator_update(&id, vec![]);
+			}
+
+			assert_eq!(voter_scores(), vec![(10, 100), (20, 50), (1, 10)]);
+			// only the actual nominator backs anyone.
+			assert_eq!(StakeTracker::approval_stake(&10), Some(50));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(50));
+		});
+	}
+
+	#[test]
+	fn makes_a_voter() {
+		ExtBuilder::default().build_and_execute(|| {
+			assert!(!StakeTracker::is_voter(&20));
+			assert_eq!(StakeTracker::voter_count(), 0);
+
+			StakeTracker::on_nominator_update(&20, vec![]);
+			assert!(StakeTracker::is_voter(&20));
+			assert!(!StakeTracker::is_target(&20));
+			assert_eq!(StakeTracker::voter_count(), 1);
+			assert_eq!(StakeTracker::target_count(), 0);
+		});
+	}
+
+	#[test]
+	fn moves_approval_between_targets() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_nominator_update(&20, vec![]);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(50));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(50));
+
+			let prev = nominate(20, vec![11]);
+			StakeTracker::on_nominator_update(&20, prev);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(0));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(50));
+		});
+	}
+
+	#[test]
+	fn distributes_to_all_targets() {
+		let targets = vec![40, 41, 42, 43, 44];
+		let backed = |nominators| Approval { own: FIXTURE_VALIDATOR_STAKE, nominators };
+		ExtBuilder::default()
+			.with_validators(targets.clone())
+			.with_nominator(50, targets.clone())
+			.build_and_execute(|| {
+				assert_eq!(StakeTracker::target_count(), 5);
+				for t in &targets {
+					assert_eq!(StakeTracker::approval(t), Some(backed(FIXTURE_NOMINATOR_STAKE)));
+				}
+
+				let prev = nominate(50, vec![]);
+				StakeTracker::on_nominator_update(&50, prev);
+				for t in &targets {
+					assert_eq!(StakeTracker::approval(t), Some(backed(0)));
+				}
+
+				let prev = nominate(50, targets.clone());
+				StakeTracker::on_nominator_update(&50, prev);
+				for t in &targets {
+					assert_eq!(StakeTracker::approval(t), Some(backed(FIXTURE_NOMINATOR_STAKE)));
+				}
+			});
+	}
+
+	#[test]
+	fn truncates_to_max_approval_targets() {
+		MaxApprovalTargets::set(2);
+		ExtBuilder::default().with_validators(vec![40, 41, 42]).build_and_execute(|| {
+			bond(50, 50);
+			nominate(50, vec![40, 41, 42]);
+			StakeTracker::on_nominator_update(&50, vec![]);
+
+			// only the first two nominations are applied.
+			assert_eq!(StakeTracker::approval_stake(&40), Some(150));
+			assert_eq!(StakeTracker::approval_stake(&41), Some(150));
+			assert_eq!(StakeTracker::approval_stake(&42), Some(100));
+
+			// the previous nominations are truncated alike.
+			let prev = nominate(50, vec![]);
+			StakeTracker::on_nominator_update(&50, prev);
+			for target in [40, 41, 42] {
+				assert_eq!(StakeTracker::approval_stake(&target), Some(100));
+			}
+		});
+	}
+
+	#[test]
+	fn only_touches_changed_targets() {
+		ExtBuilder::default()
+			.with_validators(vec![40, 41, 42, 43])
+			.with_nominator(50, vec![40, 41, 42])
+			.build_and_execute(|| {
+				let before = target_scores().into_iter().collect::<BTreeMap<_, _>>();
+				System::reset_events();
+
+				// 42 is swapped for 43.
+				let prev = nominate(50, vec![40, 41, 43]);
+				StakeTracker::on_nominator_update(&50, prev);
+
+				let after = target_scores().into_iter().collect::<BTreeMap<_, _>>();
+				let moved = after.iter().filter(|(t, score)| before[t] != **score).count();
+				assert_eq!(moved, 2);
+				assert_eq!((after[&42], after[&43]), (before[&42] - 50, before[&43] + 50));
+				// no other approval was even written.
+				let updated = stake_tracker_events()
+					.into_iter()
+					.filter_map(|e| match e {
+						Event::ApprovalStakeUpdated { who, .. } => Some(who),
+						_ => None,
+					})
+					.collect::<Vec<_>>();
+				assert_eq!(updated, vec![42, 43]);
+			});
+	}
+
+	#[test]
+	fn skips_non_validator_targets() {
+		ExtBuilder::default().build_and_execute(|| {
+			// 1 is bonded but does not validate, 2 is not even bonded.
+			let _ = nominate(20, vec![10, 1, 2]);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(50));
+			assert_eq!(StakeTracker::approval(&1), None);
+			assert_eq!(StakeTracker::approval(&2), None);
+
+			// nor are they backed by later updates.
+			let prev = set_active_stake(20, 60);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			assert_eq!(StakeTracker::approval_stake(&10), Some(60));
+			assert_eq!(StakeTracker::approval(&1), None);
+			let prev = nominate(20, vec![10]);
+			StakeTracker::on_nominator_update(&20, prev);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(60));
+			assert_eq!(StakeTracker::approval(&1), None);
+		});
+	}
+
+	#[test]
+	fn noop_when_in_the_list() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_nominator_update(&20, vec![]);
+			assert_storage_noop!(StakeTracker::on_nominator_update(&20, vec![10, 11]));
+		});
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "Defensive failure has been triggered!")]
+	fn panics_on_mismatched_prev_nominations() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			let prev = nominate(20, vec![11]);
+			StakeTracker::on_nominator_update(&20, prev);
+
+			// 20 does not back 10 anymore, as far as the approvals are concerned.
+			let _ = nominate(20, vec![10, 11]);
+			StakeTracker::on_nominator_update(&20, vec![10]);
+		});
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "Defensive failure has been triggered!")]
+	fn panics_when_not_bonded() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_nominator_update(&2, vec![]);
+		});
+	}
+
+	#[test]
+	fn ignored_for_non_nominators() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			// 20 chilled, the hook should have been `on_nominator_remove`.
+			chill(20);
+			assert_storage_noop!(StakeTracker::on_nominator_update(&20, vec![10, 11]));
+
+			// 10 is a validator.
+			assert_storage_noop!(StakeTracker::on_nominator_update(&10, vec![11]));
+			assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 200, nominators: 80 }));
+		});
+	}
+}
+
+mod on_nominator_remove {
+	use super::*;
+
+	#[test]
+	fn removes_voter() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_nominator_update(&20, vec![]);
+			assert_eq!(voter_scores(), vec![(20, 50)]);
+
+			chill(20);
+			StakeTracker::on_nominator_remove(&20, vec![10, 11]);
+			assert!(voter_scores().is_empty());
+		});
+	}
+
+	#[test]
+	fn removes_backing_of_all_nominations() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 50 }));
+			assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 200, nominators: 80 }));
+
+			chill(20);
+			StakeTracker::on_nominator_remove(&20, vec![10, 11]);
+
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 0 }));
+			assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 200, nominators: 30 }));
+			assert_eq!(target_scores(), vec![(10, 100), (11, 230)]);
+			assert_eq!(NominatorCount::<Test>::get(10), 0);
+			assert_eq!(NominatorCount::<Test>::get(11), 1);
+			assert!(!VoterBagsList::contains(&20));
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn skips_removed_targets() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			chill(10);
+			StakeTracker::on_validator_remove(&10);
+
+			chill(20);
+			StakeTracker::on_nominator_remove(&20, vec![10, 11]);
+
+			assert_eq!(StakeTracker::approval(&10), None);
+			assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 200, nominators: 30 }));
+			assert_consistent();
+		});
+	}
+}
+
+mod on_validator_add {
+	use super::*;
+
+	#[test]
+	fn works_for_everyone() {
+		ExtBuilder::default().build_and_execute(|| {
+			for id in [1, 10, 20] {
+				StakeTracker::on_validator_add(&id);
+			}
+
+			assert_eq!(voter_scores(), vec![(10, 100), (20, 50), (1, 10)]);
+			assert_eq!(target_scores(), vec![(10, 100), (20, 50), (1, 10)]);
+		});
+	}
+
+	#[test]
+	fn seeds_approval_with_self_stake() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+
+			// no stake update is needed for the target to be scored right away.
+			let active = StakingMock::stake(&10).unwrap().active;
+			assert_eq!(StakeTracker::approval_stake(&10), Some(active));
+			assert_eq!(target_scores(), vec![(10, active)]);
+
+			// removing and re-adding seeds it again from scratch.
+			chill(10);
+			StakeTracker::on_validator_remove(&10);
+			assert_eq!(StakeTracker::approval_stake(&10), None);
+			validate(10);
+			StakeTracker::on_validator_add(&10);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(active));
+		});
+	}
+
+	#[test]
+	fn recomputes_approval_when_validating_again() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 50 }));
+
+			// 10 chills, and loses its only nominator.
+			chill(10);
+			StakeTracker::on_validator_remove(&10);
+			let prev = nominate(20, vec![11]);
+			StakeTracker::on_nominator_update(&20, prev);
+			assert_eq!(StakeTracker::approval(&10), None);
+
+			// nominating 10 while nothing backs it is not tracked.
+			let prev = nominate(30, vec![10, 11]);
+			StakeTracker::on_nominator_update(&30, prev);
+			assert_eq!(StakeTracker::approval(&10), None);
+
+			// but its nominators are counted once it validates again.
+			validate(10);
+			StakeTracker::on_validator_add(&10);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 30 }));
+			assert_eq!(StakeTracker::nominator_count(&10), 1);
+			assert_eq!(target_scores(), vec![(10, 130)]);
+		});
+	}
+
+	#[test]
+	fn noop_when_in_the_list() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			assert_storage_noop!(StakeTracker::on_validator_add(&10));
+		});
+	}
+
+	#[test]
+	fn does_not_double_count_self_stake() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			let approval = ApprovalStake::<Test>::get(StakeTracker::shard_of(&10), 10);
+			assert_eq!(approval, Some(Approval { own: 100, nominators: 50 }));
+
+			assert_storage_noop!(StakeTracker::on_validator_add(&10));
+			assert_eq!(ApprovalStake::<Test>::get(StakeTracker::shard_of(&10), 10), approval);
+		});
+	}
+
+	#[test]
+	fn noop_when_evicted() {
+		MaxTargets::set(Some(1));
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_validator_add(&11);
+			assert!(EvictedBagsList::contains(&10));
+
+			assert_storage_noop!(StakeTracker::on_validator_add(&10));
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 0 }));
+		});
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "Defensive failure has been triggered!")]
+	fn panics_when_not_bonded() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&2);
+		});
+	}
+}
+
+mod on_validator_remove {
+	use super::*;
+
+	#[test]
+	fn removes_voter_and_target() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			assert_eq!(voter_scores(), vec![(10, 100)]);
+			assert_eq!(target_scores(), vec![(10, 100)]);
+
+			chill(10);
+			StakeTracker::on_validator_remove(&10);
+			assert!(voter_scores().is_empty());
+			assert!(target_scores().is_empty());
+			assert_eq!(StakeTracker::approval_stake(&10), None);
+		});
+	}
+
+	#[test]
+	fn chill_nominate_revalidate() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 50 }));
+
+			// 10 chills, then nominates 11.
+			chill(10);
+			StakeTracker::on_validator_remove(&10);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 0, nominators: 50 }));
+			let prev = nominate(10, vec![11]);
+			StakeTracker::on_nominator_update(&10, prev);
+			assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 0, nominators: 150 }));
+
+			// 20 keeps on backing 10 in the meantime, which is still tracked.
+			let prev = set_active_stake(20, 60);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 0, nominators: 60 }));
+
+			// once 10 validates again, its approval is recomputed.
+			validate(10);
+			StakeTracker::on_nominator_remove(&10, vec![11]);
+			StakeTracker::on_validator_add(&10);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 60 }));
+			assert_eq!(target_scores(), vec![(10, 160)]);
+		});
+	}
+}
+
+mod on_unstake {
+	use super::*;
+
+	#[test]
+	fn noop() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_nominator_update(&20, vec![]);
+
+			assert_storage_noop!(StakeTracker::on_unstake(&10));
+			assert_storage_noop!(StakeTracker::on_unstake(&20));
+		});
+	}
+}
+
+mod on_withdraw_unbonded {
+	use super::*;
+
+	#[test]
+	fn removes_nominator_contribution() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_validator_add(&11);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			StakeTracker::on_nominator_update(&30, vec![]);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(150));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(280));
+
+			let prev = withdraw(20);
+			StakeTracker::on_withdraw_unbonded(&20, vec![10, 11], prev);
+
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 0 }));
+			assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 200, nominators: 30 }));
+			assert!(!VoterBagsList::contains(&20));
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn noop_when_not_tracked() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&11);
+
+			let prev = withdraw(30);
+			assert_storage_noop!(StakeTracker::on_withdraw_unbonded(&30, vec![11], prev));
+		});
+	}
+
+	#[test]
+	fn subtracts_once_after_nominator_remove() {
+		for track_voters in [true, false] {
+			TrackVoters::set(track_voters);
+			ExtBuilder::default().build_and_execute(|| {
+				StakeTracker::on_validator_add(&10);
+				StakeTracker::on_validator_add(&11);
+				StakeTracker::on_nominator_update(&20, vec![]);
+				assert_eq!(StakeTracker::approval_stake(&10), Some(150));
+
+				chill(20);
+				StakeTracker::on_nominator_remove(&20, vec![10, 11]);
+				let prev = withdraw(20);
+				StakeTracker::on_withdraw_unbonded(&20, vec![10, 11], prev);
+
+				assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 0 }));
+				assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 200, nominators: 0 }));
+				assert_storage_noop!(StakeTracker::on_nominator_remove(&20, vec![10, 11]));
+			});
+		}
+		TrackVoters::set(true);
+	}
+}
+
+mod genesis {
+	use super::*;
+
+	#[test]
+	fn eager_genesis_seeds_everything() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 0);
+			assert_consistent();
+			assert_eq!(StakeTracker::approval_stake(&10), Some(150));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(280));
+		});
+	}
+
+	#[test]
+	fn lazy_genesis_reaches_consistency() {
+		ExtBuilder::default().seed_genesis(true).build_and_execute(|| {
+			// nothing is seeded at genesis.
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 5);
+			assert!(voter_scores().is_empty());
+			assert!(target_scores().is_empty());
+
+			// two stakers are seeded per block.
+			run_to_block(2);
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 3);
+			run_to_block(3);
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 1);
+			run_to_block(4);
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 0);
+
+			assert_consistent();
+			assert_eq!(StakeTracker::approval_stake(&10), Some(150));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(280));
+		});
+	}
+
+	#[test]
+	fn lazy_genesis_reconciles_staking_changes() {
+		ExtBuilder::default().seed_genesis(true).build_and_execute(|| {
+			run_to_block(2);
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 3);
+
+			// whether or not they are seeded already, stakers keep on staking.
+			let prev = set_active_stake(20, 70);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			let prev = nominate(30, vec![10]);
+			StakeTracker::on_nominator_update(&30, prev);
+			let prev = set_active_stake(11, 150);
+			StakeTracker::on_stake_update(&11, Some(prev));
+
+			run_to_block(4);
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 0);
+
+			assert_consistent();
+			assert_eq!(StakeTracker::approval_stake(&10), Some(200));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(220));
+		});
+	}
+}
+
+mod score_provider {
+	use super::*;
+
+	#[test]
+	fn matches_voter_list() {
+		ExtBuilder::default().build_and_execute(|| {
+			// untracked voters fall back to the (missing) list score.
+			assert_eq!(<StakeTracker as ScoreProvider<_>>::score(&20), 0);
+
+			StakeTracker::on_nominator_update(&20, vec![]);
+			assert_eq!(<StakeTracker as ScoreProvider<_>>::score(&20), 50);
+			assert_eq!(
+				<StakeTracker as ScoreProvider<_>>::score(&20),
+				VoterBagsList::get_score(&20).unwrap()
+			);
+
+			// tracked voters are scored by their latest stake.
+			set_active_stake(20, 60);
+			assert_eq!(<StakeTracker as ScoreProvider<_>>::score(&20), 60);
+		});
+	}
+
+	#[test]
+	fn clamps_instead_of_wrapping() {
+		ExtBuilder::default().build_and_execute(|| {
+			let max = VoteWeight::MAX as Balance;
+			bond(40, max);
+			bond(41, max + 1);
+			validate(40);
+			validate(41);
+
+			StakeTracker::on_validator_add(&40);
+			assert_eq!(VoterBagsList::get_score(&40), Ok(VoteWeight::MAX));
+
+			// the mock conversion would wrap around to 0.
+			assert_eq!(WrappingCurrencyToVote::to_vote(max + 1, 0), 0);
+			StakeTracker::on_validator_add(&41);
+			assert_eq!(VoterBagsList::get_score(&41), Ok(VoteWeight::MAX));
+			assert_eq!(<StakeTracker as ScoreProvider<_>>::score(&41), VoteWeight::MAX);
+			// clamping is only logged, even on read-only paths.
+			assert_storage_noop!(<StakeTracker as ScoreProvider<_>>::score(&41));
+		});
+	}
+
+	#[test]
+	fn floors_tiny_stakes() {
+		MinVoteScore::set(20);
+		ExtBuilder::default().build_and_execute(|| {
+			bond(40, 5);
+			validate(40);
+
+			StakeTracker::on_validator_add(&40);
+			assert_eq!(VoterBagsList::get_score(&40), Ok(20));
+			assert_eq!(<StakeTracker as ScoreProvider<_>>::score(&40), 20);
+			// stakes above the floor are left as they are, no stake is still no vote.
+			assert_eq!(StakeTracker::to_vote(100), 100);
+			assert_eq!(StakeTracker::to_vote(0), 0);
+		});
+	}
+
+	#[test]
+	fn from_vote_round_trips() {
+		MinVoteScore::set(20);
+		ExtBuilder::default().build_and_execute(|| {
+			let unit = StakeTracker::from_vote(1);
+			let max = StakeTracker::from_vote(VoteWeight::MAX);
+
+			// every magnitude, and its neighbours, up to the largest non-clamped balance.
+			let balances = (0..64)
+				.map(|shift| 1 << shift)
+				.flat_map(|b: Balance| [b - 1, b, b + 1, b.saturating_mul(3) / 2])
+				.chain((0..1_000).map(|i| i * 7_919))
+				.filter(|b| (20..=max).contains(b));
+			for balance in balances {
+				let round_trip = StakeTracker::from_vote(StakeTracker::to_vote(balance));
+				assert!(
+					round_trip.abs_diff(balance) <= unit,
+					"{} came back as {}",
+					balance,
+					round_trip
+				);
+			}
+
+			// clamped and floored balances lose their precision.
+			assert_eq!(StakeTracker::from_vote(StakeTracker::to_vote(max + 1)), max);
+			assert_eq!(StakeTracker::from_vote(StakeTracker::to_vote(5)), 20);
+			assert_eq!(StakeTracker::from_vote(StakeTracker::to_vote(0)), 0);
+		});
+	}
+
+	#[test]
+	fn wide_scores_do_not_clamp_at_vote_weight_max() {
+		type C = WrappingCurrencyToVote;
+		let beyond = VoteWeight::MAX as Balance + 1;
+
+		// a `VoteWeight` score is clamped by the pallet, where a `u128` one keeps the balance.
+		assert_eq!(<VoteWeight as VoterScore<Balance>>::from_balance::<C>(beyond, 0), None);
+		assert_eq!(<u128 as VoterScore<Balance>>::from_balance::<C>(beyond, 0), Some(beyond));
+		assert_eq!(
+			<u128 as VoterScore<Balance>>::from_balance::<C>(beyond + 1, 0),
+			Some(beyond + 1)
+		);
+		assert_eq!(<u128 as VoterScore<Balance>>::to_balance::<C>(beyond, 0), beyond);
+		assert_eq!(
+			<u128 as VoterScore<Balance>>::from_balance::<C>(Balance::MAX, 0),
+			Some(u128::MAX)
+		);
+	}
+}
+
+mod target_stake_kind {
+	use super::*;
+
+	fn set_stake(who: AccountId, total: Balance, active: Balance) -> Stake<Balance> {
+		Bonded::mutate(|b| b.insert(who, Stake { total, active })).unwrap()
+	}
+
+	#[test]
+	fn total_stake_scores_targets() {
+		TargetStakeKind::set(StakeKind::Total);
+		ExtBuilder::default().build_and_execute(|| {
+			set_stake(10, 120, 100);
+			set_stake(20, 70, 50);
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_nominator_add(&20);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 120, nominators: 70 }));
+			assert_eq!(target_scores(), vec![(10, 190)]);
+			// voters are still scored by their active stake.
+			assert_eq!(voter_scores(), vec![(10, 100), (20, 50)]);
+
+			// unbonding leaves the total stake, hence the approval, untouched.
+			let prev = set_stake(20, 70, 40);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			assert_eq!(target_scores(), vec![(10, 190)]);
+			assert_eq!(voter_scores(), vec![(10, 100), (20, 40)]);
+
+			// withdrawing does not.
+			let prev = set_stake(20, 40, 40);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			assert_eq!(target_scores(), vec![(10, 160)]);
+		});
+	}
+
+	#[test]
+	fn active_stake_by_default() {
+		ExtBuilder::default().build_and_execute(|| {
+			set_stake(10, 120, 100);
+			StakeTracker::on_validator_add(&10);
+			assert_eq!(target_scores(), vec![(10, 100)]);
+		});
+	}
+}
+
+mod approval_split {
+	use super::*;
+
+	#[test]
+	fn validator_without_nominators() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 0 }));
+
+			let prev = set_active_stake(10, 90);
+			StakeTracker::on_stake_update(&10, Some(prev));
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 90, nominators: 0 }));
+			assert_eq!(target_scores(), vec![(10, 90)]);
+		});
+	}
+
+	#[test]
+	fn validator_with_nominators() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 50 }));
+
+			// each side only moves with its own stake.
+			let prev = set_active_stake(20, 60);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			let prev = set_active_stake(10, 90);
+			StakeTracker::on_stake_update(&10, Some(prev));
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 90, nominators: 60 }));
+			assert_eq!(target_scores(), vec![(10, 150)]);
+		});
+	}
+
+	#[test]
+	fn genesis_splits_approvals() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 50 }));
+			assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 200, nominators: 80 }));
+		});
+	}
+}
+
+mod change_reasons {
+	use super::*;
+
+	fn updated(
+		who: AccountId,
+		own: Balance,
+		nominators: Balance,
+		reason: ApprovalChangeReason,
+	) -> Event<Test> {
+		Event::ApprovalStakeUpdated { who, approval: Approval { own, nominators }, reason }
+	}
+
+	#[test]
+	fn bond_driven_change() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			System::reset_events();
+
+			let prev = set_active_stake(20, 60);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			let prev = set_active_stake(10, 90);
+			StakeTracker::on_stake_update(&10, Some(prev));
+
+			assert_eq!(
+				stake_tracker_events(),
+				vec![
+					updated(10, 100, 60, ApprovalChangeReason::StakeUpdate),
+					updated(11, 0, 60, ApprovalChangeReason::StakeUpdate),
+					updated(10, 90, 60, ApprovalChangeReason::StakeUpdate),
+				]
+			);
+		});
+	}
+
+	#[test]
+	fn slash_driven_change() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			System::reset_events();
+
+			// staking reports the slash before writing the slashed ledger.
+			StakeTracker::on_slash(&20, 40, &Default::default());
+			set_active_stake(20, 40);
+			StakeTracker::on_slash(&10, 80, &Default::default());
+			set_active_stake(10, 80);
+
+			assert_eq!(
+				stake_tracker_events(),
+				vec![
+					updated(10, 100, 40, ApprovalChangeReason::Slash),
+					updated(11, 0, 40, ApprovalChangeReason::Slash),
+					updated(10, 80, 40, ApprovalChangeReason::Slash),
+				]
+			);
+			assert_eq!(target_scores(), vec![(10, 120)]);
+			assert_eq!(voter_scores(), vec![(10, 80), (20, 40)]);
+		});
+	}
+
+	#[test]
+	fn nomination_change() {
+		ExtBuilder::default().build_and_execute(|| {
+			let prev = nominate(20, vec![11]);
+			StakeTracker::on_nominator_update(&20, prev);
+
+			// 11 is still nominated, hence untouched.
+			assert_eq!(
+				stake_tracker_events(),
+				vec![updated(10, 0, 0, ApprovalChangeReason::NominationChange)]
+			);
+		});
+	}
+}
+
+mod approval_threshold {
+	use super::*;
+
+	fn below_threshold_events() -> Vec<Event<Test>> {
+		stake_tracker_events()
+			.into_iter()
+			.filter(|e| matches!(e, Event::ApprovalBelowThreshold { .. }))
+			.collect()
+	}
+
+	#[test]
+	fn emitted_once_when_crossing() {
+		MinApprovalThreshold::set(130);
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(150));
+
+			for active in [40, 30, 25, 10] {
+				let prev = set_active_stake(20, active);
+				StakeTracker::on_stake_update(&20, Some(prev));
+			}
+			StakeTracker::on_slash(&10, 90, &Default::default());
+			set_active_stake(10, 90);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(100));
+
+			// 11 is not a target, hence never reported.
+			assert_eq!(
+				below_threshold_events(),
+				vec![Event::ApprovalBelowThreshold { who: 10, approval: 125 }]
+			);
+
+			// crossing again after recovering is reported again.
+			let prev = set_active_stake(20, 50);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			let prev = set_active_stake(20, 20);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			assert_eq!(
+				below_threshold_events(),
+				vec![
+					Event::ApprovalBelowThreshold { who: 10, approval: 125 },
+					Event::ApprovalBelowThreshold { who: 10, approval: 110 },
+				]
+			);
+		});
+	}
+}
+
+mod migrations {
+	use super::*;
+	use frame_support::{
+		storage_alias,
+		traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+		Twox64Concat,
+	};
+
+	mod v0 {
+		use super::*;
+
+		#[storage_alias]
+		pub type ApprovalStake =
+			StorageDoubleMap<StakeTracker, Twox64Concat, u32, Twox64Concat, AccountId, Balance>;
+	}
+
+	#[test]
+	fn v1_splits_approvals() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			let _ = ApprovalStake::<Test>::clear(u32::MAX, None);
+			// 11 had drifted, 42 is not a validator.
+			for (who, approval) in [(10, 150), (11, 230), (42, 7)] {
+				v0::ApprovalStake::insert(StakeTracker::shard_of(&who), who, approval);
+			}
+			StorageVersion::new(0).put::<StakeTracker>();
+
+			crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+			assert_eq!(StakeTracker::on_chain_storage_version(), 1);
+
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 50 }));
+			assert_eq!(StakeTracker::approval(&11), Some(Approval { own: 200, nominators: 30 }));
+			assert_eq!(StakeTracker::approval(&42), Some(Approval { own: 0, nominators: 7 }));
+		});
+	}
+
+	#[test]
+	fn v1_splits_approvals_by_target_stake_kind() {
+		TargetStakeKind::set(StakeKind::Total);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			let _ = ApprovalStake::<Test>::clear(u32::MAX, None);
+			Bonded::mutate(|b| b.insert(10, Stake { total: 120, active: 100 }));
+			v0::ApprovalStake::insert(StakeTracker::shard_of(&10), 10, 170);
+			StorageVersion::new(0).put::<StakeTracker>();
+
+			crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+			// the own stake is the one the pallet counts, not only the active one.
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 120, nominators: 50 }));
+		});
+		TargetStakeKind::set(StakeKind::Active);
+	}
+
+	#[test]
+	fn v1_is_skipped_once_applied() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			StorageVersion::new(1).put::<StakeTracker>();
+			assert_eq!(StakeTracker::current_storage_version(), 1);
+
+			assert_storage_noop!(crate::migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade());
+			assert_eq!(StakeTracker::on_chain_storage_version(), 1);
+		});
+	}
+}
+
+mod sharding {
+	use super::*;
+
+	#[test]
+	fn approvals_are_routed_to_their_shard() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			for (shard, who, approval) in ApprovalStake::<Test>::iter() {
+				assert!(shard < ApprovalStakeShards::get());
+				assert_eq!(shard, StakeTracker::shard_of(&who));
+				assert_eq!(StakeTracker::approval(&who), Some(approval));
+			}
+
+			// writes go to the shard of the account.
+			StakeTracker::add_approval(&42, 7, ApprovalChangeReason::Manual);
+			assert_eq!(
+				ApprovalStake::<Test>::get(StakeTracker::shard_of(&42), 42),
+				Some(Approval { own: 0, nominators: 7 })
+			);
+			for shard in
+				(0..ApprovalStakeShards::get()).filter(|s| *s != StakeTracker::shard_of(&42))
+			{
+				assert_eq!(ApprovalStake::<Test>::get(shard, 42), None);
+			}
+		});
+	}
+
+	#[test]
+	fn total_sums_across_shards() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_eq!(StakeTracker::total_approval_stake(), 150 + 280);
+			assert_eq!(
+				(0..ApprovalStakeShards::get())
+					.map(StakeTracker::shard_approval_stake)
+					.sum::<Balance>(),
+				150 + 280,
+			);
+
+			StakeTracker::add_approval(&42, 7, ApprovalChangeReason::Manual);
+			assert_eq!(StakeTracker::total_approval_stake(), 150 + 280 + 7);
+		});
+	}
+
+	#[test]
+	fn single_shard_keeps_everything_together() {
+		ApprovalStakeShards::set(1);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_eq!(StakeTracker::shard_of(&10), 0);
+			assert_eq!(StakeTracker::shard_of(&11), 0);
+			assert_eq!(StakeTracker::shard_approval_stake(0), 150 + 280);
+			assert_eq!(StakeTracker::total_approval_stake(), 150 + 280);
+		});
+	}
+}
+
+mod eviction {
+	use super::*;
+
+	fn evicted() -> Vec<AccountId> {
+		let mut evicted =
+			<EvictedBagsList as SortedListProvider<AccountId>>::iter().collect::<Vec<_>>();
+		evicted.sort();
+		evicted
+	}
+
+	fn set_stake(who: AccountId, active: Balance) {
+		let prev = set_active_stake(who, active);
+		StakeTracker::on_stake_update(&who, Some(prev));
+	}
+
+	#[test]
+	fn full_list_evicts_lowest_target() {
+		MaxTargets::set(Some(1));
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_validator_add(&11);
+
+			assert_eq!(target_scores(), vec![(11, 200)]);
+			assert_eq!(evicted(), vec![10]);
+			// the approval of evicted targets is still tracked.
+			assert_eq!(StakeTracker::approval_stake(&10), Some(100));
+		});
+	}
+
+	#[test]
+	fn no_thrashing_within_margin() {
+		MaxTargets::set(Some(1));
+		EvictionMargin::set(20);
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_validator_add(&11);
+			assert_eq!(target_scores(), vec![(11, 200)]);
+
+			// 10 outscores 11, but not by more than the margin.
+			for active in [210, 190, 220, 205] {
+				set_stake(10, active);
+				assert_eq!(target_scores(), vec![(11, 200)]);
+				assert_eq!(evicted(), vec![10]);
+			}
+
+			// 10 now clearly outscores 11.
+			set_stake(10, 221);
+			assert_eq!(target_scores(), vec![(10, 221)]);
+			assert_eq!(evicted(), vec![11]);
+
+			// and it stays put while oscillating within the margin around 11.
+			for active in [199, 181, 215, 180] {
+				set_stake(10, active);
+				assert_eq!(target_scores(), vec![(10, active)]);
+				assert_eq!(evicted(), vec![11]);
+			}
+
+			// until it clearly falls behind.
+			set_stake(10, 179);
+			assert_eq!(target_scores(), vec![(11, 200)]);
+			assert_eq!(evicted(), vec![10]);
+		});
+	}
+
+	#[test]
+	fn removed_target_is_replaced_by_best_evicted() {
+		MaxTargets::set(Some(1));
+		EvictionMargin::set(1_000);
+		ExtBuilder::default().build_and_execute(|| {
+			bond(12, 150);
+			validate(12);
+			for v in [10, 11, 12] {
+				StakeTracker::on_validator_add(&v);
+			}
+			assert_eq!(target_scores(), vec![(11, 200)]);
+			assert_eq!(evicted(), vec![10, 12]);
+
+			chill(11);
+			StakeTracker::on_validator_remove(&11);
+			assert_eq!(target_scores(), vec![(12, 150)]);
+			assert_eq!(evicted(), vec![10]);
+
+			// evicted targets are dropped as well when they stop validating.
+			chill(10);
+			StakeTracker::on_validator_remove(&10);
+			assert_eq!(target_scores(), vec![(12, 150)]);
+			assert!(evicted().is_empty());
+		});
+	}
+}
+
+mod era_recompute {
+	use super::*;
+
+	/// Injects some drift into the approvals.
+	fn inject_drift() {
+		StakeTracker::sub_approval(&10, 50, ApprovalChangeReason::Manual);
+		StakeTracker::add_approval(&11, 7, ApprovalChangeReason::Manual);
+		assert_eq!(StakeTracker::approval_stake(&10), Some(100));
+		assert_eq!(StakeTracker::approval_stake(&11), Some(287));
+	}
+
+	#[test]
+	fn disabled_by_default() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			inject_drift();
+			CurrentEra::set(1);
+			run_to_block(5);
+
+			assert_eq!(LastRecomputedEra::<Test>::get(), None);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(100));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(287));
+		});
+	}
+
+	/// Runs blocks until all approvals are recomputed, returning how many blocks it took.
+	fn finish_recompute() -> u64 {
+		let start = System::block_number();
+		while RecomputeProgress::<Test>::exists() ||
+			LazyGenesisQueue::<Test>::iter().next().is_some()
+		{
+			assert!(System::block_number() < start + 20, "recompute never finishes");
+			run_to_block(System::block_number() + 1);
+		}
+		System::block_number() - start
+	}
+
+	#[test]
+	fn era_start_recompute_corrects_drift() {
+		RecomputeApprovalsEachEra::set(true);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			// the first block recomputes era 0, which does not change anything.
+			run_to_block(2);
+			assert_eq!(LastRecomputedEra::<Test>::get(), Some(0));
+			assert!(RecomputeProgress::<Test>::exists());
+			finish_recompute();
+			assert_consistent();
+
+			// drift is kept for the rest of the era.
+			inject_drift();
+			run_to_block(System::block_number() + 3);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(100));
+
+			// but not beyond.
+			CurrentEra::set(1);
+			run_to_block(System::block_number() + 1);
+			assert_eq!(LastRecomputedEra::<Test>::get(), Some(1));
+			assert!(RecomputeProgress::<Test>::exists());
+			finish_recompute();
+
+			assert_consistent();
+			assert_eq!(StakeTracker::approval_stake(&10), Some(150));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(280));
+		});
+	}
+
+	#[test]
+	fn recompute_is_paged() {
+		RecomputeApprovalsEachEra::set(true);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			// the 4 voters are queued 2 per block, nothing is cleared in the meantime.
+			run_to_block(2);
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 2);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(150));
+			run_to_block(3);
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 4);
+			run_to_block(4);
+			assert_eq!(RecomputeProgress::<Test>::get(), Some(RecomputePhase::Clearing));
+
+			// then the approvals are cleared, and only then reseeded.
+			run_to_block(5);
+			assert_eq!(ApprovalStake::<Test>::iter().count(), 0);
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 4);
+			assert_eq!(finish_recompute(), 2);
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn recompute_reconciles_staking_changes() {
+		RecomputeApprovalsEachEra::set(true);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			run_to_block(2);
+			finish_recompute();
+			inject_drift();
+
+			CurrentEra::set(1);
+			run_to_block(System::block_number() + 1);
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 2);
+
+			// whether or not they are queued already, stakers keep on staking.
+			let prev = set_active_stake(20, 70);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			let prev = set_active_stake(11, 150);
+			StakeTracker::on_stake_update(&11, Some(prev));
+			let prev = nominate(30, vec![10]);
+			StakeTracker::on_nominator_update(&30, prev);
+
+			// including new ones, which are queued as well.
+			bond(40, 60);
+			nominate(40, vec![10]);
+			StakeTracker::on_nominator_add(&40);
+			assert!(LazyGenesisQueue::<Test>::contains_key(&40));
+
+			finish_recompute();
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn queueing_resumes_from_the_top_when_cursor_is_removed() {
+		RecomputeApprovalsEachEra::set(true);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			run_to_block(2);
+			let Some(RecomputePhase::Queueing(Some(cursor))) = RecomputeProgress::<Test>::get()
+			else {
+				panic!("voters are being queued")
+			};
+
+			chill(cursor);
+			StakeTracker::on_validator_remove(&cursor);
+
+			finish_recompute();
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn election_does_not_rush_recompute() {
+		RecomputeApprovalsEachEra::set(true);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			run_to_block(2);
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 2);
+
+			// only one more page is queued.
+			ElectionOngoing::set(true);
+			run_to_block(3);
+			assert_eq!(LazyGenesisQueue::<Test>::iter().count(), 4);
+			assert!(RecomputeProgress::<Test>::exists());
+
+			finish_recompute();
+			assert_consistent();
+		});
+	}
+}
+
+mod target_snapshot {
+	use super::*;
+
+	fn setup() {
+		for (v, active) in [(12, 500), (13, 40), (14, 1_500)] {
+			bond(v, active);
+			validate(v);
+		}
+		for v in [10, 11, 12, 13, 14] {
+			StakeTracker::on_validator_add(&v);
+		}
+	}
+
+	fn paginate(page_size: u32) -> Vec<(AccountId, Balance)> {
+		let mut snapshot = vec![];
+		let mut start = None;
+		loop {
+			let (page, next) = StakeTracker::target_snapshot(page_size, start);
+			assert!(page.len() <= page_size as usize);
+			snapshot.extend(page);
+			match next {
+				Some(_) => start = next,
+				None => break snapshot,
+			}
+		}
+	}
+
+	#[test]
+	fn sorted_approval_snapshot_ignores_insertion_order() {
+		let approvals = [(12, 500), (14, 1_500), (10, 500), (13, 40), (11, 1_500)];
+		let snapshot_of = |approvals: Vec<(AccountId, Balance)>| {
+			ExtBuilder::default().build().execute_with(|| {
+				for (who, approval) in approvals {
+					StakeTracker::add_approval(&who, approval, ApprovalChangeReason::Manual);
+				}
+				StakeTracker::approval_snapshot_sorted()
+			})
+		};
+
+		let snapshot = snapshot_of(approvals.to_vec());
+		// ties are broken by account id.
+		assert_eq!(snapshot, vec![(11, 1_500), (14, 1_500), (10, 500), (12, 500), (13, 40)]);
+		assert_eq!(snapshot_of(approvals.into_iter().rev().collect()).encode(), snapshot.encode());
+	}
+
+	#[test]
+	fn paginates_without_gaps_or_duplicates() {
+		ExtBuilder::default().build_and_execute(|| {
+			setup();
+			let targets = target_scores();
+			assert_eq!(targets.len(), 5);
+
+			for page_size in 1..=6 {
+				assert_eq!(paginate(page_size), targets);
+			}
+
+			// an empty page keeps the cursor where it is.
+			assert_eq!(StakeTracker::target_snapshot(0, Some(10)), (vec![], Some(10)));
+			// the last page has no next page.
+			assert_eq!(StakeTracker::target_snapshot(5, None), (targets, None));
+		});
+	}
+
+	#[test]
+	fn resumes_when_returned_targets_are_removed() {
+		ExtBuilder::default().build_and_execute(|| {
+			setup();
+			let targets = target_scores();
+			let (first, next) = StakeTracker::target_snapshot(2, None);
+			let start = next.unwrap();
+			assert_eq!(targets[2].0, start);
+
+			for (v, _) in &first {
+				chill(*v);
+				StakeTracker::on_validator_remove(v);
+			}
+
+			// the page resumes at the cursor, without gaps or duplicates.
+			let (page, next) = StakeTracker::target_snapshot(10, Some(start));
+			assert_eq!(page.first().map(|(t, _)| *t), Some(start));
+			assert_eq!(next, None);
+			let snapshot = first.into_iter().chain(page).collect::<Vec<_>>();
+			assert_eq!(snapshot, targets);
+			let mut ids = snapshot.iter().map(|(t, _)| *t).collect::<Vec<_>>();
+			ids.sort();
+			ids.dedup();
+			assert_eq!(ids.len(), targets.len());
+		});
+	}
+
+	#[test]
+	fn restarts_when_cursor_is_removed() {
+		ExtBuilder::default().build_and_execute(|| {
+			setup();
+			let (_, next) = StakeTracker::target_snapshot(2, None);
+			let start = next.unwrap();
+
+			chill(start);
+			StakeTracker::on_validator_remove(&start);
+
+			// the remaining targets are all returned again.
+			let (page, next) = StakeTracker::target_snapshot(10, Some(start));
+			assert_eq!(page, target_scores());
+			assert_eq!(page.len(), 4);
+			assert_eq!(next, None);
+		});
+	}
+}
+
+mod eligible_targets {
+	use super::*;
+
+	fn setup() {
+		for (v, active) in [(12, 500), (13, 40), (14, 1_500), (15, 30)] {
+			bond(v, active);
+			validate(v);
+		}
+		for v in [10, 11, 12, 13, 14, 15] {
+			StakeTracker::on_validator_add(&v);
+		}
+	}
+
+	#[test]
+	fn stops_at_threshold() {
+		ExtBuilder::default().build_and_execute(|| {
+			setup();
+			assert_eq!(
+				target_scores(),
+				vec![(14, 1_500), (10, 150), (11, 280), (12, 500), (13, 40), (15, 30)]
+			);
+
+			// 15 is past the cutoff, whatever its approval.
+			ApprovalStake::<Test>::mutate(StakeTracker::shard_of(&15), &15, |approval| {
+				approval.as_mut().unwrap().own = 1_000
+			});
+			assert_eq!(
+				StakeTracker::eligible_targets(100, 10),
+				vec![(14, 1_500), (10, 150), (11, 280), (12, 500)]
+			);
+			assert_eq!(StakeTracker::eligible_targets(100, 2), vec![(14, 1_500), (10, 150)]);
+			assert_eq!(StakeTracker::eligible_targets(1_501, 10), vec![]);
+			assert_eq!(StakeTracker::eligible_targets(0, 0), vec![]);
+		});
+	}
+
+	#[test]
+	fn skips_targets_scored_above_their_approval() {
+		ExtBuilder::default().build_and_execute(|| {
+			setup();
+
+			// 10 lost its backing, but was not reordered yet.
+			ApprovalStake::<Test>::mutate(StakeTracker::shard_of(&10), &10, |approval| {
+				approval.as_mut().unwrap().nominators = 0
+			});
+			assert_eq!(
+				StakeTracker::eligible_targets(120, 10),
+				vec![(14, 1_500), (11, 280), (12, 500)]
+			);
+		});
+	}
+}
+
+mod approval_median {
+	use super::*;
+
+	#[test]
+	fn is_none_without_targets() {
+		ExtBuilder::default().build_and_execute(|| {
+			assert_eq!(StakeTracker::approval_median(), None);
+		});
+	}
+
+	#[test]
+	fn averages_middle_approvals_of_even_count() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_eq!(target_scores(), vec![(10, 150), (11, 280)]);
+			assert_eq!(StakeTracker::approval_median(), Some(215));
+
+			// rounded down.
+			StakeTracker::add_approval(&11, 1, ApprovalChangeReason::Manual);
+			assert_eq!(StakeTracker::approval_median(), Some(215));
+		});
+	}
+
+	#[test]
+	fn picks_middle_approval_of_odd_count() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			for (v, active) in [(12, 500), (13, 40), (14, 1_500)] {
+				bond(v, active);
+				validate(v);
+				StakeTracker::on_validator_add(&v);
+			}
+			// approvals 40, 150, 280, 500, 1_500.
+			assert_eq!(StakeTracker::approval_median(), Some(280));
+
+			StakeTracker::on_validator_remove(&14);
+			// approvals 40, 150, 280, 500.
+			assert_eq!(StakeTracker::approval_median(), Some(215));
+		});
+	}
+}
+
+mod reorder_threshold {
+	use super::*;
+
+	fn set_stake(who: AccountId, active: Balance) {
+		let prev = set_active_stake(who, active);
+		StakeTracker::on_stake_update(&who, Some(prev));
+	}
+
+	fn deferred() -> Vec<AccountId> {
+		PendingApprovalReconciliation::<Test>::get().into_inner()
+	}
+
+	#[test]
+	fn reorders_right_away_by_default() {
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+
+			set_stake(10, 101);
+			assert_eq!(TargetBagsList::get_score(&10), Ok(101));
+			assert!(deferred().is_empty());
+		});
+	}
+
+	#[test]
+	fn sub_threshold_updates_are_deferred() {
+		ReorderThreshold::set(Perbill::from_percent(10));
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+
+			// changes add up against the score in the list.
+			set_stake(10, 105);
+			set_stake(10, 110);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(110));
+			assert_eq!(TargetBagsList::get_score(&10), Ok(100));
+			assert_eq!(deferred(), vec![10]);
+
+			// and are reconciled once the block is idle.
+			run_to_block(2);
+			assert_eq!(TargetBagsList::get_score(&10), Ok(110));
+			assert!(deferred().is_empty());
+		});
+	}
+
+	#[test]
+	fn super_threshold_updates_reorder_right_away() {
+		ReorderThreshold::set(Perbill::from_percent(10));
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+
+			set_stake(10, 105);
+			assert_eq!(deferred(), vec![10]);
+
+			// the accumulated change exceeds the threshold.
+			set_stake(10, 111);
+			assert_eq!(TargetBagsList::get_score(&10), Ok(111));
+			assert!(deferred().is_empty());
+
+			set_stake(10, 99);
+			assert_eq!(TargetBagsList::get_score(&10), Ok(99));
+			assert!(deferred().is_empty());
+		});
+	}
+
+	#[test]
+	fn removed_targets_are_not_flushed() {
+		ReorderThreshold::set(Perbill::from_percent(10));
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			set_stake(10, 105);
+			assert_eq!(deferred(), vec![10]);
+
+			chill(10);
+			StakeTracker::on_validator_remove(&10);
+			run_to_block(2);
+			assert!(!TargetBagsList::contains(&10));
+			assert!(deferred().is_empty());
+		});
+	}
+}
+
+mod ensure_consistency {
+	use super::*;
+
+	#[test]
+	fn lists_inconsistent_targets() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert!(StakeTracker::inconsistent_targets(10).is_empty());
+
+			// 10 lost the backing of 20, 11 got some out of thin air.
+			StakeTracker::sub_approval(&10, 50, ApprovalChangeReason::Manual);
+			StakeTracker::add_approval(&11, 7, ApprovalChangeReason::Manual);
+
+			let mut inconsistent = StakeTracker::inconsistent_targets(10);
+			inconsistent.sort();
+			assert_eq!(inconsistent, vec![(10, 100, 150), (11, 287, 280)]);
+			assert_eq!(StakeTracker::inconsistent_targets(1).len(), 1);
+			assert!(StakeTracker::inconsistent_targets(0).is_empty());
+		});
+	}
+
+	#[test]
+	fn lists_inconsistent_evicted_targets() {
+		MaxTargets::set(Some(1));
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert!(EvictedBagsList::contains(&10));
+			assert!(StakeTracker::inconsistent_targets(10).is_empty());
+
+			StakeTracker::sub_approval(&10, 50, ApprovalChangeReason::Manual);
+			assert_eq!(StakeTracker::inconsistent_targets(10), vec![(10, 100, 150)]);
+		});
+	}
+
+	#[test]
+	fn detects_corrupted_approvals() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_eq!(StakeTracker::ensure_consistency(), Ok(()));
+
+			ApprovalStake::<Test>::mutate(StakeTracker::shard_of(&10), &10, |approval| {
+				approval.as_mut().unwrap().own += 1
+			});
+			assert_eq!(
+				StakeTracker::ensure_consistency(),
+				Err("target score does not match its approval stake")
+			);
+		});
+	}
+
+	#[test]
+	fn detects_stale_voters() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			// chilled in staking without firing any hook.
+			chill(20);
+			assert_eq!(StakeTracker::ensure_consistency(), Err("voter is idle"));
+		});
+	}
+
+	#[test]
+	fn detects_idle_voters() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			// 1 is bonded but idle, e.g. a voter that a removal hook failed to remove.
+			assert_ok!(VoterBagsList::on_insert(1, 10));
+			assert_eq!(StakeTracker::ensure_consistency(), Err("voter is idle"));
+
+			assert_ok!(VoterBagsList::on_remove(&1));
+			assert_eq!(StakeTracker::ensure_consistency(), Ok(()));
+		});
+	}
+
+	#[test]
+	fn approvals_within_tolerance_of_exposures() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			// no exposures known, nothing to compare.
+			assert_eq!(StakeTracker::ensure_exposure_consistency(), Ok(()));
+
+			// 10 is exposed exactly as approved, 11 gained 20 since the era started.
+			Exposures::set(BTreeMap::from([(10, 150), (11, 260)]));
+			assert_eq!(StakeTracker::ensure_exposure_consistency(), Ok(()));
+		});
+	}
+
+	#[test]
+	fn detects_approvals_diverged_from_exposures() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			// 10 lost 50 of 150 since the era started, beyond the 10% tolerance.
+			Exposures::set(BTreeMap::from([(10, 150), (11, 280)]));
+			StakeTracker::sub_approval(&10, 50, ApprovalChangeReason::Manual);
+			assert_eq!(
+				StakeTracker::ensure_exposure_consistency(),
+				Err("target approval diverged from its exposure")
+			);
+
+			ExposureTolerance::set(Perbill::from_percent(50));
+			assert_eq!(StakeTracker::ensure_exposure_consistency(), Ok(()));
+		});
+	}
+}
+
+mod force_set_approval {
+	use super::*;
+	use sp_runtime::traits::BadOrigin;
+
+	#[test]
+	fn sets_approval_and_score() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			System::reset_events();
+
+			assert_ok!(StakeTracker::force_set_approval(RuntimeOrigin::root(), 10, 400));
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 300 }));
+			assert_eq!(TargetBagsList::get_score(&10), Ok(400));
+			assert_eq!(
+				stake_tracker_events(),
+				vec![Event::ApprovalStakeUpdated {
+					who: 10,
+					approval: Approval { own: 100, nominators: 300 },
+					reason: ApprovalChangeReason::Manual,
+				}]
+			);
+
+			// below the own stake, nothing is left to the nominators.
+			assert_ok!(StakeTracker::force_set_approval(RuntimeOrigin::root(), 10, 60));
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 60, nominators: 0 }));
+			assert_eq!(TargetBagsList::get_score(&10), Ok(60));
+		});
+	}
+
+	#[test]
+	fn rejects_unknown_targets() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			// 20 nominates, 2 is not even bonded.
+			for who in [20, 2] {
+				assert_noop!(
+					StakeTracker::force_set_approval(RuntimeOrigin::root(), who, 100),
+					Error::<Test>::NotATarget
+				);
+			}
+		});
+	}
+
+	#[test]
+	fn requires_root() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_noop!(
+				StakeTracker::force_set_approval(RuntimeOrigin::signed(10), 10, 400),
+				BadOrigin
+			);
+		});
+	}
+}
+
+mod force_reconcile_target {
+	use super::*;
+	use sp_runtime::traits::BadOrigin;
+
+	#[test]
+	fn fixes_drifted_approval() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			// 10 lost the backing of 20.
+			StakeTracker::sub_approval(&10, 50, ApprovalChangeReason::Manual);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(100));
+			System::reset_events();
+
+			assert_ok!(StakeTracker::force_reconcile_target(RuntimeOrigin::root(), 10));
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 50 }));
+			assert_eq!(TargetBagsList::get_score(&10), Ok(150));
+			assert_eq!(
+				stake_tracker_events(),
+				vec![Event::ApprovalStakeUpdated {
+					who: 10,
+					approval: Approval { own: 100, nominators: 50 },
+					reason: ApprovalChangeReason::Manual,
+				}]
+			);
+			assert_eq!(StakeTracker::ensure_consistency(), Ok(()));
+		});
+	}
+
+	#[test]
+	fn noop_if_already_correct() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_storage_noop!(assert_ok!(StakeTracker::force_reconcile_target(
+				RuntimeOrigin::root(),
+				10
+			)));
+		});
+	}
+
+	#[test]
+	fn requires_reconcile_origin() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			StakeTracker::sub_approval(&10, 50, ApprovalChangeReason::Manual);
+
+			assert_noop!(
+				StakeTracker::force_reconcile_target(RuntimeOrigin::signed(20), 10),
+				BadOrigin
+			);
+			assert_eq!(StakeTracker::approval_stake(&10), Some(100));
+		});
+	}
+}
+
+mod rebag_targets {
+	use super::*;
+
+	#[test]
+	fn moves_misplaced_targets() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_eq!(StakeTracker::rebag_targets(10), 0);
+
+			// the thresholds change, leaving 10 (150) out of its notional bag, but not 11 (280).
+			TargetBagThresholds::set(&[200, 1_000]);
+			assert_eq!(StakeTracker::rebag_targets(10), 1);
+			assert_eq!(target_scores(), vec![(11, 280), (10, 150)]);
+
+			assert_eq!(StakeTracker::rebag_targets(10), 0);
+			assert_eq!(StakeTracker::ensure_consistency(), Ok(()));
+		});
+	}
+
+	#[test]
+	fn walks_targets_round_robin() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			TargetBagThresholds::set(&[200, 1_000]);
+
+			// 10 is walked first, and moved behind 11.
+			assert_eq!(StakeTracker::rebag_targets(1), 1);
+			assert_eq!(RebagCursor::<Test>::get(), Some(10));
+			// nothing is left after 10, the next call starts from the top again.
+			assert_eq!(StakeTracker::rebag_targets(1), 0);
+			assert_eq!(RebagCursor::<Test>::get(), None);
+		});
+	}
+}
+
+mod on_approval_change {
+	use super::*;
+
+	#[test]
+	fn observes_old_and_new_approvals() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			ApprovalChanges::take();
+
+			let prev = set_active_stake(20, 60);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			assert_eq!(ApprovalChanges::take(), vec![(10, 150, 160), (11, 280, 290)]);
+
+			// the validator leaving is seen as its own stake being dropped from its approval.
+			chill(10);
+			StakeTracker::on_validator_remove(&10);
+			assert_eq!(ApprovalChanges::take(), vec![(10, 160, 60)]);
+		});
+	}
+
+	#[test]
+	fn not_called_without_change() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			ApprovalChanges::take();
+
+			StakeTracker::on_stake_update(&20, Some(StakingMock::stake(&20).unwrap()));
+			StakeTracker::add_approval(&10, 0, ApprovalChangeReason::Manual);
+			assert!(ApprovalChanges::get().is_empty());
+		});
+	}
+}
+
+mod coalescing {
+	use super::*;
+
+	#[test]
+	fn defers_list_updates_to_end_of_block() {
+		CoalesceStakeUpdates::set(true);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_eq!(target_scores(), vec![(10, 150), (11, 280)]);
+
+			// 20 bonds, then bonds extra, in the same block.
+			let prev = set_active_stake(20, 60);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			let prev = set_active_stake(20, 70);
+			StakeTracker::on_stake_update(&20, Some(prev));
+
+			// the approvals are up to date, the lists are not written yet.
+			assert_eq!(StakeTracker::approval_stake(&10), Some(170));
+			assert_eq!(StakeTracker::approval_stake(&11), Some(300));
+			assert_eq!(target_scores(), vec![(10, 150), (11, 280)]);
+			assert_eq!(VoterBagsList::get_score(&20), Ok(50));
+			assert_eq!(CoalescedVoters::<Test>::iter_keys().collect::<Vec<_>>(), vec![20]);
+			let mut coalesced = CoalescedTargets::<Test>::iter_keys().collect::<Vec<_>>();
+			coalesced.sort();
+			assert_eq!(coalesced, vec![10, 11]);
+			assert_eq!(StakeTracker::ensure_consistency(), Ok(()));
+
+			// each account is updated once, to its net result.
+			StakeTracker::on_finalize(System::block_number());
+			assert_eq!(target_scores(), vec![(10, 170), (11, 300)]);
+			assert_eq!(VoterBagsList::get_score(&20), Ok(70));
+			assert_eq!(CoalescedVoters::<Test>::iter().count(), 0);
+			assert_eq!(CoalescedTargets::<Test>::iter().count(), 0);
+			assert_eq!(StakeTracker::ensure_consistency(), Ok(()));
+		});
+	}
+
+	#[test]
+	fn other_updates_are_not_deferred() {
+		CoalesceStakeUpdates::set(true);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			let prev = nominate(30, vec![10, 11]);
+			StakeTracker::on_nominator_update(&30, prev);
+			assert_eq!(target_scores(), vec![(10, 180), (11, 280)]);
+			assert_eq!(CoalescedTargets::<Test>::iter().count(), 0);
+		});
+	}
+
+	#[test]
+	fn full_maps_apply_updates_right_away() {
+		CoalesceStakeUpdates::set(true);
+		MaxCoalesced::set(1);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			let prev = set_active_stake(20, 60);
+			StakeTracker::on_stake_update(&20, Some(prev));
+
+			// 20 and 10 fill the maps, so 11 is reordered right away.
+			assert_eq!(CoalescedVoters::<Test>::iter_keys().collect::<Vec<_>>(), vec![20]);
+			assert_eq!(CoalescedTargets::<Test>::iter_keys().collect::<Vec<_>>(), vec![10]);
+			assert_eq!(target_scores(), vec![(10, 150), (11, 290)]);
+
+			// a coalesced account is still coalesced, others are updated right away.
+			let prev = set_active_stake(20, 70);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			let prev = set_active_stake(30, 40);
+			StakeTracker::on_stake_update(&30, Some(prev));
+			assert_eq!(VoterBagsList::get_score(&20), Ok(50));
+			assert_eq!(VoterBagsList::get_score(&30), Ok(40));
+			assert_eq!(CoalescedVoters::<Test>::count(), 1);
+			assert_eq!(CoalescedTargets::<Test>::count(), 1);
+			assert_eq!(target_scores(), vec![(10, 150), (11, 310)]);
+			assert_eq!(StakeTracker::ensure_consistency(), Ok(()));
+
+			StakeTracker::on_finalize(System::block_number());
+			assert_eq!(target_scores(), vec![(10, 170), (11, 310)]);
+			assert_eq!(VoterBagsList::get_score(&20), Ok(70));
+			assert_eq!(CoalescedVoters::<Test>::count(), 0);
+			assert_eq!(CoalescedTargets::<Test>::count(), 0);
+		});
+	}
+
+	#[test]
+	fn flush_weight_is_reserved() {
+		ExtBuilder::default().build_and_execute(|| {
+			let reserved = <() as WeightInfo>::flush_coalesced(MaxCoalesced::get());
+			assert!(!StakeTracker::on_initialize(2).all_gte(reserved));
+			CoalesceStakeUpdates::set(true);
+			assert!(StakeTracker::on_initialize(3).all_gte(reserved));
+		});
+	}
+}
+
+mod consistency_report {
+	use super::*;
+
+	fn reports() -> Vec<(u32, u32)> {
+		stake_tracker_events()
+			.into_iter()
+			.filter_map(|e| match e {
+				Event::ConsistencyReport { checked, diverged } => Some((checked, diverged)),
+				_ => None,
+			})
+			.collect()
+	}
+
+	#[test]
+	fn off_by_default() {
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			run_to_block(10);
+			assert!(reports().is_empty());
+		});
+	}
+
+	#[test]
+	fn counts_diverged_targets() {
+		ConsistencyReportInterval::set(2);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			ApprovalStake::<Test>::mutate(StakeTracker::shard_of(&10), &10, |approval| {
+				approval.as_mut().unwrap().own += 1
+			});
+
+			run_to_block(2);
+			assert!(reports().is_empty());
+
+			run_to_block(3);
+			assert_eq!(reports(), vec![(2, 1)]);
+		});
+	}
+
+	#[test]
+	fn checks_targets_round_robin() {
+		ConsistencyReportInterval::set(1);
+		ConsistencyReportSize::set(1);
+		ExtBuilder::default().seed_genesis(false).build_and_execute(|| {
+			assert_eq!(target_scores(), vec![(10, 150), (11, 280)]);
+			ApprovalStake::<Test>::mutate(StakeTracker::shard_of(&10), &10, |approval| {
+				approval.as_mut().unwrap().own += 1
+			});
+
+			// 10, then 11, then 10 again.
+			run_to_block(2);
+			assert_eq!(ConsistencyCursor::<Test>::get(), Some(10));
+			run_to_block(3);
+			assert_eq!(ConsistencyCursor::<Test>::get(), Some(11));
+			run_to_block(4);
+			assert_eq!(ConsistencyCursor::<Test>::get(), Some(10));
+			assert_eq!(reports(), vec![(1, 1), (1, 0), (1, 1)]);
+		});
+	}
+}
+
+mod on_idle {
+	use super::*;
+
+	fn pending() -> Vec<AccountId> {
+		PendingApprovalReconciliation::<Test>::get().into_inner()
+	}
+
+	fn reconciled(v: AccountId) -> bool {
+		TargetBagsList::get_score(&v).ok() == StakeTracker::approval_stake(&v)
+	}
+
+	/// Adds five targets, then bumps the stake of four of them below the threshold, in a
+	/// different order than the list.
+	fn setup() {
+		for (v, active) in [(12, 300), (13, 400), (14, 500)] {
+			bond(v, active);
+			validate(v);
+		}
+		for v in [10, 11, 12, 13, 14] {
+			StakeTracker::on_validator_add(&v);
+		}
+		for v in [13, 10, 14, 11] {
+			let active = StakingMock::stake(&v).unwrap().active;
+			let prev = set_active_stake(v, active + 1);
+			StakeTracker::on_stake_update(&v, Some(prev));
+		}
+	}
+
+	#[test]
+	fn drains_within_weight_in_fifo_order() {
+		ReorderThreshold::set(Perbill::from_percent(10));
+		ExtBuilder::default().build_and_execute(|| {
+			setup();
+			assert_eq!(pending(), vec![13, 10, 14, 11]);
+
+			// the queue itself, and two targets.
+			let queue_weight = <Test as frame_system::Config>::DbWeight::get().reads_writes(1, 1);
+			let weight = queue_weight
+				.saturating_add(<() as WeightInfo>::reconcile_approval().saturating_mul(2));
+			assert_eq!(StakeTracker::on_idle(1, weight), weight);
+			assert_eq!(pending(), vec![14, 11]);
+			assert!(reconciled(13) && reconciled(10));
+			assert!(!reconciled(14) && !reconciled(11));
+
+			// not even enough for a single target.
+			StakeTracker::on_idle(1, queue_weight);
+			assert_eq!(pending(), vec![14, 11]);
+
+			StakeTracker::on_idle(1, Weight::MAX);
+			assert!(pending().is_empty());
+			assert!([10, 11, 12, 13, 14].into_iter().all(reconciled));
+		});
+	}
+
+	#[test]
+	fn noop_without_weight() {
+		ReorderThreshold::set(Perbill::from_percent(10));
+		ExtBuilder::default().build_and_execute(|| {
+			setup();
+			assert_storage_noop!(StakeTracker::on_idle(1, Weight::zero()));
+		});
+	}
+
+	#[test]
+	fn drained_at_once_during_election() {
+		ReorderThreshold::set(Perbill::from_percent(10));
+		ExtBuilder::default().build_and_execute(|| {
+			setup();
+
+			ElectionOngoing::set(true);
+			StakeTracker::on_initialize(2);
+			assert!(pending().is_empty());
+			assert!([10, 11, 12, 13, 14].into_iter().all(reconciled));
+		});
+	}
+
+	#[test]
+	fn full_queue_reorders_right_away() {
+		ReorderThreshold::set(Perbill::from_percent(10));
+		MaxPendingReconciliations::set(3);
+		ExtBuilder::default().build_and_execute(|| {
+			setup();
+			assert_eq!(pending(), vec![13, 10, 14]);
+			assert!(reconciled(11));
+		});
+	}
+}
+
+mod track_voters {
+	use super::*;
+
+	#[test]
+	fn only_approvals_are_tracked_when_disabled() {
+		TrackVoters::set(false);
+		ExtBuilder::default().build_and_execute(|| {
+			StakeTracker::on_validator_add(&10);
+			StakeTracker::on_validator_add(&11);
+			StakeTracker::on_nominator_update(&20, vec![]);
+			let prev = set_active_stake(20, 60);
+			StakeTracker::on_stake_update(&20, Some(prev));
+
+			assert_eq!(VoterBagsList::count(), 0);
+			assert_eq!(target_scores(), vec![(10, 160), (11, 260)]);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 60 }));
+
+			// the backing of 10 is kept while it is chilled, and counted once it validates again.
+			chill(10);
+			StakeTracker::on_validator_remove(&10);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 0, nominators: 60 }));
+			let prev = set_active_stake(20, 70);
+			StakeTracker::on_stake_update(&20, Some(prev));
+			validate(10);
+			StakeTracker::on_validator_add(&10);
+			assert_eq!(StakeTracker::approval(&10), Some(Approval { own: 100, nominators: 70 }));
+			assert_eq!(target_scores(), vec![(10, 170), (11, 270)]);
+			assert_eq!(VoterBagsList::count(), 0);
+		});
+	}
+}
+
+mod voter_tie_break {
+	use super::*;
+
+	/// The voters, sorted by score, after bonding the nominators 20 and 21 with `stake` and adding
+	/// them in the `order` given.
+	fn voters_by_score(stake: Balance, order: [AccountId; 2]) -> Vec<(AccountId, VoteWeight)> {
+		ExtBuilder::default().build().execute_with(|| {
+			set_active_stake(20, stake);
+			bond(21, stake);
+			nominate(21, vec![10]);
+			for who in order {
+				StakeTracker::on_nominator_update(&who, vec![]);
+			}
+			let mut voters = voter_scores();
+			voters.sort_by_key(|(who, score)| (sp_std::cmp::Reverse(*score), *who));
+			voters
+		})
+	}
+
+	#[test]
+	fn disabled_by_default() {
+		assert_eq!(voters_by_score(50, [20, 21]), vec![(20, 50), (21, 50)]);
+		assert_eq!(voters_by_score(50, [21, 20]), vec![(20, 50), (21, 50)]);
+	}
+
+	#[test]
+	fn equal_stakes_are_ordered_reproducibly() {
+		VoterTieBreak::set(true);
+		let voters = voters_by_score(1 << 20, [20, 21]);
+		assert_ne!(voters[0].1, voters[1].1);
+		assert_eq!(voters_by_score(1 << 20, [21, 20]), voters);
+
+		// only the low bits are used to break ties.
+		let mask = (1 << TIE_BREAK_BITS) - 1;
+		ExtBuilder::default().build_and_execute(|| {
+			let vote = StakeTracker::to_vote(1 << 40);
+			assert_eq!(StakeTracker::voter_score(&20, 1 << 40) & !mask, vote);
+		});
+	}
+
+	#[test]
+	fn small_scores_are_kept() {
+		VoterTieBreak::set(true);
+		assert_eq!(voters_by_score(50, [20, 21]), vec![(20, 50), (21, 50)]);
+
+		MinVoteScore::set(100);
+		ExtBuilder::default().build_and_execute(|| {
+			assert_eq!(StakeTracker::voter_score(&20, 1), 100);
+			assert_eq!(StakeTracker::voter_score(&20, (1 << TIE_BREAK_BITS) - 1), 65535);
+			assert!(StakeTracker::voter_score(&20, 1 << TIE_BREAK_BITS) >= 1 << TIE_BREAK_BITS);
+		});
+		MinVoteScore::set(1);
+	}
+}
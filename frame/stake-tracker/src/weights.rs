@@ -0,0 +1,282 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Autogenerated weights for pallet_stake_tracker
+//!
+//! THIS FILE WAS AUTO-GENERATED USING THE SUBSTRATE BENCHMARK CLI VERSION 4.0.0-dev
+//! DATE: 2023-09-05, STEPS: `50`, REPEAT: `20`, LOW RANGE: `[]`, HIGH RANGE: `[]`
+//! WORST CASE MAP SIZE: `1000000`
+//! HOSTNAME: `runner-e8ezs4ez-project-145-concurrent-0`, CPU: `Intel(R) Xeon(R) CPU @ 2.60GHz`
+//! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: Some("dev"), DB CACHE: 1024
+
+// Executed Command:
+// ./target/production/substrate
+// benchmark
+// pallet
+// --chain=dev
+// --steps=50
+// --repeat=20
+// --pallet=pallet_stake_tracker
+// --no-storage-info
+// --no-median-slopes
+// --no-min-squares
+// --extrinsic=*
+// --execution=wasm
+// --wasm-execution=compiled
+// --heap-pages=4096
+// --output=./frame/stake-tracker/src/weights.rs
+// --header=./HEADER-APACHE2
+// --template=./.maintain/frame-weight-template.hbs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+#![allow(missing_docs)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pallet_stake_tracker.
+pub trait WeightInfo {
+	fn reconcile_approval() -> Weight;
+	fn force_reconcile_target(v: u32, ) -> Weight;
+	fn force_set_approval() -> Weight;
+	fn flush_coalesced(n: u32, ) -> Weight;
+}
+
+/// Weights for pallet_stake_tracker using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: StakeTracker PendingApprovalReconciliation (r:1 w:1)
+	/// Proof: StakeTracker PendingApprovalReconciliation (max_values: Some(1), max_size: Some(1025), added: 1520, mode: MaxEncodedLen)
+	/// Storage: TargetList ListNodes (r:4 w:4)
+	/// Proof: TargetList ListNodes (max_values: None, max_size: Some(170), added: 2645, mode: MaxEncodedLen)
+	/// Storage: StakeTracker ApprovalStake (r:1 w:0)
+	/// Proof: StakeTracker ApprovalStake (max_values: None, max_size: Some(84), added: 2559, mode: MaxEncodedLen)
+	/// Storage: TargetList ListBags (r:2 w:2)
+	/// Proof: TargetList ListBags (max_values: None, max_size: Some(90), added: 2565, mode: MaxEncodedLen)
+	fn reconcile_approval() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1652`
+		//  Estimated: `11570`
+		// Minimum execution time: 45_213_000 picoseconds.
+		Weight::from_parts(46_820_000, 11570)
+			.saturating_add(T::DbWeight::get().reads(8_u64))
+			.saturating_add(T::DbWeight::get().writes(7_u64))
+	}
+	/// Storage: Staking Bonded (r:1001 w:0)
+	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
+	/// Storage: VoterList ListBags (r:1 w:0)
+	/// Proof: VoterList ListBags (max_values: None, max_size: Some(82), added: 2557, mode: MaxEncodedLen)
+	/// Storage: VoterList ListNodes (r:1000 w:0)
+	/// Proof: VoterList ListNodes (max_values: None, max_size: Some(154), added: 2629, mode: MaxEncodedLen)
+	/// Storage: StakeTracker LazyGenesisQueue (r:1000 w:0)
+	/// Proof: StakeTracker LazyGenesisQueue (max_values: None, max_size: Some(40), added: 2515, mode: MaxEncodedLen)
+	/// Storage: Staking Nominators (r:1000 w:0)
+	/// Proof: Staking Nominators (max_values: None, max_size: Some(558), added: 3033, mode: MaxEncodedLen)
+	/// Storage: Staking Ledger (r:1000 w:0)
+	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
+	/// Storage: StakeTracker ApprovalStake (r:1 w:1)
+	/// Proof: StakeTracker ApprovalStake (max_values: None, max_size: Some(84), added: 2559, mode: MaxEncodedLen)
+	/// Storage: StakeTracker NominatorCount (r:1 w:1)
+	/// Proof: StakeTracker NominatorCount (max_values: None, max_size: Some(44), added: 2519, mode: MaxEncodedLen)
+	/// Storage: TargetList ListNodes (r:4 w:4)
+	/// Proof: TargetList ListNodes (max_values: None, max_size: Some(170), added: 2645, mode: MaxEncodedLen)
+	/// Storage: StakeTracker PendingApprovalReconciliation (r:1 w:1)
+	/// Proof: StakeTracker PendingApprovalReconciliation (max_values: Some(1), max_size: Some(1025), added: 1520, mode: MaxEncodedLen)
+	/// Storage: TargetList ListBags (r:2 w:2)
+	/// Proof: TargetList ListBags (max_values: None, max_size: Some(90), added: 2565, mode: MaxEncodedLen)
+	/// The range of component `v` is `[0, 1000]`.
+	fn force_reconcile_target(v: u32, ) -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1873 + v * (571 ±0)`
+		//  Estimated: `11570 + v * (3566 ±0)`
+		// Minimum execution time: 61_407_000 picoseconds.
+		Weight::from_parts(62_915_371, 11570)
+			// Standard Error: 9_847
+			.saturating_add(Weight::from_parts(31_245_682, 0).saturating_mul(v.into()))
+			.saturating_add(T::DbWeight::get().reads(11_u64))
+			.saturating_add(T::DbWeight::get().reads((5_u64).saturating_mul(v.into())))
+			.saturating_add(T::DbWeight::get().writes(9_u64))
+			.saturating_add(Weight::from_parts(0, 3566).saturating_mul(v.into()))
+	}
+	/// Storage: StakeTracker ApprovalStake (r:1 w:1)
+	/// Proof: StakeTracker ApprovalStake (max_values: None, max_size: Some(84), added: 2559, mode: MaxEncodedLen)
+	/// Storage: TargetList ListNodes (r:4 w:4)
+	/// Proof: TargetList ListNodes (max_values: None, max_size: Some(170), added: 2645, mode: MaxEncodedLen)
+	/// Storage: StakeTracker PendingApprovalReconciliation (r:1 w:1)
+	/// Proof: StakeTracker PendingApprovalReconciliation (max_values: Some(1), max_size: Some(1025), added: 1520, mode: MaxEncodedLen)
+	/// Storage: TargetList ListBags (r:2 w:2)
+	/// Proof: TargetList ListBags (max_values: None, max_size: Some(90), added: 2565, mode: MaxEncodedLen)
+	fn force_set_approval() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `2984`
+		//  Estimated: `11570`
+		// Minimum execution time: 52_661_000 picoseconds.
+		Weight::from_parts(54_108_000, 11570)
+			.saturating_add(T::DbWeight::get().reads(8_u64))
+			.saturating_add(T::DbWeight::get().writes(8_u64))
+	}
+	/// Storage: StakeTracker CoalescedVoters (r:1001 w:1000)
+	/// Proof: StakeTracker CoalescedVoters (max_values: None, max_size: Some(40), added: 2515, mode: MaxEncodedLen)
+	/// Storage: StakeTracker CounterForCoalescedVoters (r:1 w:1)
+	/// Proof: StakeTracker CounterForCoalescedVoters (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
+	/// Storage: Staking Bonded (r:1000 w:0)
+	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
+	/// Storage: Staking Ledger (r:1000 w:0)
+	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
+	/// Storage: VoterList ListNodes (r:1000 w:1000)
+	/// Proof: VoterList ListNodes (max_values: None, max_size: Some(154), added: 2629, mode: MaxEncodedLen)
+	/// Storage: StakeTracker CoalescedTargets (r:1001 w:1000)
+	/// Proof: StakeTracker CoalescedTargets (max_values: None, max_size: Some(40), added: 2515, mode: MaxEncodedLen)
+	/// Storage: StakeTracker CounterForCoalescedTargets (r:1 w:1)
+	/// Proof: StakeTracker CounterForCoalescedTargets (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
+	/// Storage: StakeTracker ApprovalStake (r:1000 w:0)
+	/// Proof: StakeTracker ApprovalStake (max_values: None, max_size: Some(84), added: 2559, mode: MaxEncodedLen)
+	/// Storage: EvictedTargetList ListNodes (r:1000 w:0)
+	/// Proof: EvictedTargetList ListNodes (max_values: None, max_size: Some(170), added: 2645, mode: MaxEncodedLen)
+	/// Storage: TargetList ListNodes (r:4000 w:4000)
+	/// Proof: TargetList ListNodes (max_values: None, max_size: Some(170), added: 2645, mode: MaxEncodedLen)
+	/// Storage: TargetList ListBags (r:2000 w:2000)
+	/// Proof: TargetList ListBags (max_values: None, max_size: Some(90), added: 2565, mode: MaxEncodedLen)
+	/// The range of component `n` is `[0, 1000]`.
+	fn flush_coalesced(n: u32, ) -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `168 + n * (1262 ±0)`
+		//  Estimated: `3517 + n * (10580 ±0)`
+		// Minimum execution time: 6_932_000 picoseconds.
+		Weight::from_parts(7_184_000, 3517)
+			// Standard Error: 41_603
+			.saturating_add(Weight::from_parts(98_752_914, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().reads((13_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+			.saturating_add(T::DbWeight::get().writes((9_u64).saturating_mul(n.into())))
+			.saturating_add(Weight::from_parts(0, 10580).saturating_mul(n.into()))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: StakeTracker PendingApprovalReconciliation (r:1 w:1)
+	/// Proof: StakeTracker PendingApprovalReconciliation (max_values: Some(1), max_size: Some(1025), added: 1520, mode: MaxEncodedLen)
+	/// Storage: TargetList ListNodes (r:4 w:4)
+	/// Proof: TargetList ListNodes (max_values: None, max_size: Some(170), added: 2645, mode: MaxEncodedLen)
+	/// Storage: StakeTracker ApprovalStake (r:1 w:0)
+	/// Proof: StakeTracker ApprovalStake (max_values: None, max_size: Some(84), added: 2559, mode: MaxEncodedLen)
+	/// Storage: TargetList ListBags (r:2 w:2)
+	/// Proof: TargetList ListBags (max_values: None, max_size: Some(90), added: 2565, mode: MaxEncodedLen)
+	fn reconcile_approval() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1652`
+		//  Estimated: `11570`
+		// Minimum execution time: 45_213_000 picoseconds.
+		Weight::from_parts(46_820_000, 11570)
+			.saturating_add(RocksDbWeight::get().reads(8_u64))
+			.saturating_add(RocksDbWeight::get().writes(7_u64))
+	}
+	/// Storage: Staking Bonded (r:1001 w:0)
+	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
+	/// Storage: VoterList ListBags (r:1 w:0)
+	/// Proof: VoterList ListBags (max_values: None, max_size: Some(82), added: 2557, mode: MaxEncodedLen)
+	/// Storage: VoterList ListNodes (r:1000 w:0)
+	/// Proof: VoterList ListNodes (max_values: None, max_size: Some(154), added: 2629, mode: MaxEncodedLen)
+	/// Storage: StakeTracker LazyGenesisQueue (r:1000 w:0)
+	/// Proof: StakeTracker LazyGenesisQueue (max_values: None, max_size: Some(40), added: 2515, mode: MaxEncodedLen)
+	/// Storage: Staking Nominators (r:1000 w:0)
+	/// Proof: Staking Nominators (max_values: None, max_size: Some(558), added: 3033, mode: MaxEncodedLen)
+	/// Storage: Staking Ledger (r:1000 w:0)
+	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
+	/// Storage: StakeTracker ApprovalStake (r:1 w:1)
+	/// Proof: StakeTracker ApprovalStake (max_values: None, max_size: Some(84), added: 2559, mode: MaxEncodedLen)
+	/// Storage: StakeTracker NominatorCount (r:1 w:1)
+	/// Proof: StakeTracker NominatorCount (max_values: None, max_size: Some(44), added: 2519, mode: MaxEncodedLen)
+	/// Storage: TargetList ListNodes (r:4 w:4)
+	/// Proof: TargetList ListNodes (max_values: None, max_size: Some(170), added: 2645, mode: MaxEncodedLen)
+	/// Storage: StakeTracker PendingApprovalReconciliation (r:1 w:1)
+	/// Proof: StakeTracker PendingApprovalReconciliation (max_values: Some(1), max_size: Some(1025), added: 1520, mode: MaxEncodedLen)
+	/// Storage: TargetList ListBags (r:2 w:2)
+	/// Proof: TargetList ListBags (max_values: None, max_size: Some(90), added: 2565, mode: MaxEncodedLen)
+	/// The range of component `v` is `[0, 1000]`.
+	fn force_reconcile_target(v: u32, ) -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1873 + v * (571 ±0)`
+		//  Estimated: `11570 + v * (3566 ±0)`
+		// Minimum execution time: 61_407_000 picoseconds.
+		Weight::from_parts(62_915_371, 11570)
+			// Standard Error: 9_847
+			.saturating_add(Weight::from_parts(31_245_682, 0).saturating_mul(v.into()))
+			.saturating_add(RocksDbWeight::get().reads(11_u64))
+			.saturating_add(RocksDbWeight::get().reads((5_u64).saturating_mul(v.into())))
+			.saturating_add(RocksDbWeight::get().writes(9_u64))
+			.saturating_add(Weight::from_parts(0, 3566).saturating_mul(v.into()))
+	}
+	/// Storage: StakeTracker ApprovalStake (r:1 w:1)
+	/// Proof: StakeTracker ApprovalStake (max_values: None, max_size: Some(84), added: 2559, mode: MaxEncodedLen)
+	/// Storage: TargetList ListNodes (r:4 w:4)
+	/// Proof: TargetList ListNodes (max_values: None, max_size: Some(170), added: 2645, mode: MaxEncodedLen)
+	/// Storage: StakeTracker PendingApprovalReconciliation (r:1 w:1)
+	/// Proof: StakeTracker PendingApprovalReconciliation (max_values: Some(1), max_size: Some(1025), added: 1520, mode: MaxEncodedLen)
+	/// Storage: TargetList ListBags (r:2 w:2)
+	/// Proof: TargetList ListBags (max_values: None, max_size: Some(90), added: 2565, mode: MaxEncodedLen)
+	fn force_set_approval() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `2984`
+		//  Estimated: `11570`
+		// Minimum execution time: 52_661_000 picoseconds.
+		Weight::from_parts(54_108_000, 11570)
+			.saturating_add(RocksDbWeight::get().reads(8_u64))
+			.saturating_add(RocksDbWeight::get().writes(8_u64))
+	}
+	/// Storage: StakeTracker CoalescedVoters (r:1001 w:1000)
+	/// Proof: StakeTracker CoalescedVoters (max_values: None, max_size: Some(40), added: 2515, mode: MaxEncodedLen)
+	/// Storage: StakeTracker CounterForCoalescedVoters (r:1 w:1)
+	/// Proof: StakeTracker CounterForCoalescedVoters (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
+	/// Storage: Staking Bonded (r:1000 w:0)
+	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
+	/// Storage: Staking Ledger (r:1000 w:0)
+	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
+	/// Storage: VoterList ListNodes (r:1000 w:1000)
+	/// Proof: VoterList ListNodes (max_values: None, max_size: Some(154), added: 2629, mode: MaxEncodedLen)
+	/// Storage: StakeTracker CoalescedTargets (r:1001 w:1000)
+	/// Proof: StakeTracker CoalescedTargets (max_values: None, max_size: Some(40), added: 2515, mode: MaxEncodedLen)
+	/// Storage: StakeTracker CounterForCoalescedTargets (r:1 w:1)
+	/// Proof: StakeTracker CounterForCoalescedTargets (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
+	/// Storage: StakeTracker ApprovalStake (r:1000 w:0)
+	/// Proof: StakeTracker ApprovalStake (max_values: None, max_size: Some(84), added: 2559, mode: MaxEncodedLen)
+	/// Storage: EvictedTargetList ListNodes (r:1000 w:0)
+	/// Proof: EvictedTargetList ListNodes (max_values: None, max_size: Some(170), added: 2645, mode: MaxEncodedLen)
+	/// Storage: TargetList ListNodes (r:4000 w:4000)
+	/// Proof: TargetList ListNodes (max_values: None, max_size: Some(170), added: 2645, mode: MaxEncodedLen)
+	/// Storage: TargetList ListBags (r:2000 w:2000)
+	/// Proof: TargetList ListBags (max_values: None, max_size: Some(90), added: 2565, mode: MaxEncodedLen)
+	/// The range of component `n` is `[0, 1000]`.
+	fn flush_coalesced(n: u32, ) -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `168 + n * (1262 ±0)`
+		//  Estimated: `3517 + n * (10580 ±0)`
+		// Minimum execution time: 6_932_000 picoseconds.
+		Weight::from_parts(7_184_000, 3517)
+			// Standard Error: 41_603
+			.saturating_add(Weight::from_parts(98_752_914, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+			.saturating_add(RocksDbWeight::get().reads((13_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+			.saturating_add(RocksDbWeight::get().writes((9_u64).saturating_mul(n.into())))
+			.saturating_add(Weight::from_parts(0, 10580).saturating_mul(n.into()))
+	}
+}
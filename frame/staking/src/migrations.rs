@@ -57,6 +57,114 @@ impl Default for ObsoleteReleases {
 #[storage_alias]
 type StorageVersion<T: Config> = StorageValue<Pallet<T>, ObsoleteReleases, ValueQuery>;
 
+pub mod v14 {
+	use super::*;
+	use frame_support::{
+		migrations::{SteppedMigration, SteppedMigrationError},
+		weights::WeightMeter,
+	};
+
+	/// Starts the initialization of [`TotalActiveStake`] from the existing ledgers.
+	///
+	/// Summing the active stake of all ledgers is not bounded, so it is only scheduled here: the
+	/// ledgers are then scanned in the following blocks, see [`InitTotalActiveStake`].
+	pub struct MigrateToV14<T>(sp_std::marker::PhantomData<T>);
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV14<T> {
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() == 13,
+				"Required v13 before upgrading to v14"
+			);
+
+			Ok(Default::default())
+		}
+
+		fn on_runtime_upgrade() -> Weight {
+			let current = Pallet::<T>::current_storage_version();
+			let onchain = Pallet::<T>::on_chain_storage_version();
+
+			if current == 14 && onchain == 13 {
+				TotalActiveStake::<T>::kill();
+				TotalActiveStakeCursor::<T>::put(None::<T::AccountId>);
+				current.put::<Pallet<T>>();
+
+				log!(info, "v14 applied successfully, initializing the total active stake");
+				T::DbWeight::get().reads_writes(1, 3)
+			} else {
+				log!(warn, "Skipping v14, should be removed");
+				T::DbWeight::get().reads(1)
+			}
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() == 14,
+				"v14 not applied"
+			);
+
+			frame_support::ensure!(
+				TotalActiveStakeCursor::<T>::exists(),
+				"TotalActiveStake initialization not started"
+			);
+
+			Ok(())
+		}
+	}
+
+	/// Adds the active stake of the ledgers to [`TotalActiveStake`], as many per step as fit in
+	/// the weight given, in their storage order.
+	///
+	/// The cursor is the controller of the last ledger accounted for. Driven by the `on_idle` hook
+	/// of the pallet for as long as [`TotalActiveStakeCursor`] is set.
+	pub struct InitTotalActiveStake<T>(sp_std::marker::PhantomData<T>);
+	impl<T: Config> SteppedMigration for InitTotalActiveStake<T> {
+		type Cursor = T::AccountId;
+		type Identifier = u16;
+
+		fn id() -> Self::Identifier {
+			14
+		}
+
+		fn step(
+			cursor: Option<Self::Cursor>,
+			meter: &mut WeightMeter,
+		) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+			// reading and writing the total, and reading each ledger.
+			let base = T::DbWeight::get().reads_writes(1, 1);
+			let per_ledger = T::DbWeight::get().reads(1);
+			let required = base.saturating_add(per_ledger);
+			if !meter.can_consume(required) {
+				return Err(SteppedMigrationError::InsufficientWeight { required })
+			}
+			meter.consume(base);
+
+			let mut ledgers = match cursor {
+				Some(ref last) => Ledger::<T>::iter_from(Ledger::<T>::hashed_key_for(last)),
+				None => Ledger::<T>::iter(),
+			};
+			let mut total = TotalActiveStake::<T>::get();
+			let mut last = cursor;
+			let done = loop {
+				if meter.try_consume(per_ledger).is_err() {
+					break false
+				}
+				match ledgers.next() {
+					Some((controller, ledger)) => {
+						total = total.saturating_add(ledger.active);
+						last = Some(controller);
+					},
+					None => break true,
+				}
+			};
+			TotalActiveStake::<T>::put(total);
+
+			Ok(if done { None } else { last })
+		}
+	}
+}
+
 pub mod v13 {
 	use super::*;
 
@@ -39,7 +39,10 @@ use sp_runtime::{
 	traits::{IdentityLookup, Zero},
 	BuildStorage,
 };
-use sp_staking::offence::{DisableStrategy, OffenceDetails, OnOffenceHandler};
+use sp_staking::{
+	offence::{DisableStrategy, OffenceDetails, OnOffenceHandler},
+	Stake,
+};
 
 pub const INIT_TIMESTAMP: u64 = 30_000;
 pub const BLOCK_TIME: u64 = 1000;
@@ -270,6 +273,7 @@ parameter_types! {
 	pub static LedgerSlashPerEra:
 		(BalanceOf<Test>, BTreeMap<EraIndex, BalanceOf<Test>>) =
 		(Zero::zero(), BTreeMap::new());
+	pub static WithdrawnUnbonded: Vec<(AccountId, Vec<AccountId>, Stake<Balance>)> = vec![];
 }
 
 pub struct EventListenerMock;
@@ -281,6 +285,14 @@ impl OnStakingUpdate<AccountId, Balance> for EventListenerMock {
 	) {
 		LedgerSlashPerEra::set((slashed_bonded, slashed_chunks.clone()));
 	}
+
+	fn on_withdraw_unbonded(
+		who: &AccountId,
+		nominations: Vec<AccountId>,
+		prev_stake: Stake<Balance>,
+	) {
+		WithdrawnUnbonded::mutate(|w| w.push((*who, nominations, prev_stake)));
+	}
 }
 
 impl crate::pallet::pallet::Config for Test {
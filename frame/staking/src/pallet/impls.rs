@@ -25,12 +25,13 @@ use frame_election_provider_support::{
 use frame_support::{
 	defensive,
 	dispatch::WithPostDispatchInfo,
+	migrations::{SteppedMigration, SteppedMigrationError},
 	pallet_prelude::*,
 	traits::{
 		Currency, Defensive, DefensiveResult, EstimateNextNewSession, Get, Imbalance,
 		LockableCurrency, OnUnbalanced, TryCollect, UnixTime, WithdrawReasons,
 	},
-	weights::Weight,
+	weights::{Weight, WeightMeter},
 };
 use frame_system::{pallet_prelude::BlockNumberFor, RawOrigin};
 use pallet_session::historical;
@@ -41,15 +42,15 @@ use sp_runtime::{
 use sp_staking::{
 	currency_to_vote::CurrencyToVote,
 	offence::{DisableStrategy, OffenceDetails, OnOffenceHandler},
-	EraIndex, SessionIndex, Stake, StakingInterface,
+	EraIndex, OnStakingUpdate, SessionIndex, Stake, StakingInterface,
 };
 use sp_std::prelude::*;
 
 use crate::{
-	election_size_tracker::StaticTracker, log, slashing, weights::WeightInfo, ActiveEraInfo,
-	BalanceOf, EraPayout, Exposure, ExposureOf, Forcing, IndividualExposure, MaxNominationsOf,
-	MaxWinnersOf, Nominations, NominationsQuota, PositiveImbalanceOf, RewardDestination,
-	SessionInterface, StakingLedger, ValidatorPrefs,
+	election_size_tracker::StaticTracker, log, migrations, slashing, weights::WeightInfo,
+	ActiveEraInfo, BalanceOf, EraPayout, Exposure, ExposureOf, Forcing, IndividualExposure,
+	MaxNominationsOf, MaxWinnersOf, Nominations, NominationsQuota, PositiveImbalanceOf,
+	RewardDestination, SessionInterface, StakingLedger, ValidatorPrefs,
 };
 
 use super::{pallet::*, STAKING_ID};
@@ -116,6 +117,10 @@ impl<T: Config> Pallet<T> {
 				// This account must have called `unbond()` with some value that caused the active
 				// portion to fall below existential deposit + will have no more unlocking chunks
 				// left. We can now safely remove all staking-related information.
+				// Listeners can't fetch the nominations and stake once the stash is killed.
+				let nominations = Self::nominations(&stash).unwrap_or_default();
+				let prev_stake = Stake { total: old_total, active: ledger.active };
+				T::EventListeners::on_withdraw_unbonded(&stash, nominations, prev_stake);
 				Self::kill_stash(&stash, num_slashing_spans)?;
 				// Remove the lock.
 				T::Currency::remove_lock(STAKING_ID, &stash);
@@ -123,7 +128,8 @@ impl<T: Config> Pallet<T> {
 				T::WeightInfo::withdraw_unbonded_kill(num_slashing_spans)
 			} else {
 				// This was the consequence of a partial unbond. just update the ledger and move on.
-				Self::update_ledger(&controller, &ledger);
+				// Consolidating the unlocked chunks left the active stake as it was.
+				Self::update_ledger(&controller, &ledger, ledger.active);
 
 				// This is only an update, so we use less overall weight.
 				T::WeightInfo::withdraw_unbonded_update(num_slashing_spans)
@@ -276,12 +282,78 @@ impl<T: Config> Pallet<T> {
 
 	/// Update the ledger for a controller.
 	///
-	/// This will also update the stash lock.
-	pub(crate) fn update_ledger(controller: &T::AccountId, ledger: &StakingLedger<T>) {
+	/// This will also update the stash lock and [`TotalActiveStake`], given the active stake of
+	/// the ledger prior to the update, `prev_active`, or zero if it is new.
+	pub(crate) fn update_ledger(
+		controller: &T::AccountId,
+		ledger: &StakingLedger<T>,
+		prev_active: BalanceOf<T>,
+	) {
 		T::Currency::set_lock(STAKING_ID, &ledger.stash, ledger.total, WithdrawReasons::all());
+		if Self::is_active_stake_counted(controller) {
+			TotalActiveStake::<T>::mutate(|total| {
+				*total = total.saturating_sub(prev_active).saturating_add(ledger.active)
+			});
+		}
 		<Ledger<T>>::insert(controller, ledger);
 	}
 
+	/// Whether the active stake of the ledger of `controller` is part of [`TotalActiveStake`].
+	///
+	/// Not the case for the ledgers that its initialization has not reached yet: their stake is
+	/// added once reached, as it is then.
+	fn is_active_stake_counted(controller: &T::AccountId) -> bool {
+		match TotalActiveStakeCursor::<T>::get() {
+			None => true,
+			Some(None) => false,
+			Some(Some(last)) =>
+				Ledger::<T>::hashed_key_for(controller) <= Ledger::<T>::hashed_key_for(last),
+		}
+	}
+
+	/// Moves the ledger of the controller `from`, if any, to the controller `to`.
+	///
+	/// Its active stake is added to or removed from [`TotalActiveStake`] if the move brings it in
+	/// or out of what the initialization of the total has reached.
+	pub(crate) fn move_ledger(from: &T::AccountId, to: &T::AccountId) {
+		if let Some(ledger) = <Ledger<T>>::take(from) {
+			match (Self::is_active_stake_counted(from), Self::is_active_stake_counted(to)) {
+				(true, false) => TotalActiveStake::<T>::mutate(|total| {
+					*total = total.saturating_sub(ledger.active)
+				}),
+				(false, true) => TotalActiveStake::<T>::mutate(|total| {
+					*total = total.saturating_add(ledger.active)
+				}),
+				_ => (),
+			}
+			<Ledger<T>>::insert(to, ledger);
+		}
+	}
+
+	/// Makes progress on the initialization of [`TotalActiveStake`], if it is ongoing, within
+	/// `limit`.
+	pub(crate) fn do_init_total_active_stake(limit: Weight) -> Weight {
+		let mut meter = WeightMeter::from_limit(limit);
+		// reading and writing the cursor.
+		if meter.try_consume(T::DbWeight::get().reads_writes(1, 1)).is_err() {
+			return Weight::zero()
+		}
+		let Some(cursor) = TotalActiveStakeCursor::<T>::get() else {
+			return T::DbWeight::get().reads(1)
+		};
+
+		match migrations::v14::InitTotalActiveStake::<T>::step(cursor, &mut meter) {
+			Ok(Some(last)) => TotalActiveStakeCursor::<T>::put(Some(last)),
+			Ok(None) => {
+				TotalActiveStakeCursor::<T>::kill();
+				log!(info, "total active stake initialized: {:?}", TotalActiveStake::<T>::get());
+			},
+			Err(SteppedMigrationError::InsufficientWeight { .. }) => (),
+			Err(err) => defensive!("initializing the total active stake failed", err),
+		}
+		meter.consumed()
+	}
+
 	/// Chill a stash account.
 	pub(crate) fn chill_stash(stash: &T::AccountId) {
 		let chilled_as_validator = Self::do_remove_validator(stash);
@@ -302,10 +374,11 @@ impl<T: Config> Pallet<T> {
 			RewardDestination::Staked => Self::bonded(stash)
 				.and_then(|c| Self::ledger(&c).map(|l| (c, l)))
 				.and_then(|(controller, mut l)| {
+					let prev_active = l.active;
 					l.active += amount;
 					l.total += amount;
 					let r = T::Currency::deposit_into_existing(stash, amount).ok();
-					Self::update_ledger(&controller, &l);
+					Self::update_ledger(&controller, &l, prev_active);
 					r
 				}),
 			RewardDestination::Account(dest_account) =>
@@ -664,7 +737,11 @@ impl<T: Config> Pallet<T> {
 		slashing::clear_stash_metadata::<T>(stash, num_slashing_spans)?;
 
 		<Bonded<T>>::remove(stash);
-		<Ledger<T>>::remove(&controller);
+		if let Some(ledger) = <Ledger<T>>::take(&controller) {
+			if Self::is_active_stake_counted(&controller) {
+				TotalActiveStake::<T>::mutate(|total| *total = total.saturating_sub(ledger.active));
+			}
+		}
 
 		<Payee<T>>::remove(stash);
 		Self::do_remove_validator(stash);
@@ -1154,6 +1231,8 @@ impl<T: Config> ElectionDataProvider for Pallet<T> {
 		<Bonded<T>>::remove_all(None);
 		#[allow(deprecated)]
 		<Ledger<T>>::remove_all(None);
+		TotalActiveStake::<T>::kill();
+		TotalActiveStakeCursor::<T>::kill();
 		#[allow(deprecated)]
 		<Validators<T>>::remove_all();
 		#[allow(deprecated)]
@@ -1656,6 +1735,12 @@ impl<T: Config> StakingInterface for Pallet<T> {
 		})
 	}
 
+	fn active_era_exposure(validator: &Self::AccountId) -> Option<Self::Balance> {
+		let era = ActiveEra::<T>::get()?.index;
+		ErasStakers::<T>::contains_key(era, validator)
+			.then(|| ErasStakers::<T>::get(era, validator).total)
+	}
+
 	fn bonding_duration() -> EraIndex {
 		T::BondingDuration::get()
 	}
@@ -1671,6 +1756,10 @@ impl<T: Config> StakingInterface for Pallet<T> {
 			.ok_or(Error::<T>::NotStash.into())
 	}
 
+	fn total_active_stake() -> BalanceOf<T> {
+		TotalActiveStake::<T>::get()
+	}
+
 	fn bond_extra(who: &Self::AccountId, extra: Self::Balance) -> DispatchResult {
 		Self::bond_extra(RawOrigin::Signed(who.clone()).into(), extra)
 	}
@@ -65,7 +65,7 @@ pub mod pallet {
 	use super::*;
 
 	/// The current storage version.
-	const STORAGE_VERSION: StorageVersion = StorageVersion::new(13);
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(14);
 
 	#[pallet::pallet]
 	#[pallet::storage_version(STORAGE_VERSION)]
@@ -310,6 +310,21 @@ pub mod pallet {
 	#[pallet::storage]
 	pub type MinimumActiveStake<T> = StorageValue<_, BalanceOf<T>, ValueQuery>;
 
+	/// The sum of the active stake of all ledgers.
+	///
+	/// Kept up to date whenever a ledger is updated or removed. Only covers the ledgers reached so
+	/// far while [`TotalActiveStakeCursor`] is set.
+	#[pallet::storage]
+	pub type TotalActiveStake<T> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+	/// The progress of the initialization of [`TotalActiveStake`], while it is ongoing.
+	///
+	/// Holds the controller of the last ledger whose active stake has been accounted for, in
+	/// storage order, or `None` if none has yet. See [`crate::migrations::v14`].
+	#[pallet::storage]
+	pub type TotalActiveStakeCursor<T: Config> =
+		StorageValue<_, Option<T::AccountId>, OptionQuery>;
+
 	/// The minimum amount of commission that validators can set.
 	///
 	/// If set to `0`, no limit exists.
@@ -769,6 +784,10 @@ pub mod pallet {
 			T::DbWeight::get().reads(1)
 		}
 
+		fn on_idle(_now: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			Self::do_init_total_active_stake(remaining_weight)
+		}
+
 		fn on_finalize(_n: BlockNumberFor<T>) {
 			// Set the start of the first era.
 			if let Some(mut active_era) = Self::active_era() {
@@ -878,7 +897,7 @@ pub mod pallet {
 					// satisfied.
 					.defensive_map_err(|_| Error::<T>::BoundNotMet)?,
 			};
-			Self::update_ledger(&controller_to_be_deprecated, &item);
+			Self::update_ledger(&controller_to_be_deprecated, &item, Zero::zero());
 			Ok(())
 		}
 
@@ -908,6 +927,7 @@ pub mod pallet {
 			let mut ledger = Self::ledger(&controller).ok_or(Error::<T>::NotController)?;
 
 			let stash_balance = T::Currency::free_balance(&stash);
+			let prev_active = ledger.active;
 			if let Some(extra) = stash_balance.checked_sub(&ledger.total) {
 				let extra = extra.min(max_additional);
 				ledger.total += extra;
@@ -919,7 +939,7 @@ pub mod pallet {
 				);
 
 				// NOTE: ledger must be updated prior to calling `Self::weight_of`.
-				Self::update_ledger(&controller, &ledger);
+				Self::update_ledger(&controller, &ledger, prev_active);
 				// update this staker in the sorted list, if they exist in it.
 				if T::VoterList::contains(&stash) {
 					let _ =
@@ -986,6 +1006,7 @@ pub mod pallet {
 			);
 
 			if !value.is_zero() {
+				let prev_active = ledger.active;
 				ledger.active -= value;
 
 				// Avoid there being a dust balance left in the staking system.
@@ -1020,7 +1041,7 @@ pub mod pallet {
 						.map_err(|_| Error::<T>::NoMoreChunks)?;
 				};
 				// NOTE: ledger must be updated prior to calling `Self::weight_of`.
-				Self::update_ledger(&controller, &ledger);
+				Self::update_ledger(&controller, &ledger, prev_active);
 
 				// update this staker in the sorted list, if they exist in it.
 				if T::VoterList::contains(&ledger.stash) {
@@ -1253,9 +1274,7 @@ pub mod pallet {
 			}
 			if old_controller != stash {
 				<Bonded<T>>::insert(&stash, &stash);
-				if let Some(l) = <Ledger<T>>::take(&old_controller) {
-					<Ledger<T>>::insert(&stash, l);
-				}
+				Self::move_ledger(&old_controller, &stash);
 			}
 			Ok(())
 		}
@@ -1502,6 +1521,7 @@ pub mod pallet {
 			ensure!(!ledger.unlocking.is_empty(), Error::<T>::NoUnlockChunk);
 
 			let initial_unlocking = ledger.unlocking.len() as u32;
+			let prev_active = ledger.active;
 			let (ledger, rebonded_value) = ledger.rebond(value);
 			// Last check: the new active amount of ledger must be more than ED.
 			ensure!(ledger.active >= T::Currency::minimum_balance(), Error::<T>::InsufficientBond);
@@ -1512,7 +1532,7 @@ pub mod pallet {
 			});
 
 			// NOTE: ledger must be updated prior to calling `Self::weight_of`.
-			Self::update_ledger(&controller, &ledger);
+			Self::update_ledger(&controller, &ledger, prev_active);
 			if T::VoterList::contains(&ledger.stash) {
 				let _ = T::VoterList::on_update(&ledger.stash, Self::weight_of(&ledger.stash))
 					.defensive();
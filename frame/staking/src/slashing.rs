@@ -607,6 +607,7 @@ pub fn do_slash<T: Config>(
 		None => return, // nothing to do.
 	};
 
+	let prev_active = ledger.active;
 	let value = ledger.slash(value, T::Currency::minimum_balance(), slash_era);
 
 	if !value.is_zero() {
@@ -618,7 +619,7 @@ pub fn do_slash<T: Config>(
 			*reward_payout = reward_payout.saturating_sub(missing);
 		}
 
-		<Pallet<T>>::update_ledger(&controller, &ledger);
+		<Pallet<T>>::update_ledger(&controller, &ledger, prev_active);
 
 		// trigger the event
 		<Pallet<T>>::deposit_event(super::Event::<T>::Slashed {
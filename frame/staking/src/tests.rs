@@ -1783,6 +1783,111 @@ fn reward_to_stake_works() {
 		});
 }
 
+#[test]
+fn total_active_stake_is_tracked() {
+	use sp_staking::StakingInterface;
+	let ledgers_active = || Ledger::<Test>::iter_values().map(|l| l.active).sum::<Balance>();
+
+	ExtBuilder::default().nominate(true).build_and_execute(|| {
+		// genesis stakers are accounted for.
+		assert!(<Staking as StakingInterface>::total_active_stake() > 0);
+		assert_eq!(<Staking as StakingInterface>::total_active_stake(), ledgers_active());
+
+		// bonding a new stash.
+		let _ = Balances::make_free_balance_be(&61, 1000);
+		assert_ok!(Staking::bond(RuntimeOrigin::signed(61), 500, RewardDestination::Staked));
+		assert_eq!(<Staking as StakingInterface>::total_active_stake(), ledgers_active());
+
+		// bonding extra.
+		assert_ok!(Staking::bond_extra(RuntimeOrigin::signed(61), 100));
+		assert_eq!(<Staking as StakingInterface>::total_active_stake(), ledgers_active());
+
+		// unbonding everything.
+		assert_ok!(Staking::unbond(RuntimeOrigin::signed(61), 600));
+		assert_eq!(<Staking as StakingInterface>::total_active_stake(), ledgers_active());
+
+		// withdrawing and thereby killing the stash.
+		mock::start_active_era(3);
+		assert_ok!(Staking::withdraw_unbonded(RuntimeOrigin::signed(61), 0));
+		assert!(!<Ledger<Test>>::contains_key(&61));
+		assert_eq!(<Staking as StakingInterface>::total_active_stake(), ledgers_active());
+	});
+}
+
+#[test]
+fn total_active_stake_is_initialized_over_blocks() {
+	use frame_support::traits::{Hooks, OnRuntimeUpgrade, StorageVersion};
+	use sp_staking::StakingInterface;
+	let ledgers_active = || Ledger::<Test>::iter_values().map(|l| l.active).sum::<Balance>();
+	let db = <Test as frame_system::Config>::DbWeight::get();
+
+	ExtBuilder::default().nominate(true).build_and_execute(|| {
+		assert!(Ledger::<Test>::iter().count() > 3);
+		StorageVersion::new(13).put::<Staking>();
+
+		// the upgrade itself only schedules the initialization.
+		assert_eq!(
+			migrations::v14::MigrateToV14::<Test>::on_runtime_upgrade(),
+			db.reads_writes(1, 3)
+		);
+		assert_eq!(StorageVersion::get::<Staking>(), 14);
+		assert_eq!(TotalActiveStakeCursor::<Test>::get(), Some(None));
+		assert_eq!(<Staking as StakingInterface>::total_active_stake(), 0);
+
+		// not enough weight to account for any ledger.
+		assert_eq!(Staking::on_idle(1, db.reads_writes(2, 2)), db.reads_writes(1, 1));
+		assert_eq!(TotalActiveStakeCursor::<Test>::get(), Some(None));
+
+		// two ledgers per block, while stakers keep bonding.
+		let mut steps = 0;
+		let limit = db.reads_writes(4, 2);
+		while TotalActiveStakeCursor::<Test>::exists() {
+			assert!(Staking::on_idle(1, limit).all_lte(limit));
+			steps += 1;
+
+			let stash = 200 + steps;
+			let _ = Balances::make_free_balance_be(&stash, 1000);
+			assert_ok!(Staking::bond(RuntimeOrigin::signed(stash), 500, RewardDestination::Staked));
+			assert_ok!(Staking::bond_extra(RuntimeOrigin::signed(21), 10));
+			assert_ok!(Staking::unbond(RuntimeOrigin::signed(stash), 100));
+		}
+
+		assert!(steps > 1);
+		assert_eq!(<Staking as StakingInterface>::total_active_stake(), ledgers_active());
+
+		// and it is kept up to date from then on.
+		assert_eq!(Staking::on_idle(1, limit), db.reads(1));
+		assert_ok!(Staking::bond_extra(RuntimeOrigin::signed(21), 10));
+		assert_eq!(<Staking as StakingInterface>::total_active_stake(), ledgers_active());
+	});
+}
+
+#[test]
+fn withdraw_unbonded_notifies_listeners() {
+	ExtBuilder::default().build_and_execute(|| {
+		let _ = Balances::make_free_balance_be(&61, 1000);
+		assert_ok!(Staking::bond(RuntimeOrigin::signed(61), 500, RewardDestination::Staked));
+		assert_ok!(Staking::nominate(RuntimeOrigin::signed(61), vec![11, 21]));
+		assert_ok!(Staking::unbond(RuntimeOrigin::signed(61), 200));
+		mock::start_active_era(3);
+
+		// a partial withdrawal keeps the stash around.
+		assert_ok!(Staking::withdraw_unbonded(RuntimeOrigin::signed(61), 0));
+		assert!(WithdrawnUnbonded::get().is_empty());
+
+		// the last stake is passed along once the stash is killed, nominators chill first.
+		assert_ok!(Staking::chill(RuntimeOrigin::signed(61)));
+		assert_ok!(Staking::unbond(RuntimeOrigin::signed(61), 300));
+		mock::start_active_era(6);
+		assert_ok!(Staking::withdraw_unbonded(RuntimeOrigin::signed(61), 0));
+		assert!(!<Ledger<Test>>::contains_key(&61));
+		assert_eq!(
+			WithdrawnUnbonded::take(),
+			vec![(61, vec![], sp_staking::Stake { total: 300, active: 0 })]
+		);
+	});
+}
+
 #[test]
 fn reap_stash_works() {
 	ExtBuilder::default()
@@ -99,14 +99,18 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Proof: Balances Freezes (max_values: None, max_size: Some(49), added: 2524, mode: MaxEncodedLen)
 	/// Storage: Staking Payee (r:0 w:1)
 	/// Proof: Staking Payee (max_values: None, max_size: Some(73), added: 2548, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	fn bond() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `1047`
 		//  Estimated: `4764`
 		// Minimum execution time: 53_983_000 picoseconds.
 		Weight::from_parts(55_296_000, 4764)
-			.saturating_add(T::DbWeight::get().reads(5_u64))
-			.saturating_add(T::DbWeight::get().writes(4_u64))
+			.saturating_add(T::DbWeight::get().reads(7_u64))
+			.saturating_add(T::DbWeight::get().writes(5_u64))
 	}
 	/// Storage: Staking Bonded (r:1 w:0)
 	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
@@ -120,14 +124,18 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Proof: VoterList ListNodes (max_values: None, max_size: Some(154), added: 2629, mode: MaxEncodedLen)
 	/// Storage: VoterList ListBags (r:2 w:2)
 	/// Proof: VoterList ListBags (max_values: None, max_size: Some(82), added: 2557, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	fn bond_extra() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `2028`
 		//  Estimated: `8877`
 		// Minimum execution time: 96_590_000 picoseconds.
 		Weight::from_parts(98_921_000, 8877)
-			.saturating_add(T::DbWeight::get().reads(9_u64))
-			.saturating_add(T::DbWeight::get().writes(7_u64))
+			.saturating_add(T::DbWeight::get().reads(11_u64))
+			.saturating_add(T::DbWeight::get().writes(8_u64))
 	}
 	/// Storage: Staking Ledger (r:1 w:1)
 	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
@@ -147,14 +155,18 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
 	/// Storage: VoterList ListBags (r:2 w:2)
 	/// Proof: VoterList ListBags (max_values: None, max_size: Some(82), added: 2557, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	fn unbond() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `2233`
 		//  Estimated: `8877`
 		// Minimum execution time: 99_901_000 picoseconds.
 		Weight::from_parts(102_919_000, 8877)
-			.saturating_add(T::DbWeight::get().reads(12_u64))
-			.saturating_add(T::DbWeight::get().writes(7_u64))
+			.saturating_add(T::DbWeight::get().reads(14_u64))
+			.saturating_add(T::DbWeight::get().writes(8_u64))
 	}
 	/// Storage: Staking Ledger (r:1 w:1)
 	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
@@ -164,6 +176,10 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Proof: Balances Locks (max_values: None, max_size: Some(1299), added: 3774, mode: MaxEncodedLen)
 	/// Storage: Balances Freezes (r:1 w:0)
 	/// Proof: Balances Freezes (max_values: None, max_size: Some(49), added: 2524, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `s` is `[0, 100]`.
 	fn withdraw_unbonded_update(s: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -173,8 +189,8 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 		Weight::from_parts(47_052_829, 4764)
 			// Standard Error: 1_044
 			.saturating_add(Weight::from_parts(43_887, 0).saturating_mul(s.into()))
-			.saturating_add(T::DbWeight::get().reads(4_u64))
-			.saturating_add(T::DbWeight::get().writes(2_u64))
+			.saturating_add(T::DbWeight::get().reads(6_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
 	}
 	/// Storage: Staking Ledger (r:1 w:1)
 	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
@@ -204,6 +220,10 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Proof: Staking Payee (max_values: None, max_size: Some(73), added: 2548, mode: MaxEncodedLen)
 	/// Storage: Staking SpanSlash (r:0 w:100)
 	/// Proof: Staking SpanSlash (max_values: None, max_size: Some(76), added: 2551, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `s` is `[0, 100]`.
 	fn withdraw_unbonded_kill(s: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -213,8 +233,8 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 		Weight::from_parts(104_772_163, 6248)
 			// Standard Error: 3_674
 			.saturating_add(Weight::from_parts(1_470_124, 0).saturating_mul(s.into()))
-			.saturating_add(T::DbWeight::get().reads(13_u64))
-			.saturating_add(T::DbWeight::get().writes(11_u64))
+			.saturating_add(T::DbWeight::get().reads(15_u64))
+			.saturating_add(T::DbWeight::get().writes(12_u64))
 			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(s.into())))
 			.saturating_add(Weight::from_parts(0, 4).saturating_mul(s.into()))
 	}
@@ -343,14 +363,18 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
 	/// Storage: Staking Ledger (r:2 w:2)
 	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	fn set_controller() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `907`
 		//  Estimated: `8122`
 		// Minimum execution time: 21_710_000 picoseconds.
 		Weight::from_parts(22_430_000, 8122)
-			.saturating_add(T::DbWeight::get().reads(3_u64))
-			.saturating_add(T::DbWeight::get().writes(3_u64))
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().writes(4_u64))
 	}
 	/// Storage: Staking ValidatorCount (r:0 w:1)
 	/// Proof: Staking ValidatorCount (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
@@ -433,6 +457,10 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Proof: Staking Payee (max_values: None, max_size: Some(73), added: 2548, mode: MaxEncodedLen)
 	/// Storage: Staking SpanSlash (r:0 w:100)
 	/// Proof: Staking SpanSlash (max_values: None, max_size: Some(76), added: 2551, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `s` is `[0, 100]`.
 	fn force_unstake(s: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -442,8 +470,8 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 		Weight::from_parts(95_688_129, 6248)
 			// Standard Error: 5_030
 			.saturating_add(Weight::from_parts(1_487_249, 0).saturating_mul(s.into()))
-			.saturating_add(T::DbWeight::get().reads(12_u64))
-			.saturating_add(T::DbWeight::get().writes(12_u64))
+			.saturating_add(T::DbWeight::get().reads(14_u64))
+			.saturating_add(T::DbWeight::get().writes(13_u64))
 			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(s.into())))
 			.saturating_add(Weight::from_parts(0, 4).saturating_mul(s.into()))
 	}
@@ -516,6 +544,10 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Proof: Balances Locks (max_values: None, max_size: Some(1299), added: 3774, mode: MaxEncodedLen)
 	/// Storage: Balances Freezes (r:257 w:0)
 	/// Proof: Balances Freezes (max_values: None, max_size: Some(49), added: 2524, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `n` is `[0, 256]`.
 	fn payout_stakers_alive_staked(n: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -525,10 +557,10 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 		Weight::from_parts(151_046_907, 30845)
 			// Standard Error: 41_899
 			.saturating_add(Weight::from_parts(49_837_804, 0).saturating_mul(n.into()))
-			.saturating_add(T::DbWeight::get().reads(11_u64))
-			.saturating_add(T::DbWeight::get().reads((6_u64).saturating_mul(n.into())))
-			.saturating_add(T::DbWeight::get().writes(3_u64))
-			.saturating_add(T::DbWeight::get().writes((3_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().reads(13_u64))
+			.saturating_add(T::DbWeight::get().reads((8_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes(4_u64))
+			.saturating_add(T::DbWeight::get().writes((4_u64).saturating_mul(n.into())))
 			.saturating_add(Weight::from_parts(0, 3774).saturating_mul(n.into()))
 	}
 	/// Storage: Staking Ledger (r:1 w:1)
@@ -543,6 +575,10 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
 	/// Storage: VoterList ListBags (r:2 w:2)
 	/// Proof: VoterList ListBags (max_values: None, max_size: Some(82), added: 2557, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `l` is `[1, 32]`.
 	fn rebond(l: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -552,8 +588,8 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 		Weight::from_parts(93_137_456, 8877)
 			// Standard Error: 4_799
 			.saturating_add(Weight::from_parts(54_421, 0).saturating_mul(l.into()))
-			.saturating_add(T::DbWeight::get().reads(9_u64))
-			.saturating_add(T::DbWeight::get().writes(7_u64))
+			.saturating_add(T::DbWeight::get().reads(11_u64))
+			.saturating_add(T::DbWeight::get().writes(8_u64))
 	}
 	/// Storage: Staking Bonded (r:1 w:1)
 	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
@@ -581,6 +617,10 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Proof: Staking Payee (max_values: None, max_size: Some(73), added: 2548, mode: MaxEncodedLen)
 	/// Storage: Staking SpanSlash (r:0 w:100)
 	/// Proof: Staking SpanSlash (max_values: None, max_size: Some(76), added: 2551, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `s` is `[1, 100]`.
 	fn reap_stash(s: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -590,8 +630,8 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 		Weight::from_parts(107_036_296, 6248)
 			// Standard Error: 3_935
 			.saturating_add(Weight::from_parts(1_465_860, 0).saturating_mul(s.into()))
-			.saturating_add(T::DbWeight::get().reads(12_u64))
-			.saturating_add(T::DbWeight::get().writes(11_u64))
+			.saturating_add(T::DbWeight::get().reads(14_u64))
+			.saturating_add(T::DbWeight::get().writes(12_u64))
 			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(s.into())))
 			.saturating_add(Weight::from_parts(0, 4).saturating_mul(s.into()))
 	}
@@ -809,14 +849,18 @@ impl WeightInfo for () {
 	/// Proof: Balances Freezes (max_values: None, max_size: Some(49), added: 2524, mode: MaxEncodedLen)
 	/// Storage: Staking Payee (r:0 w:1)
 	/// Proof: Staking Payee (max_values: None, max_size: Some(73), added: 2548, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	fn bond() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `1047`
 		//  Estimated: `4764`
 		// Minimum execution time: 53_983_000 picoseconds.
 		Weight::from_parts(55_296_000, 4764)
-			.saturating_add(RocksDbWeight::get().reads(5_u64))
-			.saturating_add(RocksDbWeight::get().writes(4_u64))
+			.saturating_add(RocksDbWeight::get().reads(7_u64))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
 	}
 	/// Storage: Staking Bonded (r:1 w:0)
 	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
@@ -830,14 +874,18 @@ impl WeightInfo for () {
 	/// Proof: VoterList ListNodes (max_values: None, max_size: Some(154), added: 2629, mode: MaxEncodedLen)
 	/// Storage: VoterList ListBags (r:2 w:2)
 	/// Proof: VoterList ListBags (max_values: None, max_size: Some(82), added: 2557, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	fn bond_extra() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `2028`
 		//  Estimated: `8877`
 		// Minimum execution time: 96_590_000 picoseconds.
 		Weight::from_parts(98_921_000, 8877)
-			.saturating_add(RocksDbWeight::get().reads(9_u64))
-			.saturating_add(RocksDbWeight::get().writes(7_u64))
+			.saturating_add(RocksDbWeight::get().reads(11_u64))
+			.saturating_add(RocksDbWeight::get().writes(8_u64))
 	}
 	/// Storage: Staking Ledger (r:1 w:1)
 	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
@@ -857,14 +905,18 @@ impl WeightInfo for () {
 	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
 	/// Storage: VoterList ListBags (r:2 w:2)
 	/// Proof: VoterList ListBags (max_values: None, max_size: Some(82), added: 2557, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	fn unbond() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `2233`
 		//  Estimated: `8877`
 		// Minimum execution time: 99_901_000 picoseconds.
 		Weight::from_parts(102_919_000, 8877)
-			.saturating_add(RocksDbWeight::get().reads(12_u64))
-			.saturating_add(RocksDbWeight::get().writes(7_u64))
+			.saturating_add(RocksDbWeight::get().reads(14_u64))
+			.saturating_add(RocksDbWeight::get().writes(8_u64))
 	}
 	/// Storage: Staking Ledger (r:1 w:1)
 	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
@@ -874,6 +926,10 @@ impl WeightInfo for () {
 	/// Proof: Balances Locks (max_values: None, max_size: Some(1299), added: 3774, mode: MaxEncodedLen)
 	/// Storage: Balances Freezes (r:1 w:0)
 	/// Proof: Balances Freezes (max_values: None, max_size: Some(49), added: 2524, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `s` is `[0, 100]`.
 	fn withdraw_unbonded_update(s: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -883,8 +939,8 @@ impl WeightInfo for () {
 		Weight::from_parts(47_052_829, 4764)
 			// Standard Error: 1_044
 			.saturating_add(Weight::from_parts(43_887, 0).saturating_mul(s.into()))
-			.saturating_add(RocksDbWeight::get().reads(4_u64))
-			.saturating_add(RocksDbWeight::get().writes(2_u64))
+			.saturating_add(RocksDbWeight::get().reads(6_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
 	}
 	/// Storage: Staking Ledger (r:1 w:1)
 	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
@@ -914,6 +970,10 @@ impl WeightInfo for () {
 	/// Proof: Staking Payee (max_values: None, max_size: Some(73), added: 2548, mode: MaxEncodedLen)
 	/// Storage: Staking SpanSlash (r:0 w:100)
 	/// Proof: Staking SpanSlash (max_values: None, max_size: Some(76), added: 2551, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `s` is `[0, 100]`.
 	fn withdraw_unbonded_kill(s: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -923,8 +983,8 @@ impl WeightInfo for () {
 		Weight::from_parts(104_772_163, 6248)
 			// Standard Error: 3_674
 			.saturating_add(Weight::from_parts(1_470_124, 0).saturating_mul(s.into()))
-			.saturating_add(RocksDbWeight::get().reads(13_u64))
-			.saturating_add(RocksDbWeight::get().writes(11_u64))
+			.saturating_add(RocksDbWeight::get().reads(15_u64))
+			.saturating_add(RocksDbWeight::get().writes(12_u64))
 			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(s.into())))
 			.saturating_add(Weight::from_parts(0, 4).saturating_mul(s.into()))
 	}
@@ -1053,14 +1113,18 @@ impl WeightInfo for () {
 	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
 	/// Storage: Staking Ledger (r:2 w:2)
 	/// Proof: Staking Ledger (max_values: None, max_size: Some(1091), added: 3566, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	fn set_controller() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `907`
 		//  Estimated: `8122`
 		// Minimum execution time: 21_710_000 picoseconds.
 		Weight::from_parts(22_430_000, 8122)
-			.saturating_add(RocksDbWeight::get().reads(3_u64))
-			.saturating_add(RocksDbWeight::get().writes(3_u64))
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
 	}
 	/// Storage: Staking ValidatorCount (r:0 w:1)
 	/// Proof: Staking ValidatorCount (max_values: Some(1), max_size: Some(4), added: 499, mode: MaxEncodedLen)
@@ -1143,6 +1207,10 @@ impl WeightInfo for () {
 	/// Proof: Staking Payee (max_values: None, max_size: Some(73), added: 2548, mode: MaxEncodedLen)
 	/// Storage: Staking SpanSlash (r:0 w:100)
 	/// Proof: Staking SpanSlash (max_values: None, max_size: Some(76), added: 2551, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `s` is `[0, 100]`.
 	fn force_unstake(s: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -1152,8 +1220,8 @@ impl WeightInfo for () {
 		Weight::from_parts(95_688_129, 6248)
 			// Standard Error: 5_030
 			.saturating_add(Weight::from_parts(1_487_249, 0).saturating_mul(s.into()))
-			.saturating_add(RocksDbWeight::get().reads(12_u64))
-			.saturating_add(RocksDbWeight::get().writes(12_u64))
+			.saturating_add(RocksDbWeight::get().reads(14_u64))
+			.saturating_add(RocksDbWeight::get().writes(13_u64))
 			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(s.into())))
 			.saturating_add(Weight::from_parts(0, 4).saturating_mul(s.into()))
 	}
@@ -1226,6 +1294,10 @@ impl WeightInfo for () {
 	/// Proof: Balances Locks (max_values: None, max_size: Some(1299), added: 3774, mode: MaxEncodedLen)
 	/// Storage: Balances Freezes (r:257 w:0)
 	/// Proof: Balances Freezes (max_values: None, max_size: Some(49), added: 2524, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `n` is `[0, 256]`.
 	fn payout_stakers_alive_staked(n: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -1235,10 +1307,10 @@ impl WeightInfo for () {
 		Weight::from_parts(151_046_907, 30845)
 			// Standard Error: 41_899
 			.saturating_add(Weight::from_parts(49_837_804, 0).saturating_mul(n.into()))
-			.saturating_add(RocksDbWeight::get().reads(11_u64))
-			.saturating_add(RocksDbWeight::get().reads((6_u64).saturating_mul(n.into())))
-			.saturating_add(RocksDbWeight::get().writes(3_u64))
-			.saturating_add(RocksDbWeight::get().writes((3_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().reads(13_u64))
+			.saturating_add(RocksDbWeight::get().reads((8_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+			.saturating_add(RocksDbWeight::get().writes((4_u64).saturating_mul(n.into())))
 			.saturating_add(Weight::from_parts(0, 3774).saturating_mul(n.into()))
 	}
 	/// Storage: Staking Ledger (r:1 w:1)
@@ -1253,6 +1325,10 @@ impl WeightInfo for () {
 	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
 	/// Storage: VoterList ListBags (r:2 w:2)
 	/// Proof: VoterList ListBags (max_values: None, max_size: Some(82), added: 2557, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `l` is `[1, 32]`.
 	fn rebond(l: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -1262,8 +1338,8 @@ impl WeightInfo for () {
 		Weight::from_parts(93_137_456, 8877)
 			// Standard Error: 4_799
 			.saturating_add(Weight::from_parts(54_421, 0).saturating_mul(l.into()))
-			.saturating_add(RocksDbWeight::get().reads(9_u64))
-			.saturating_add(RocksDbWeight::get().writes(7_u64))
+			.saturating_add(RocksDbWeight::get().reads(11_u64))
+			.saturating_add(RocksDbWeight::get().writes(8_u64))
 	}
 	/// Storage: Staking Bonded (r:1 w:1)
 	/// Proof: Staking Bonded (max_values: None, max_size: Some(72), added: 2547, mode: MaxEncodedLen)
@@ -1291,6 +1367,10 @@ impl WeightInfo for () {
 	/// Proof: Staking Payee (max_values: None, max_size: Some(73), added: 2548, mode: MaxEncodedLen)
 	/// Storage: Staking SpanSlash (r:0 w:100)
 	/// Proof: Staking SpanSlash (max_values: None, max_size: Some(76), added: 2551, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStakeCursor (r:1 w:0)
+	/// Proof: Staking TotalActiveStakeCursor (max_values: Some(1), max_size: Some(33), added: 528, mode: MaxEncodedLen)
+	/// Storage: Staking TotalActiveStake (r:1 w:1)
+	/// Proof: Staking TotalActiveStake (max_values: Some(1), max_size: Some(16), added: 511, mode: MaxEncodedLen)
 	/// The range of component `s` is `[1, 100]`.
 	fn reap_stash(s: u32, ) -> Weight {
 		// Proof Size summary in bytes:
@@ -1300,8 +1380,8 @@ impl WeightInfo for () {
 		Weight::from_parts(107_036_296, 6248)
 			// Standard Error: 3_935
 			.saturating_add(Weight::from_parts(1_465_860, 0).saturating_mul(s.into()))
-			.saturating_add(RocksDbWeight::get().reads(12_u64))
-			.saturating_add(RocksDbWeight::get().writes(11_u64))
+			.saturating_add(RocksDbWeight::get().reads(14_u64))
+			.saturating_add(RocksDbWeight::get().writes(12_u64))
 			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(s.into())))
 			.saturating_add(Weight::from_parts(0, 4).saturating_mul(s.into()))
 	}
@@ -16,12 +16,18 @@
 // limitations under the License.
 
 use crate::{
-	traits::{GetStorageVersion, NoStorageVersionSet, PalletInfoAccess, StorageVersion},
-	weights::{RuntimeDbWeight, Weight},
+	storage::transactional::with_transaction,
+	traits::{
+		tasks::{self, TaskError},
+		GetStorageVersion, NoStorageVersionSet, PalletInfoAccess, StorageVersion, Task,
+	},
+	weights::{RuntimeDbWeight, Weight, WeightMeter},
 };
+use codec::{Decode, Encode, FullCodec, MaxEncodedLen};
 use impl_trait_for_tuples::impl_for_tuples;
 use sp_core::Get;
 use sp_io::{hashing::twox_128, storage::clear_prefix, KillStorageResult};
+use sp_runtime::{DispatchError, TransactionOutcome};
 use sp_std::marker::PhantomData;
 
 /// EXPERIMENTAL: The API of this feature may change.
@@ -349,3 +355,302 @@ impl<P: Get<&'static str>, DbWeight: Get<RuntimeDbWeight>> frame_support::traits
 		Ok(())
 	}
 }
+
+/// A migration that spans multiple blocks, executed one step at a time.
+///
+/// Each step is given the cursor returned by the previous one, `None` for the first step, and
+/// returns the cursor to resume from, or `None` once the migration is complete. The runner is
+/// in charge of persisting the cursor between steps.
+pub trait SteppedMigration {
+	/// The progress of this migration, persisted between steps.
+	type Cursor: FullCodec + MaxEncodedLen;
+
+	/// The type of the unique identifier of this migration.
+	type Identifier: FullCodec + MaxEncodedLen;
+
+	/// The unique identifier of this migration.
+	fn id() -> Self::Identifier;
+
+	/// The maximum number of steps this migration may take, if known.
+	fn max_steps() -> Option<u32> {
+		None
+	}
+
+	/// Executes the next step of this migration, from `cursor`, within the weight left in
+	/// `meter`.
+	///
+	/// Returns the cursor to resume from, or `None` if the migration is complete.
+	fn step(
+		cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError>;
+
+	/// Same as [`Self::step`], but rolls back the changes of the step if it fails.
+	fn transactional_step(
+		cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+		with_transaction(|| match Self::step(cursor, meter) {
+			Ok(cursor) => TransactionOutcome::Commit(Ok(Ok(cursor))),
+			Err(err) => TransactionOutcome::Rollback(Ok(Err(err))),
+		})
+		.map_err(|_: DispatchError| SteppedMigrationError::Failed)?
+	}
+}
+
+/// The reasons for a step of a [`SteppedMigration`] to fail.
+#[derive(Debug, Encode, Decode, MaxEncodedLen, scale_info::TypeInfo, Clone, PartialEq, Eq)]
+pub enum SteppedMigrationError {
+	/// Not enough weight was left to make any progress. `required` is the weight of the next
+	/// step.
+	InsufficientWeight {
+		/// The weight needed to make progress.
+		required: Weight,
+	},
+	/// The cursor given to the step is not a valid one for this migration.
+	InvalidCursor,
+	/// The migration failed, and cannot make progress anymore.
+	Failed,
+}
+
+/// A [`SteppedMigration`] whose steps are the tasks of `M`.
+///
+/// [`Task::enumerate`] yields the remaining steps of the migration, and [`Task::run`] performs
+/// one of them. Each step of the migration runs as many tasks as fit in its weight meter, each
+/// costing its [`Task::weight`], on top of `EnumerationWeight` for each task enumerated, and
+/// checked for validity. A failed task fails the whole migration.
+///
+/// Running a task must either remove it from [`Task::enumerate`] or make it invalid. Invalid
+/// tasks are skipped, and are expected to stay where they are in [`Task::enumerate`]: the cursor
+/// is the number of tasks skipped so far, which are not enumerated again, see
+/// [`Task::enumerate_paged`]. The migration is complete once no task is left past the cursor.
+///
+/// The identifier is the [`Task::TASK_INDEX`] of `M`.
+pub struct MigrationTask<M, EnumerationWeight>(PhantomData<(M, EnumerationWeight)>);
+
+impl<M: Task, EnumerationWeight: Get<Weight>> SteppedMigration
+	for MigrationTask<M, EnumerationWeight>
+{
+	type Cursor = u64;
+	type Identifier = u64;
+
+	fn id() -> Self::Identifier {
+		M::TASK_INDEX
+	}
+
+	fn step(
+		cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+		let per_task = EnumerationWeight::get();
+		let mut skipped = cursor.unwrap_or_default();
+		let mut progressed = false;
+		let out_of_weight = |progressed: bool, skipped: u64, required: Weight| {
+			if progressed {
+				Ok(Some(skipped))
+			} else {
+				Err(SteppedMigrationError::InsufficientWeight { required })
+			}
+		};
+
+		loop {
+			// at most as many tasks as could be checked with the weight left.
+			let page_size = meter
+				.remaining()
+				.checked_div_per_component(&per_task)
+				.unwrap_or(u64::MAX)
+				.min(u32::MAX.into()) as u32;
+			if page_size == 0 {
+				return out_of_weight(progressed, skipped, per_task)
+			}
+			let (page, _) = M::enumerate_paged(Some(skipped), page_size);
+
+			if page.is_empty() {
+				meter.consume(per_task);
+				// the tasks skipped by earlier steps are still enumerated, unless the cursor is
+				// not one of this migration.
+				if !progressed && skipped > 0 {
+					if meter.try_consume(per_task).is_err() {
+						return out_of_weight(progressed, skipped, per_task)
+					}
+					if M::enumerate_paged(Some(skipped - 1), 1).0.is_empty() {
+						return Err(SteppedMigrationError::InvalidCursor)
+					}
+				}
+				return Ok(None)
+			}
+
+			for task in page {
+				if meter.try_consume(per_task).is_err() {
+					return out_of_weight(progressed, skipped, per_task)
+				}
+				if !task.is_valid() {
+					skipped = skipped.saturating_add(1);
+					progressed = true;
+					continue
+				}
+				let required = task.weight();
+				if !meter.can_consume(required) {
+					return out_of_weight(progressed, skipped, per_task.saturating_add(required))
+				}
+				match tasks::execute(&task) {
+					Ok(weight) => {
+						meter.consume(weight);
+						progressed = true;
+					},
+					Err(TaskError::Invalid(_)) => skipped = skipped.saturating_add(1),
+					Err(err) => {
+						log::error!("migration task {:?} failed: {:?}", task, err);
+						return Err(SteppedMigrationError::Failed)
+					},
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::unhashed;
+	use scale_info::TypeInfo;
+
+	const TODO: &[u8] = b"todo";
+	const DONE: &[u8] = b"done";
+	const BLOCKED: &[u8] = b"blocked";
+
+	/// Moves one item from [`TODO`] to [`DONE`], unless it is [`BLOCKED`].
+	#[derive(Encode, Decode, TypeInfo, Clone, Debug, PartialEq, Eq)]
+	struct MoveItem(u32);
+
+	impl Task for MoveItem {
+		type Enumeration = sp_std::vec::IntoIter<Self>;
+		type Output = ();
+
+		const TASK_INDEX: u64 = 0;
+
+		fn enumerate() -> Self::Enumeration {
+			unhashed::get_or_default::<Vec<u32>>(TODO)
+				.into_iter()
+				.map(MoveItem)
+				.collect::<Vec<_>>()
+				.into_iter()
+		}
+
+		fn is_valid(&self) -> bool {
+			unhashed::get_or_default::<Vec<u32>>(TODO).contains(&self.0) &&
+				!unhashed::get_or_default::<Vec<u32>>(BLOCKED).contains(&self.0)
+		}
+
+		fn run(&self) -> Result<(), DispatchError> {
+			let mut todo = unhashed::get_or_default::<Vec<u32>>(TODO);
+			todo.retain(|item| *item != self.0);
+			unhashed::put(TODO, &todo);
+			let mut done = unhashed::get_or_default::<Vec<u32>>(DONE);
+			done.push(self.0);
+			unhashed::put(DONE, &done);
+			Ok(())
+		}
+
+		fn weight(&self) -> Weight {
+			Weight::from_parts(10, 0)
+		}
+	}
+
+	crate::parameter_types! {
+		const EnumerationWeight: Weight = Weight::from_parts(1, 0);
+	}
+
+	type Migration = MigrationTask<MoveItem, EnumerationWeight>;
+
+	fn steps(limit: u64) -> Vec<Option<u64>> {
+		let mut cursor = None;
+		let mut cursors = vec![];
+		loop {
+			let mut meter = WeightMeter::from_limit(Weight::from_parts(limit, 0));
+			cursor = Migration::transactional_step(cursor, &mut meter).unwrap();
+			cursors.push(cursor);
+			if cursor.is_none() {
+				return cursors
+			}
+		}
+	}
+
+	#[test]
+	fn migration_task_runs_across_steps() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			unhashed::put(TODO, &(0..10).collect::<Vec<u32>>());
+
+			// three tasks fit in each step, the last step finds nothing left.
+			assert_eq!(steps(35), vec![Some(0), Some(0), Some(0), None]);
+			assert_eq!(unhashed::get::<Vec<u32>>(DONE), Some((0..10).collect()));
+			assert_eq!(unhashed::get::<Vec<u32>>(TODO), Some(vec![]));
+		});
+	}
+
+	#[test]
+	fn migration_task_completes_past_invalid_tasks() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			unhashed::put(TODO, &(0..6).collect::<Vec<u32>>());
+			unhashed::put(BLOCKED, &vec![1u32, 4]);
+
+			// the first step runs 0, 2 and 3, and skips 1 and 4. The second one resumes past them.
+			assert_eq!(steps(35), vec![Some(2), None]);
+			assert_eq!(unhashed::get::<Vec<u32>>(DONE), Some(vec![0, 2, 3, 5]));
+			// the blocked items are still enumerated.
+			assert_eq!(unhashed::get::<Vec<u32>>(TODO), Some(vec![1, 4]));
+
+			// the migration stays complete.
+			let mut meter = WeightMeter::from_limit(Weight::from_parts(35, 0));
+			assert_eq!(Migration::step(Some(2), &mut meter), Ok(None));
+		});
+	}
+
+	#[test]
+	fn migration_task_completes_when_nothing_is_left() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let mut meter = WeightMeter::from_limit(Weight::from_parts(1, 0));
+			assert_eq!(Migration::step(None, &mut meter), Ok(None));
+			assert_eq!(meter.consumed(), Weight::from_parts(1, 0));
+		});
+	}
+
+	#[test]
+	fn migration_task_rejects_cursors_past_the_tasks() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			unhashed::put(TODO, &vec![0u32]);
+
+			let mut meter = WeightMeter::from_limit(Weight::from_parts(35, 0));
+			assert_eq!(
+				Migration::step(Some(5), &mut meter),
+				Err(SteppedMigrationError::InvalidCursor)
+			);
+			assert_eq!(unhashed::get::<Vec<u32>>(DONE), None);
+		});
+	}
+
+	#[test]
+	fn migration_task_needs_weight_for_one_task() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			unhashed::put(TODO, &vec![0u32]);
+
+			let mut meter = WeightMeter::from_limit(Weight::from_parts(5, 0));
+			assert_eq!(
+				Migration::step(None, &mut meter),
+				Err(SteppedMigrationError::InsufficientWeight {
+					required: Weight::from_parts(11, 0)
+				})
+			);
+			assert_eq!(unhashed::get::<Vec<u32>>(DONE), None);
+
+			// not even a task can be enumerated.
+			let mut meter = WeightMeter::from_limit(Weight::zero());
+			assert_eq!(
+				Migration::step(None, &mut meter),
+				Err(SteppedMigrationError::InsufficientWeight {
+					required: Weight::from_parts(1, 0)
+				})
+			);
+		});
+	}
+}
@@ -122,6 +122,12 @@ pub use safe_mode::{SafeMode, SafeModeError, SafeModeNotify};
 mod tx_pause;
 pub use tx_pause::{TransactionPause, TransactionPauseError};
 
+pub mod tasks;
+pub use tasks::{
+	InvalidTaskReason, StorageTaskSource, Task, TaskError, TaskMetadata, TaskProgress, TaskSource,
+	VecTaskSource,
+};
+
 #[cfg(feature = "try-runtime")]
 mod try_runtime;
 #[cfg(feature = "try-runtime")]
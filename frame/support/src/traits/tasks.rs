@@ -0,0 +1,1333 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains the [`Task`] trait, which defines a general-purpose way for defining and executing
+//! service work, and supporting types.
+
+use crate::storage::{with_transaction, TransactionOutcome};
+use codec::{Decode, Encode, FullCodec, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_runtime::{traits::AtLeast32BitUnsigned, DispatchError, Perbill, RuntimeDebug};
+use sp_std::{
+	collections::btree_set::BTreeSet,
+	fmt::Debug,
+	iter::{Filter, Iterator, Take},
+	vec::Vec,
+};
+use sp_weights::Weight;
+
+/// The reason why a particular task is not a valid piece of work.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum InvalidTaskReason {
+	/// The task did not specify why it is invalid.
+	Unknown,
+	/// The work this task represents has already been done.
+	AlreadyDone,
+	/// The task is stale, see [`Task::expires_at`].
+	Expired,
+	/// Some precondition of the task does not hold (yet).
+	PreconditionUnmet,
+}
+
+/// A description of a [`Task`] type, for introspection tooling. See [`Task::metadata`].
+#[derive(Encode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct TaskMetadata {
+	/// The [`Task::TASK_INDEX`] of the task type.
+	pub index: u64,
+	/// A human-readable name of the task type.
+	pub name: &'static str,
+	/// A short description of what tasks of this type do.
+	pub description: &'static str,
+}
+
+/// The outcome of a successful [`Task::run_step`].
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum TaskProgress<T> {
+	/// All the work of the task is done.
+	Done,
+	/// Only part of the work is done, `next` is the task doing the rest of it.
+	Incomplete {
+		/// The task to run next, e.g. in a later block.
+		next: T,
+	},
+}
+
+/// A general-purpose trait which defines a type of service work (i.e., work to be performed by an
+/// off-chain worker) including methods for enumerating, validating, indexing, and running
+/// tasks of this type.
+pub trait Task: Sized + FullCodec + TypeInfo + Clone + Debug + PartialEq + Eq {
+	/// An [`Iterator`] over tasks of this type used as the return type for `enumerate`.
+	type Enumeration: Iterator<Item = Self>;
+
+	/// The result of [`Task::run`], returned to its caller, e.g. an aggregated price.
+	///
+	/// It is SCALE-encodable so that it can be surfaced in an event. Tasks that do not compute
+	/// anything use `()`.
+	type Output: FullCodec;
+
+	/// A unique value representing this `Task`. Analogous to `call_index`, but for tasks.
+	const TASK_INDEX: u64;
+
+	/// The log target under which [`execute`] reports on tasks of this type.
+	///
+	/// This allows verbose logging to be enabled for a single task type via `RUST_LOG`. Nothing
+	/// prevents several task types from sharing a target, in which case their logs are simply
+	/// interleaved under it.
+	const LOG_TARGET: &'static str = "runtime::tasks";
+
+	/// The maximum number of tasks of this type yielded by [`Task::enumerate_capped`], or `None`
+	/// if enumeration is unbounded.
+	const MAX_ENUMERATED: Option<u64> = None;
+
+	/// The maximum size of the encoding of a task of this type, or `None` if it is unbounded.
+	///
+	/// Tasks can be submitted on-chain in their encoded form, e.g. through a call, hence this
+	/// bounds what a submitter can make [`execute`] process. See [`Task::ensure_size`].
+	const MAX_ENCODED_SIZE: Option<u32> = None;
+
+	/// Inspects the pallet's state and enumerates tasks of this type.
+	fn enumerate() -> Self::Enumeration;
+
+	/// Describes this task type, e.g. for UIs listing the task types of a runtime.
+	///
+	/// By default, the name is the name of the type and the description is empty.
+	fn metadata() -> TaskMetadata {
+		TaskMetadata {
+			index: Self::TASK_INDEX,
+			name: sp_std::any::type_name::<Self>(),
+			description: "",
+		}
+	}
+
+	/// Same as [`Task::enumerate`], but yields at most [`Task::MAX_ENUMERATED`] tasks.
+	///
+	/// This bounds the cost of enumeration without the pallet having to rewrite its iterator.
+	fn enumerate_capped() -> Take<Self::Enumeration> {
+		let cap = Self::MAX_ENUMERATED.map_or(usize::MAX, |n| n.try_into().unwrap_or(usize::MAX));
+		Self::enumerate().take(cap)
+	}
+
+	/// Same as [`Task::enumerate`], but only yields the tasks that are valid.
+	///
+	/// This is lazy: the validity of each task is only checked as it is consumed.
+	fn enumerate_valid() -> Filter<Self::Enumeration, fn(&Self) -> bool> {
+		Self::enumerate().filter(Self::is_valid as fn(&Self) -> bool)
+	}
+
+	/// One page of at most `page_size` tasks of this type, starting at the `cursor`-th task, or at
+	/// the first one if `cursor` is `None`. Also returns the cursor of the next page, or `None`
+	/// once all tasks were enumerated.
+	///
+	/// By default, this skips the first `cursor` tasks of [`Task::enumerate`]. Tasks backed by
+	/// indexed storage may override this to seek to the cursor directly. A `page_size` of 0 yields
+	/// no task and leaves the cursor where it is.
+	fn enumerate_paged(cursor: Option<u64>, page_size: u32) -> (Vec<Self>, Option<u64>) {
+		let start = cursor.unwrap_or(0);
+		if page_size == 0 {
+			return (Vec::new(), Some(start))
+		}
+
+		let skip = start.try_into().unwrap_or(usize::MAX);
+		// one more task tells whether there is a next page.
+		let mut page = Self::enumerate()
+			.skip(skip)
+			.take((page_size as usize).saturating_add(1))
+			.collect::<Vec<_>>();
+		let next = if page.len() > page_size as usize {
+			page.pop();
+			Some(start.saturating_add(page_size.into()))
+		} else {
+			None
+		};
+		(page, next)
+	}
+
+	/// Checks if a particular instance of this `Task` variant is a valid piece of work.
+	fn is_valid(&self) -> bool;
+
+	/// Checks if a particular instance of this `Task` variant is a valid piece of work, and if
+	/// not, why.
+	///
+	/// By default, this is derived from [`Task::is_valid`], reporting
+	/// [`InvalidTaskReason::Unknown`] for invalid tasks. Tasks overriding this to report a reason
+	/// must keep [`Task::is_valid`] consistent with it, e.g. by returning `self.validity().is_ok()`
+	/// from it.
+	fn validity(&self) -> Result<(), InvalidTaskReason> {
+		if self.is_valid() {
+			Ok(())
+		} else {
+			Err(InvalidTaskReason::Unknown)
+		}
+	}
+
+	/// Ensures that the encoding of this particular task fits in [`Task::MAX_ENCODED_SIZE`].
+	fn ensure_size(&self) -> Result<(), DispatchError> {
+		match Self::MAX_ENCODED_SIZE {
+			Some(max) if self.encoded_size() > max as usize =>
+				Err(DispatchError::Other("task exceeds its maximum encoded size")),
+			_ => Ok(()),
+		}
+	}
+
+	/// Performs the work for this particular `Task` variant, returning its [`Task::Output`].
+	fn run(&self) -> Result<Self::Output, DispatchError>;
+
+	/// Performs as much of the work for this particular `Task` variant as fits in `remaining`.
+	///
+	/// Tasks processing large batches may override this to do their work across several calls,
+	/// returning [`TaskProgress::Incomplete`] with the task to run next. The caller is expected to
+	/// persist that task until it is run, e.g. in the next block. By default, this runs the whole
+	/// task through [`Task::run`], regardless of `remaining`.
+	fn run_step(&self, _remaining: Weight) -> Result<TaskProgress<Self>, DispatchError> {
+		self.run().map(|_| TaskProgress::Done)
+	}
+
+	/// Performs the work for this particular `Task` variant, like [`Task::run`], and returns the
+	/// weight it actually consumed, see [`execute_metered`].
+	///
+	/// By default, this runs [`Task::run`] and reports [`Task::weight`].
+	fn run_metered(&self) -> Result<Weight, DispatchError> {
+		self.run().map(|_| self.weight())
+	}
+
+	/// Returns the weight of executing this `Task`.
+	fn weight(&self) -> Weight;
+
+	/// Checks that a successful [`Task::run`] actually achieved its effect, e.g. that the
+	/// condition this task was enumerated for no longer holds.
+	///
+	/// [`execute`] asserts this after every successful run in debug builds, which catches tasks
+	/// that silently do nothing. Tasks whose effect cannot be verified, for instance because it is
+	/// external to the runtime, should keep the default of returning `true`.
+	fn check_postconditions(&self) -> bool {
+		true
+	}
+
+	/// Called by [`execute`] whenever [`Task::run`] fails with `err`.
+	///
+	/// This is the place for a task to record its own failure, e.g. by bumping a failure counter
+	/// or moving itself to a dead-letter list. It must not panic nor fail itself, since there is
+	/// nothing left to handle such a failure.
+	fn on_failure(&self, _err: &DispatchError) {}
+
+	/// A unique value representing this `Task`. Analogous to `call_index`, but for tasks.
+	fn task_index(&self) -> u64 {
+		Self::TASK_INDEX
+	}
+
+	/// The group of related tasks this particular task belongs to, see
+	/// [`execute_batch_grouped`].
+	///
+	/// By default, all tasks of a type form a single group.
+	fn group_index(&self) -> u64 {
+		self.task_index()
+	}
+
+	/// A hash uniquely identifying this particular task, across all task types.
+	fn full_hash_code(&self) -> [u8; 32] {
+		sp_io::hashing::blake2_256(&(self.task_index(), self).encode())
+	}
+
+	/// The maximum number of times this task is attempted before giving up on it.
+	fn max_attempts(&self) -> u32 {
+		1
+	}
+
+	/// The number of blocks to wait after a failed attempt before trying this task again.
+	fn retry_backoff(&self) -> u32 {
+		0
+	}
+
+	/// The number of blocks to wait after a successful run before running this particular task
+	/// again, regardless of its validity.
+	///
+	/// A task whose inputs change hashes differently, see [`Self::full_hash_code`], so it is not
+	/// subject to the cooldown of the task it replaces.
+	fn cooldown(&self) -> u32 {
+		0
+	}
+
+	/// Whether the validity of this particular task can be checked concurrently with that of
+	/// other tasks, off-chain, see [`enumerate_partitioned`].
+	///
+	/// Only tasks whose validity depends on nothing but the task itself should return `true`:
+	/// parallel checks run on threads of their own, which have no access to the externalities,
+	/// hence to storage.
+	fn can_run_in_parallel(&self) -> bool {
+		false
+	}
+
+	/// The priority of this particular task. Runners that order tasks, such as the queue of
+	/// `pallet-tasks`, run tasks of higher priority first.
+	fn priority(&self) -> u8 {
+		0
+	}
+
+	/// The last block at which this particular task may still be executed, or `None` if it never
+	/// becomes stale.
+	///
+	/// `Task` itself is agnostic of the block number type, hence the deadline is a `u64`, which
+	/// holds the block numbers of any chain. It is converted to the block number type of the
+	/// chain by [`enumerate_unexpired`].
+	fn expires_at(&self) -> Option<u64> {
+		None
+	}
+}
+
+/// Enumerates all tasks of type `T`, skipping those whose [`Task::expires_at`] deadline lies
+/// strictly before `now`.
+///
+/// A task with a deadline equal to `now` is still yielded, as is any task without a deadline, or
+/// with a deadline that `BlockNumber` cannot represent, which is never reached.
+pub fn enumerate_unexpired<T: Task, BlockNumber: AtLeast32BitUnsigned>(
+	now: BlockNumber,
+) -> impl Iterator<Item = T> {
+	T::enumerate().filter(move |task| {
+		match task.expires_at().map(<BlockNumber as TryFrom<u64>>::try_from) {
+			Some(Ok(deadline)) => deadline >= now,
+			Some(Err(_)) | None => true,
+		}
+	})
+}
+
+/// A source of tasks to execute, e.g. for a runner that is generic over where its tasks come
+/// from.
+///
+/// [`StorageTaskSource`] enumerates them from the pallet's state, while [`VecTaskSource`] yields a
+/// fixed set of tasks, which is handy to drive a runner in tests without any storage setup.
+pub trait TaskSource<T: Task> {
+	/// The iterator over the tasks of this source.
+	type Tasks: Iterator<Item = T>;
+
+	/// The tasks of this source, in the order they should be executed.
+	fn tasks(&self) -> Self::Tasks;
+}
+
+/// The tasks of [`Task::enumerate_capped`], i.e. those that the pallet's state currently calls
+/// for.
+#[derive(Clone, Copy, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct StorageTaskSource;
+
+impl<T: Task> TaskSource<T> for StorageTaskSource {
+	type Tasks = Take<T::Enumeration>;
+
+	fn tasks(&self) -> Self::Tasks {
+		T::enumerate_capped()
+	}
+}
+
+/// A fixed set of tasks, yielded in order.
+#[derive(Clone, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct VecTaskSource<T>(pub Vec<T>);
+
+impl<T: Task> TaskSource<T> for VecTaskSource<T> {
+	type Tasks = sp_std::vec::IntoIter<T>;
+
+	fn tasks(&self) -> Self::Tasks {
+		self.0.clone().into_iter()
+	}
+}
+
+/// The reason why [`execute`] did not execute a task successfully.
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum TaskError {
+	/// The task is not a valid piece of work, see [`Task::validity`].
+	Invalid(InvalidTaskReason),
+	/// The task is valid, but running it failed.
+	Failed(DispatchError),
+	/// The encoding of the task exceeds [`Task::MAX_ENCODED_SIZE`], hence it was neither checked
+	/// nor run.
+	Oversized,
+}
+
+/// Validates and runs a single task, logging the outcome under [`Task::LOG_TARGET`].
+///
+/// A task exceeding [`Task::MAX_ENCODED_SIZE`] is rejected as [`TaskError::Oversized`] without
+/// being run. Returns the weight of the task if it ran successfully.
+pub fn execute<T: Task>(task: &T) -> Result<Weight, TaskError> {
+	do_execute(task, |task| task.run().map(|_| task.weight()))
+}
+
+/// Same as [`execute`], but runs the task in its own storage transaction, which is rolled back
+/// if the task fails, so that a failed task leaves no partial state behind.
+///
+/// [`Task::on_failure`] is called after the rollback, hence whatever it writes is kept. Must be
+/// called within externalities, e.g. from a hook.
+pub fn execute_transactional<T: Task>(task: &T) -> Result<Weight, TaskError> {
+	do_execute(task, |task| {
+		with_transaction(|| match task.run() {
+			Ok(_) => TransactionOutcome::Commit(Ok(task.weight())),
+			Err(err) => TransactionOutcome::Rollback(Err(err)),
+		})
+	})
+}
+
+/// A task that consumed more weight than it declared, see [`execute_metered`].
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct OverweightTask {
+	/// The [`Task::task_index`] of the task.
+	pub task_index: u64,
+	/// The [`Task::weight`] of the task.
+	pub declared: Weight,
+	/// The weight the task actually consumed, as reported by [`Task::run_metered`].
+	pub actual: Weight,
+}
+
+/// Same as [`execute`], but runs the task through [`Task::run_metered`] and returns the weight
+/// it actually consumed.
+///
+/// If that weight exceeds the declared [`Task::weight`] by more than `margin` of it, in any
+/// dimension, the task is also reported as an [`OverweightTask`], which helps tuning declared
+/// weights.
+pub fn execute_metered<T: Task>(
+	task: &T,
+	margin: Perbill,
+) -> Result<(Weight, Option<OverweightTask>), TaskError> {
+	let actual = do_execute(task, |task| task.run_metered())?;
+	let declared = task.weight();
+	let allowed = declared.saturating_add(Weight::from_parts(
+		margin * declared.ref_time(),
+		margin * declared.proof_size(),
+	));
+	if !actual.any_gt(allowed) {
+		return Ok((actual, None))
+	}
+
+	log::warn!(
+		target: T::LOG_TARGET,
+		"task {:?} declared {:?} but consumed {:?}",
+		task,
+		declared,
+		actual
+	);
+	Ok((actual, Some(OverweightTask { task_index: task.task_index(), declared, actual })))
+}
+
+fn do_execute<T: Task>(
+	task: &T,
+	run: impl FnOnce(&T) -> Result<Weight, DispatchError>,
+) -> Result<Weight, TaskError> {
+	if let Err(err) = task.ensure_size() {
+		log::warn!(target: T::LOG_TARGET, "task {:?} is rejected: {:?}", task, err);
+		return Err(TaskError::Oversized)
+	}
+	if let Err(reason) = task.validity() {
+		log::debug!(target: T::LOG_TARGET, "task {:?} is invalid: {:?}", task, reason);
+		return Err(TaskError::Invalid(reason))
+	}
+
+	match run(task) {
+		Ok(weight) => {
+			debug_assert!(
+				task.check_postconditions(),
+				"task {:?} did not achieve its postconditions",
+				task
+			);
+			log::debug!(target: T::LOG_TARGET, "task {:?} executed", task);
+			Ok(weight)
+		},
+		Err(err) => {
+			log::warn!(target: T::LOG_TARGET, "task {:?} failed: {:?}", task, err);
+			task.on_failure(&err);
+			Err(TaskError::Failed(err))
+		},
+	}
+}
+
+/// Executes the tasks of `source` in order, as long as their accumulated [`Task::weight`] fits
+/// in `max_weight`.
+///
+/// Tasks that do not fit in the remaining weight are skipped, lighter ones after them may still
+/// run. Tasks that are not valid or are oversized, see [`TaskError::Oversized`], are skipped and
+/// do not count towards the limit, while those that fail still do, after their
+/// [`Task::on_failure`] has been called. Each task runs through
+/// [`execute_transactional`], hence a failed task leaves no state change. Returns the weight
+/// consumed.
+pub fn execute_batch<T: Task, S: TaskSource<T>>(source: &S, max_weight: Weight) -> Weight {
+	do_execute_batch(source.tasks(), max_weight, None)
+}
+
+/// Same as [`execute_batch`], but runs at most one task of each [`Task::group_index`].
+///
+/// Tasks whose group is in `ran_groups` are skipped, and the group of each task that runs is
+/// added to it, whether it succeeds or fails. Sharing `ran_groups` across several batches thus
+/// runs at most one task per group across all of them, e.g. within a block.
+pub fn execute_batch_grouped<T: Task, S: TaskSource<T>>(
+	source: &S,
+	max_weight: Weight,
+	ran_groups: &mut BTreeSet<u64>,
+) -> Weight {
+	do_execute_batch(source.tasks(), max_weight, Some(ran_groups))
+}
+
+fn do_execute_batch<T: Task>(
+	tasks: impl Iterator<Item = T>,
+	max_weight: Weight,
+	mut ran_groups: Option<&mut BTreeSet<u64>>,
+) -> Weight {
+	let mut consumed = Weight::zero();
+	for task in tasks {
+		let group = task.group_index();
+		if ran_groups.as_ref().is_some_and(|ran| ran.contains(&group)) {
+			log::trace!(target: T::LOG_TARGET, "task {:?} skipped, group {} ran", task, group);
+			continue
+		}
+
+		let weight = task.weight();
+		if consumed.saturating_add(weight).any_gt(max_weight) {
+			log::trace!(target: T::LOG_TARGET, "task {:?} skipped, it does not fit", task);
+			continue
+		}
+
+		match execute_transactional(&task) {
+			Ok(_) | Err(TaskError::Failed(_)) => {
+				consumed.saturating_accrue(weight);
+				if let Some(ran) = ran_groups.as_mut() {
+					ran.insert(group);
+				}
+			},
+			Err(TaskError::Invalid(_) | TaskError::Oversized) => (),
+		}
+	}
+	consumed
+}
+
+/// Enumerates all tasks of type `T`, split into those that [`Task::can_run_in_parallel`] and the
+/// others, in the order of [`Task::enumerate`].
+///
+/// The runtime itself never checks tasks concurrently: the validity of the former can be checked
+/// on threads of their own by the client, e.g. through `sc_offchain::check_partitioned`. Running
+/// tasks must always happen serially.
+pub fn enumerate_partitioned<T: Task>() -> (Vec<T>, Vec<T>) {
+	T::enumerate().partition(|task| task.can_run_in_parallel())
+}
+
+/// Helpers for offchain workers, which must never be used on-chain.
+pub mod offchain {
+	use super::*;
+	use sp_runtime::{offchain::storage::StorageValueRef, traits::UniqueSaturatedInto};
+
+	/// The prefix of the offchain storage keys under which [`cached_is_valid`] memoizes validity.
+	pub const VALIDITY_CACHE_PREFIX: &[u8] = b"tasks::validity::";
+
+	/// The offchain storage key under which [`cached_is_valid`] lists the tasks it memoized, along
+	/// with the block they were memoized for.
+	pub const VALIDITY_CACHE_INDEX: &[u8] = b"tasks::validity-index";
+
+	/// Same as [`Task::is_valid`], but memoized per task for `current_block`.
+	///
+	/// The validity of each task is kept in the persistent offchain storage, keyed by its
+	/// [`Task::full_hash_code`], along with the block it was checked at. It is only reused within
+	/// that block, and checked again once `current_block` moves on. The first miss of a block
+	/// clears the entries of earlier blocks, which [`VALIDITY_CACHE_INDEX`] keeps track of, so
+	/// that the cache only ever holds the tasks checked within a single block. The cache is local
+	/// to the node, hence this must only be called from offchain workers, never while executing
+	/// blocks.
+	pub fn cached_is_valid<T: Task, BlockNumber: AtLeast32BitUnsigned>(
+		task: &T,
+		current_block: BlockNumber,
+	) -> bool {
+		let block: u64 = current_block.unique_saturated_into();
+		let hash = task.full_hash_code();
+		let key = cache_key(&hash);
+		let cache = StorageValueRef::persistent(&key);
+		if let Ok(Some((cached_block, valid))) = cache.get::<(u64, bool)>() {
+			if cached_block == block {
+				return valid
+			}
+		}
+
+		let index = StorageValueRef::persistent(VALIDITY_CACHE_INDEX);
+		let mut cached = match index.get::<(u64, Vec<[u8; 32]>)>() {
+			Ok(Some((cached_block, hashes))) if cached_block == block => hashes,
+			Ok(Some((_, stale))) => {
+				for hash in stale {
+					StorageValueRef::persistent(&cache_key(&hash)).clear();
+				}
+				Vec::new()
+			},
+			_ => Vec::new(),
+		};
+		if !cached.contains(&hash) {
+			cached.push(hash);
+		}
+		index.set(&(block, cached));
+
+		let valid = task.is_valid();
+		cache.set(&(block, valid));
+		valid
+	}
+
+	fn cache_key(hash: &[u8; 32]) -> Vec<u8> {
+		[VALIDITY_CACHE_PREFIX, &hash[..]].concat()
+	}
+}
+
+/// Aggregates several [`Task`] types into a single enum, able to decode any of them from their
+/// [`Task::TASK_INDEX`] and encoding.
+///
+/// Each variant must wrap exactly one type implementing [`Task`], and the `TASK_INDEX`es of the
+/// wrapped types are expected to be unique. Any attributes (e.g. derives) are passed on to the
+/// generated enum.
+///
+/// ```
+/// # use frame_support::{impl_task_aggregator, traits::Task};
+/// # use codec::{Decode, Encode};
+/// # use scale_info::TypeInfo;
+/// # use sp_runtime::DispatchError;
+/// # use sp_weights::Weight;
+/// #[derive(Encode, Decode, TypeInfo, Clone, Debug, PartialEq, Eq)]
+/// pub struct Noop;
+///
+/// impl Task for Noop {
+/// 	type Enumeration = sp_std::vec::IntoIter<Self>;
+/// 	type Output = ();
+/// 	const TASK_INDEX: u64 = 7;
+/// 	fn enumerate() -> Self::Enumeration { vec![Noop].into_iter() }
+/// 	fn is_valid(&self) -> bool { true }
+/// 	fn run(&self) -> Result<(), DispatchError> { Ok(()) }
+/// 	fn weight(&self) -> Weight { Weight::zero() }
+/// }
+///
+/// impl_task_aggregator! {
+/// 	#[derive(Debug, PartialEq)]
+/// 	pub enum RuntimeTask {
+/// 		Noop(Noop),
+/// 	}
+/// }
+///
+/// assert_eq!(RuntimeTask::decode(7, &Noop.encode()), Ok(RuntimeTask::Noop(Noop)));
+/// assert!(RuntimeTask::decode(8, &Noop.encode()).is_err());
+/// ```
+#[macro_export]
+macro_rules! impl_task_aggregator {
+	(
+		$( #[$attr:meta] )*
+		$vis:vis enum $name:ident {
+			$( $variant:ident ( $task:ty ) ),* $(,)?
+		}
+	) => {
+		$( #[$attr] )*
+		$vis enum $name {
+			$( $variant($task), )*
+		}
+
+		impl $name {
+			/// Decodes `data` as the task type whose `TASK_INDEX` is `index`.
+			///
+			/// Fails if no aggregated task type has this index, or if `data` is not exactly the
+			/// encoding of a task of that type. `data` exceeding the `MAX_ENCODED_SIZE` of that
+			/// type is rejected before being decoded.
+			pub fn decode(
+				index: u64,
+				mut data: &[u8],
+			) -> Result<Self, $crate::sp_runtime::DispatchError> {
+				$(
+					if index == <$task as $crate::traits::Task>::TASK_INDEX {
+						if <$task as $crate::traits::Task>::MAX_ENCODED_SIZE
+							.is_some_and(|max| data.len() > max as usize)
+						{
+							return Err($crate::sp_runtime::DispatchError::Other(
+								"task exceeds its maximum encoded size",
+							))
+						}
+						return <$task as $crate::__private::codec::DecodeAll>::decode_all(&mut data)
+							.map(Self::$variant)
+							.map_err(|_| {
+								$crate::sp_runtime::DispatchError::Other("failed to decode task")
+							})
+					}
+				)*
+				Err($crate::sp_runtime::DispatchError::Other("unknown task index"))
+			}
+
+			/// The [`TaskMetadata`]($crate::traits::tasks::TaskMetadata) of each aggregated task
+			/// type.
+			pub fn metadata() -> $crate::__private::sp_std::vec::Vec<
+				$crate::traits::tasks::TaskMetadata,
+			> {
+				$crate::__private::sp_std::vec![
+					$( <$task as $crate::traits::Task>::metadata(), )*
+				]
+			}
+
+			/// The `TASK_INDEX` of the wrapped task.
+			pub fn task_index(&self) -> u64 {
+				match self {
+					$( Self::$variant(task) => $crate::traits::Task::task_index(task), )*
+				}
+			}
+		}
+	};
+}
+
+#[cfg(test)]
+mod mock {
+	use super::*;
+	use std::cell::{Cell, RefCell};
+
+	std::thread_local! {
+		/// The tasks enumerated by [`MockTask`], which are valid unless stale.
+		pub static PENDING: RefCell<Vec<MockTask>> = Default::default();
+		/// The ids of the [`MockTask`]s run so far, including those that failed.
+		pub static RAN: RefCell<Vec<u32>> = Default::default();
+		/// The ids of the [`MockTask`]s that failed so far, with their error.
+		pub static FAILURES: RefCell<Vec<(u32, DispatchError)>> = Default::default();
+		/// The number of times the validity of a [`MockTask`] was checked so far.
+		pub static CHECKS: Cell<u32> = Cell::new(0);
+	}
+
+	/// Replaces the tasks enumerated by [`MockTask`].
+	pub fn set_pending(tasks: impl IntoIterator<Item = MockTask>) {
+		PENDING.with(|p| *p.borrow_mut() = tasks.into_iter().collect());
+	}
+
+	/// A task whose behaviour is set per instance, leaving the constants and associated functions
+	/// of [`Task`] to their defaults.
+	///
+	/// Running it successfully removes it from [`PENDING`], which its postconditions check.
+	#[derive(Encode, Decode, TypeInfo, Clone, Debug, Default, PartialEq, Eq)]
+	pub struct MockTask {
+		pub id: u32,
+		/// See [`Task::expires_at`].
+		pub deadline: Option<u64>,
+		/// The `ref_time` of [`Task::weight`].
+		pub weight: u64,
+		/// See [`Task::group_index`], which is [`Task::TASK_INDEX`] if `None`.
+		pub group: Option<u64>,
+		/// Makes the task invalid, even while it is pending.
+		pub stale: bool,
+		/// Makes [`Task::run`] fail, after it wrote to storage.
+		pub fail: bool,
+		/// Makes [`Task::run`] succeed without removing the task from [`PENDING`].
+		pub buggy: bool,
+		/// See [`Task::can_run_in_parallel`].
+		pub parallel: bool,
+	}
+
+	impl MockTask {
+		/// A task with the given `id`, and the defaults otherwise.
+		pub fn new(id: u32) -> Self {
+			MockTask { id, ..Default::default() }
+		}
+	}
+
+	impl Task for MockTask {
+		type Enumeration = sp_std::vec::IntoIter<Self>;
+		type Output = ();
+
+		const TASK_INDEX: u64 = 0;
+
+		fn enumerate() -> Self::Enumeration {
+			PENDING.with(|p| p.borrow().clone()).into_iter()
+		}
+
+		fn is_valid(&self) -> bool {
+			CHECKS.with(|c| c.set(c.get() + 1));
+			!self.stale && PENDING.with(|p| p.borrow().contains(self))
+		}
+
+		fn run(&self) -> Result<(), DispatchError> {
+			RAN.with(|r| r.borrow_mut().push(self.id));
+			crate::storage::unhashed::put(&self.id.encode(), &true);
+			if self.fail {
+				return Err(DispatchError::Other("deliberate"))
+			}
+			if !self.buggy {
+				PENDING.with(|p| p.borrow_mut().retain(|t| t != self));
+			}
+			Ok(())
+		}
+
+		fn weight(&self) -> Weight {
+			Weight::from_parts(self.weight, 0)
+		}
+
+		fn check_postconditions(&self) -> bool {
+			!PENDING.with(|p| p.borrow().contains(self))
+		}
+
+		fn on_failure(&self, err: &DispatchError) {
+			FAILURES.with(|f| f.borrow_mut().push((self.id, *err)));
+		}
+
+		fn group_index(&self) -> u64 {
+			self.group.unwrap_or(Self::TASK_INDEX)
+		}
+
+		fn can_run_in_parallel(&self) -> bool {
+			self.parallel
+		}
+
+		fn expires_at(&self) -> Option<u64> {
+			self.deadline
+		}
+	}
+
+	/// A task overriding the constants and associated functions of [`Task`] that [`MockTask`]
+	/// leaves to their defaults, hence the other way round.
+	///
+	/// Its enumeration never ends, but is capped to 7 tasks.
+	#[derive(Encode, Decode, TypeInfo, Clone, Debug, Default, PartialEq, Eq)]
+	pub struct CustomTask {
+		/// Summed up by [`Task::run`].
+		pub payload: Vec<u8>,
+		/// The `ref_time` of [`Task::weight`], which [`Task::run_step`] processes one unit at a
+		/// time.
+		pub weight: u64,
+		/// The `ref_time` reported by [`Task::run_metered`], which is the declared one if `None`.
+		pub actual: Option<u64>,
+		/// Why the task is invalid, if it is.
+		pub invalid: Option<InvalidTaskReason>,
+	}
+
+	impl Task for CustomTask {
+		type Enumeration = sp_std::iter::Repeat<Self>;
+		type Output = u64;
+
+		const TASK_INDEX: u64 = 1;
+		const MAX_ENUMERATED: Option<u64> = Some(7);
+		const MAX_ENCODED_SIZE: Option<u32> = Some(16);
+
+		fn enumerate() -> Self::Enumeration {
+			sp_std::iter::repeat(CustomTask::default())
+		}
+
+		fn metadata() -> TaskMetadata {
+			TaskMetadata {
+				index: Self::TASK_INDEX,
+				name: "custom",
+				description: "overrides what it can",
+			}
+		}
+
+		fn is_valid(&self) -> bool {
+			self.validity().is_ok()
+		}
+
+		fn validity(&self) -> Result<(), InvalidTaskReason> {
+			self.invalid.map_or(Ok(()), Err)
+		}
+
+		fn run(&self) -> Result<u64, DispatchError> {
+			Ok(self.payload.iter().map(|b| u64::from(*b)).sum())
+		}
+
+		fn run_step(&self, remaining: Weight) -> Result<TaskProgress<Self>, DispatchError> {
+			match self.weight.saturating_sub(remaining.ref_time()) {
+				0 => Ok(TaskProgress::Done),
+				weight =>
+					Ok(TaskProgress::Incomplete { next: CustomTask { weight, ..self.clone() } }),
+			}
+		}
+
+		fn run_metered(&self) -> Result<Weight, DispatchError> {
+			self.run().map(|_| Weight::from_parts(self.actual.unwrap_or(self.weight), 0))
+		}
+
+		fn weight(&self) -> Weight {
+			Weight::from_parts(self.weight, 0)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{mock::*, *};
+
+	fn deadline_tasks() -> Vec<MockTask> {
+		vec![
+			MockTask { deadline: Some(5), ..MockTask::new(1) },
+			MockTask { deadline: Some(10), ..MockTask::new(2) },
+			MockTask::new(3),
+			MockTask { deadline: Some(15), ..MockTask::new(4) },
+		]
+	}
+
+	fn unexpired_ids(now: u64) -> Vec<u32> {
+		set_pending(deadline_tasks());
+		enumerate_unexpired::<MockTask, u64>(now).map(|t| t.id).collect()
+	}
+
+	#[test]
+	fn expires_at_defaults_to_none() {
+		assert_eq!(CustomTask::default().expires_at(), None);
+		assert!(enumerate_unexpired::<CustomTask, u32>(u32::MAX).next().is_some());
+	}
+
+	#[test]
+	fn validity_defaults_to_is_valid() {
+		set_pending([MockTask::new(1), MockTask { stale: true, ..MockTask::new(2) }]);
+		assert_eq!(MockTask::new(1).validity(), Ok(()));
+		assert_eq!(
+			MockTask { stale: true, ..MockTask::new(2) }.validity(),
+			Err(InvalidTaskReason::Unknown)
+		);
+	}
+
+	#[test]
+	fn validity_reports_reasons() {
+		assert!(CustomTask::default().is_valid());
+		assert_eq!(CustomTask::default().validity(), Ok(()));
+		for reason in [
+			InvalidTaskReason::Unknown,
+			InvalidTaskReason::AlreadyDone,
+			InvalidTaskReason::Expired,
+			InvalidTaskReason::PreconditionUnmet,
+		] {
+			let task = CustomTask { invalid: Some(reason), ..Default::default() };
+			assert!(!task.is_valid());
+			assert_eq!(task.validity(), Err(reason));
+		}
+	}
+
+	#[test]
+	fn log_target_defaults_to_tasks() {
+		// tasks that do not declare a target share the default one.
+		assert_eq!(MockTask::LOG_TARGET, "runtime::tasks");
+	}
+
+	#[test]
+	fn enumerate_capped_is_unbounded_by_default() {
+		set_pending(deadline_tasks());
+		assert_eq!(MockTask::MAX_ENUMERATED, None);
+		assert_eq!(MockTask::enumerate_capped().count(), 4);
+	}
+
+	#[test]
+	fn enumerate_valid_skips_invalid_tasks() {
+		set_pending((0..6).map(|id| MockTask { stale: id % 2 == 1, ..MockTask::new(id) }));
+
+		// nothing is checked until consumed.
+		let mut valid = MockTask::enumerate_valid();
+		assert_eq!(CHECKS.with(|c| c.get()), 0);
+		assert_eq!(valid.next(), Some(MockTask::new(0)));
+		assert_eq!(CHECKS.with(|c| c.get()), 1);
+
+		assert_eq!(valid.map(|t| t.id).collect::<Vec<_>>(), vec![2, 4]);
+		assert_eq!(CHECKS.with(|c| c.get()), 6);
+	}
+
+	#[test]
+	fn cached_is_valid_is_memoized_within_a_block() {
+		use sp_core::offchain::{testing::TestOffchainExt, OffchainDbExt, OffchainWorkerExt};
+
+		let (offchain, _state) = TestOffchainExt::new();
+		let mut ext = sp_io::TestExternalities::default();
+		ext.register_extension(OffchainDbExt::new(offchain.clone()));
+		ext.register_extension(OffchainWorkerExt::new(offchain));
+		let task = MockTask::new(1);
+		set_pending([task.clone()]);
+		ext.execute_with(|| {
+			assert!(offchain::cached_is_valid(&task, 1u32));
+			assert!(offchain::cached_is_valid(&task, 1u32));
+			assert_eq!(CHECKS.with(|c| c.get()), 1);
+
+			// a change within the block goes unnoticed.
+			PENDING.with(|p| p.borrow_mut().clear());
+			assert!(offchain::cached_is_valid(&task, 1u32));
+			assert_eq!(CHECKS.with(|c| c.get()), 1);
+
+			// but not across blocks.
+			assert!(!offchain::cached_is_valid(&task, 2u32));
+			assert!(!offchain::cached_is_valid(&task, 2u32));
+			assert_eq!(CHECKS.with(|c| c.get()), 2);
+		});
+	}
+
+	#[test]
+	fn cached_is_valid_clears_stale_entries() {
+		use sp_core::offchain::{testing::TestOffchainExt, OffchainDbExt, OffchainWorkerExt};
+
+		let (offchain, state) = TestOffchainExt::new();
+		let mut ext = sp_io::TestExternalities::default();
+		ext.register_extension(OffchainDbExt::new(offchain.clone()));
+		ext.register_extension(OffchainWorkerExt::new(offchain));
+		let tasks = (1..=4).map(MockTask::new).collect::<Vec<_>>();
+		set_pending(tasks.clone());
+		let is_cached = |task: &MockTask| {
+			let key = [offchain::VALIDITY_CACHE_PREFIX, &task.full_hash_code()[..]].concat();
+			state.read().persistent_storage.get(&key).is_some()
+		};
+		ext.execute_with(|| {
+			for task in &tasks[..3] {
+				assert!(offchain::cached_is_valid(task, 1u32));
+			}
+			assert!(tasks[..3].iter().all(is_cached));
+
+			// the tasks of block 1 are gone once another one is checked in block 2.
+			assert!(offchain::cached_is_valid(&tasks[3], 2u32));
+			assert!(!tasks[..3].iter().any(is_cached));
+			assert!(is_cached(&tasks[3]));
+		});
+	}
+
+	#[test]
+	fn enumerate_capped_bounds_infinite_enumeration() {
+		assert_eq!(CustomTask::enumerate_capped().count(), 7);
+	}
+
+	#[test]
+	fn run_returns_output() {
+		let task = CustomTask { payload: vec![10, 20, 60], ..Default::default() };
+		assert_eq!(task.run(), Ok(90));
+		// the output is what would be surfaced in an event.
+		assert_eq!(task.run().unwrap().encode(), 90u64.encode());
+		// runners that only care about the weight discard it.
+		assert_eq!(execute(&task), Ok(Weight::zero()));
+	}
+
+	#[test]
+	fn aggregator_decodes_by_task_index() {
+		crate::impl_task_aggregator! {
+			#[derive(Debug, PartialEq)]
+			enum RuntimeTask {
+				Mock(MockTask),
+				Custom(CustomTask),
+			}
+		}
+
+		let task = MockTask { deadline: Some(3), ..MockTask::new(7) };
+		let decoded = RuntimeTask::decode(MockTask::TASK_INDEX, &task.encode()).unwrap();
+		assert_eq!(decoded, RuntimeTask::Mock(task));
+		assert_eq!(decoded.task_index(), MockTask::TASK_INDEX);
+
+		let task = CustomTask { weight: 9, ..Default::default() };
+		let decoded = RuntimeTask::decode(CustomTask::TASK_INDEX, &task.encode()).unwrap();
+		assert_eq!(decoded, RuntimeTask::Custom(task));
+		assert_eq!(decoded.task_index(), CustomTask::TASK_INDEX);
+
+		// all aggregated task types are described, in order.
+		assert_eq!(RuntimeTask::metadata(), vec![MockTask::metadata(), CustomTask::metadata()]);
+
+		// unknown indices are rejected.
+		assert_eq!(
+			RuntimeTask::decode(1337, &CustomTask::default().encode()),
+			Err(DispatchError::Other("unknown task index"))
+		);
+		// as are bogus encodings, including trailing bytes.
+		assert_eq!(
+			RuntimeTask::decode(CustomTask::TASK_INDEX, &[7]),
+			Err(DispatchError::Other("failed to decode task"))
+		);
+		assert_eq!(
+			RuntimeTask::decode(CustomTask::TASK_INDEX, &(CustomTask::default(), 0u8).encode()),
+			Err(DispatchError::Other("failed to decode task"))
+		);
+	}
+
+	#[test]
+	fn execute_checks_postconditions() {
+		let task = MockTask { weight: 1, ..MockTask::new(1) };
+		set_pending([task.clone()]);
+		sp_io::TestExternalities::default().execute_with(|| {
+			assert_eq!(execute(&task), Ok(Weight::from_parts(1, 0)));
+			assert!(PENDING.with(|p| p.borrow().is_empty()));
+		});
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "did not achieve its postconditions")]
+	fn execute_catches_unmet_postconditions() {
+		let task = MockTask { buggy: true, ..MockTask::new(1) };
+		set_pending([task.clone()]);
+		sp_io::TestExternalities::default().execute_with(|| {
+			let _ = execute(&task);
+		});
+	}
+
+	#[test]
+	fn execute_batch_reports_failures() {
+		let err = DispatchError::Other("deliberate");
+		set_pending((1..=4).map(|id| MockTask {
+			weight: 10,
+			fail: id % 2 == 0,
+			..MockTask::new(id)
+		}));
+		sp_io::TestExternalities::default().execute_with(|| {
+			// failed tasks still consume their weight.
+			assert_eq!(
+				execute_batch::<MockTask, _>(&StorageTaskSource, Weight::from_parts(100, 0)),
+				Weight::from_parts(40, 0)
+			);
+			assert_eq!(FAILURES.with(|f| f.take()), vec![(2, err), (4, err)]);
+
+			// tasks that do not fit anymore are skipped.
+			assert_eq!(
+				execute_batch::<MockTask, _>(&StorageTaskSource, Weight::from_parts(15, 0)),
+				Weight::from_parts(10, 0)
+			);
+			assert_eq!(FAILURES.with(|f| f.take()), vec![(2, err)]);
+		});
+	}
+
+	#[test]
+	fn execute_batch_skips_tasks_that_do_not_fit() {
+		let task = |weight| CustomTask { weight, ..Default::default() };
+		sp_io::TestExternalities::default().execute_with(|| {
+			let tasks = vec![
+				// oversized, hence not charged.
+				CustomTask { payload: vec![0; 6], ..task(10) },
+				// too heavy for what is left, but does not stop the batch.
+				task(50),
+				task(20),
+				task(10),
+			];
+			assert_eq!(
+				execute_batch(&VecTaskSource(tasks), Weight::from_parts(30, 0)),
+				Weight::from_parts(30, 0)
+			);
+		});
+	}
+
+	#[test]
+	fn execute_batch_skips_invalid_tasks() {
+		let task = MockTask { weight: 1, ..MockTask::new(1) };
+		set_pending([task.clone()]);
+		sp_io::TestExternalities::default().execute_with(|| {
+			// the task is no longer valid once it ran.
+			let tasks = vec![task.clone(), task];
+			assert_eq!(execute_batch(&VecTaskSource(tasks), Weight::MAX), Weight::from_parts(1, 0));
+			assert_eq!(RAN.with(|r| r.take()), vec![1]);
+		});
+	}
+
+	#[test]
+	fn execute_batch_rolls_back_failed_tasks() {
+		set_pending([
+			MockTask { weight: 10, fail: true, ..MockTask::new(1) },
+			MockTask { weight: 10, ..MockTask::new(2) },
+		]);
+		sp_io::TestExternalities::default().execute_with(|| {
+			assert_eq!(
+				execute_batch::<MockTask, _>(&StorageTaskSource, Weight::MAX),
+				Weight::from_parts(20, 0)
+			);
+			assert_eq!(crate::storage::unhashed::get::<bool>(&1u32.encode()), None);
+			assert_eq!(crate::storage::unhashed::get::<bool>(&2u32.encode()), Some(true));
+		});
+	}
+
+	#[test]
+	fn execute_batch_grouped_runs_one_task_per_group() {
+		let tasks = [(1, 1), (2, 1), (3, 2)].map(|(id, group)| MockTask {
+			weight: 10,
+			group: Some(group),
+			..MockTask::new(id)
+		});
+		set_pending(tasks.clone());
+		sp_io::TestExternalities::default().execute_with(|| {
+			// 2 shares the group of 1.
+			let mut ran_groups = BTreeSet::new();
+			assert_eq!(
+				execute_batch_grouped::<MockTask, _>(
+					&StorageTaskSource,
+					Weight::MAX,
+					&mut ran_groups
+				),
+				Weight::from_parts(20, 0)
+			);
+			assert_eq!(RAN.with(|r| r.take()), vec![1, 3]);
+			assert_eq!(ran_groups, BTreeSet::from([1, 2]));
+
+			// nothing runs again with the same groups.
+			assert_eq!(
+				execute_batch_grouped::<MockTask, _>(
+					&StorageTaskSource,
+					Weight::MAX,
+					&mut ran_groups
+				),
+				Weight::zero()
+			);
+			assert!(RAN.with(|r| r.take()).is_empty());
+
+			// ungrouped batches run everything.
+			set_pending(tasks);
+			assert_eq!(
+				execute_batch::<MockTask, _>(&StorageTaskSource, Weight::MAX),
+				Weight::from_parts(30, 0)
+			);
+			assert_eq!(RAN.with(|r| r.take()), vec![1, 2, 3]);
+		});
+	}
+
+	#[test]
+	fn enumerate_paged_pages_through_all_tasks() {
+		set_pending((0..10).map(MockTask::new));
+		for page_size in 1..=11 {
+			let mut all = vec![];
+			let mut cursor = None;
+			loop {
+				let (page, next) = MockTask::enumerate_paged(cursor, page_size);
+				assert!(page.len() <= page_size as usize);
+				all.extend(page);
+				match next {
+					Some(_) => cursor = next,
+					None => break,
+				}
+			}
+			assert_eq!(all, MockTask::enumerate().collect::<Vec<_>>());
+		}
+
+		// the last page has no next page, even when it is full.
+		assert_eq!(MockTask::enumerate_paged(Some(5), 5).1, None);
+		assert_eq!(MockTask::enumerate_paged(Some(10), 5), (vec![], None));
+		assert_eq!(MockTask::enumerate_paged(Some(3), 0), (vec![], Some(3)));
+	}
+
+	#[test]
+	fn metadata_round_trips() {
+		let metadata = CustomTask::metadata();
+		let decoded = <(u64, String, String)>::decode(&mut &metadata.encode()[..]).unwrap();
+		assert_eq!(decoded, (1, "custom".to_string(), "overrides what it can".to_string()));
+
+		// the name defaults to the name of the type.
+		let metadata = MockTask::metadata();
+		assert_eq!(metadata.index, MockTask::TASK_INDEX);
+		assert!(metadata.name.ends_with("MockTask"));
+		assert_eq!(metadata.description, "");
+	}
+
+	#[test]
+	fn run_step_defaults_to_run() {
+		let task = MockTask::new(1);
+		set_pending([task.clone()]);
+		sp_io::TestExternalities::default().execute_with(|| {
+			assert_eq!(task.run_step(Weight::zero()), Ok(TaskProgress::Done));
+			assert_eq!(RAN.with(|r| r.take()), vec![1]);
+		});
+	}
+
+	#[test]
+	fn run_step_advances_across_calls() {
+		let budget = Weight::from_parts(2, 0);
+		let mut task = CustomTask { weight: 5, ..Default::default() };
+		let mut steps = vec![];
+		loop {
+			match task.run_step(budget).unwrap() {
+				TaskProgress::Done => break,
+				TaskProgress::Incomplete { next } => {
+					steps.push(next.weight);
+					// the next task survives being persisted in between.
+					task = CustomTask::decode(&mut &next.encode()[..]).unwrap();
+				},
+			}
+		}
+		assert_eq!(steps, vec![3, 1]);
+	}
+
+	#[test]
+	fn enumerate_partitioned_splits_mixed_tasks() {
+		set_pending((0..20).map(|id| MockTask { parallel: id % 2 == 0, ..MockTask::new(id) }));
+		let (parallel, serial) = enumerate_partitioned::<MockTask>();
+		assert_eq!(
+			parallel.iter().map(|t| t.id).collect::<Vec<_>>(),
+			(0..20).step_by(2).collect::<Vec<_>>()
+		);
+		assert_eq!(
+			serial.iter().map(|t| t.id).collect::<Vec<_>>(),
+			(1..20).step_by(2).collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn oversized_tasks_are_rejected() {
+		crate::impl_task_aggregator! {
+			#[derive(Debug, PartialEq)]
+			enum RuntimeTask {
+				Custom(CustomTask),
+			}
+		}
+
+		// the payload and its one byte length prefix, then 10 bytes of the other fields.
+		let fits = CustomTask { payload: vec![0; 5], ..Default::default() };
+		assert_eq!(fits.encoded_size(), 16);
+		assert_eq!(fits.ensure_size(), Ok(()));
+		assert_eq!(execute(&fits), Ok(Weight::zero()));
+		assert_eq!(RuntimeTask::decode(1, &fits.encode()), Ok(RuntimeTask::Custom(fits)));
+
+		let too_large = CustomTask { payload: vec![0; 6], ..Default::default() };
+		let err = DispatchError::Other("task exceeds its maximum encoded size");
+		assert_eq!(too_large.ensure_size(), Err(err));
+		assert_eq!(execute(&too_large), Err(TaskError::Oversized));
+		assert_eq!(RuntimeTask::decode(1, &too_large.encode()), Err(err));
+
+		// unbounded by default.
+		assert_eq!(MockTask::new(1).ensure_size(), Ok(()));
+	}
+
+	#[test]
+	fn execute_metered_reports_overweight_tasks() {
+		let margin = Perbill::from_percent(10);
+		let declared = Weight::from_parts(100, 0);
+		let task = |actual| CustomTask { weight: 100, actual: Some(actual), ..Default::default() };
+
+		// within the margin.
+		assert_eq!(execute_metered(&task(110), margin), Ok((Weight::from_parts(110, 0), None)));
+
+		// beyond the margin.
+		let actual = Weight::from_parts(250, 0);
+		assert_eq!(
+			execute_metered(&task(250), margin),
+			Ok((actual, Some(OverweightTask { task_index: 1, declared, actual })))
+		);
+
+		// tasks that are not metered report their declared weight.
+		let task = MockTask { weight: 100, ..MockTask::new(1) };
+		set_pending([task.clone()]);
+		sp_io::TestExternalities::default().execute_with(|| {
+			assert_eq!(execute_metered(&task, Perbill::zero()), Ok((declared, None)));
+		});
+	}
+
+	#[test]
+	fn group_index_defaults_to_task_index() {
+		assert_eq!(CustomTask::default().group_index(), CustomTask::TASK_INDEX);
+	}
+
+	#[test]
+	fn full_hash_code_distinguishes_tasks() {
+		let task = MockTask::new(1);
+		assert_eq!(task.full_hash_code(), task.clone().full_hash_code());
+		assert_ne!(task.full_hash_code(), MockTask::new(2).full_hash_code());
+		assert_ne!(
+			task.full_hash_code(),
+			MockTask { deadline: Some(1), ..MockTask::new(1) }.full_hash_code()
+		);
+	}
+
+	#[test]
+	fn enumerate_unexpired_keeps_tasks_without_deadline() {
+		assert_eq!(unexpired_ids(u64::MAX), vec![3]);
+	}
+
+	#[test]
+	fn enumerate_unexpired_drops_expired_tasks() {
+		// nothing expired yet.
+		assert_eq!(unexpired_ids(0), vec![1, 2, 3, 4]);
+		// the deadline block itself is still fine.
+		assert_eq!(unexpired_ids(5), vec![1, 2, 3, 4]);
+		// one block later, task 1 is stale.
+		assert_eq!(unexpired_ids(6), vec![2, 3, 4]);
+		assert_eq!(unexpired_ids(11), vec![3, 4]);
+		assert_eq!(unexpired_ids(16), vec![3]);
+		// regardless of the block number type.
+		let ids = enumerate_unexpired::<MockTask, u32>(11).map(|t| t.id).collect::<Vec<_>>();
+		assert_eq!(ids, vec![3, 4]);
+	}
+}
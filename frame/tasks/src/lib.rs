@@ -0,0 +1,662 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Tasks Pallet
+//!
+//! Executes the tasks of [`Config::Task`] with the weight left at the end of each block.
+//!
+//! ## Overview
+//!
+//! Tasks are enumerated through [`Task::enumerate_capped`] and executed in order, as long as they
+//! fit in the remaining weight. Each task runs in its own storage transaction, so that a failed
+//! task leaves no state change behind. Failed attempts are recorded in [`Attempts`], keyed by
+//! [`Task::full_hash_code`], so that flaky tasks are retried at most [`Task::max_attempts`] times,
+//! waiting [`Task::retry_backoff`] blocks between two attempts. A task that eventually succeeds
+//! has its attempts cleared.
+//!
+//! Tasks heavier than a whole block can never run. They are skipped without being attempted, and
+//! reported once through [`Event::PermanentlyOverweightTask`] so that operators can investigate.
+//!
+//! Tasks that are no longer enumerated leave their records behind, hence the entries of
+//! [`Attempts`] and [`OverweightTasks`] are forgotten [`Config::RecordRetention`] blocks after
+//! they were last updated. They are swept with the weight left by the tasks, see
+//! [`Pallet::prune_expired`]. The attempts of a task are also forgotten as soon as it is found to
+//! be invalid.
+//!
+//! A task with a non-zero [`Task::cooldown`] has the block of its last successful run recorded in
+//! [`LastRun`], and is not run again until its cooldown has elapsed.
+//!
+//! ## Kill-switch
+//!
+//! All task execution can be suspended in an emergency through [`Pallet::set_tasks_enabled`],
+//! without a runtime upgrade. While [`TasksEnabled`] is `false`, only the tasks matching
+//! [`Config::CriticalTasks`] are executed, and [`Event::TasksDisabled`] is emitted whenever other
+//! tasks are skipped.
+//!
+//! ## Scheduling
+//!
+//! Instead of waiting for an idle block, a task can be scheduled to run at a specific block
+//! through [`Pallet::schedule_task`], which hands [`Pallet::as_dispatchable`] over to
+//! [`Config::Scheduler`]. A scheduled task that is no longer valid when it fires is skipped, and
+//! [`Event::TaskSkipped`] is emitted.
+//!
+//! ## Queue
+//!
+//! Tasks are run in a deterministic order first, through a persistent queue of at most
+//! [`Config::MaxQueuedTasks`] tasks. [`Pallet::enqueue_tasks`] enumerates the tasks of
+//! [`Config::Task`] into [`QueuedTasks`], ordered by [`Task::priority`], highest first, then by
+//! [`Task::full_hash_code`]. [`Pallet::pop_next_valid`] then pops the first of them that is still
+//! valid, dropping the ones that are not. At the end of each block, [`Pallet::execute_queued`]
+//! runs the queued tasks that fit in the remaining weight, refilling the queue once it is empty,
+//! before the other tasks are executed with the weight left.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{
+	storage::IterableStorageMap,
+	traits::{
+		schedule::{self, v3::Anon as ScheduleAnon, DispatchTime},
+		tasks::{self, StorageTaskSource, TaskError, TaskSource, VecTaskSource},
+		Contains, Get, InvalidTaskReason, StorePreimage, Task,
+	},
+	weights::Weight,
+	BoundedBTreeSet,
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+use scale_info::TypeInfo;
+use sp_runtime::{DispatchError, RuntimeDebug, Saturating};
+
+/// The logging target of this pallet.
+pub const LOG_TARGET: &str = "runtime::tasks";
+
+#[macro_export]
+macro_rules! log {
+	($level:tt, $patter:expr $(, $values:expr)* $(,)?) => {
+		log::$level!(
+			target: $crate::LOG_TARGET,
+			concat!("[{:?}] 🧰 ", $patter), <frame_system::Pallet<T>>::block_number() $(, $values)*
+		)
+	};
+}
+
+/// The failed attempts at executing a task.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct AttemptInfo<BlockNumber> {
+	/// The number of failed attempts so far.
+	pub attempts: u32,
+	/// The block of the last failed attempt.
+	pub last_attempt: BlockNumber,
+}
+
+/// Where the sweep of [`Pallet::prune_expired`] resumes.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum PruneCursor {
+	/// In [`Attempts`], after the given key if any.
+	Attempts(Option<[u8; 32]>),
+	/// In [`OverweightTasks`], after the given key if any.
+	OverweightTasks(Option<[u8; 32]>),
+}
+
+impl Default for PruneCursor {
+	fn default() -> Self {
+		PruneCursor::Attempts(None)
+	}
+}
+
+/// The position of a task in [`QueuedTasks`]: the complement of its [`Task::priority`], so that
+/// higher priorities come first, and its [`Task::full_hash_code`].
+pub type QueueKey = (u8, [u8; 32]);
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// The tasks executed by this pallet.
+		type Task: Task;
+
+		/// The tasks that are still executed while the kill-switch is engaged.
+		type CriticalTasks: Contains<Self::Task>;
+
+		/// The overarching call type.
+		type RuntimeCall: Parameter
+			+ From<Call<Self>>
+			+ IsType<<Self as frame_system::Config>::RuntimeCall>;
+
+		/// The caller origin, overarching type of all pallets origins.
+		type PalletsOrigin: From<frame_system::RawOrigin<Self::AccountId>>;
+
+		/// The scheduler used by [`Pallet::schedule_task`].
+		type Scheduler: ScheduleAnon<
+			BlockNumberFor<Self>,
+			<Self as Config>::RuntimeCall,
+			Self::PalletsOrigin,
+		>;
+
+		/// The preimage provider used to bound scheduled calls.
+		type Preimages: StorePreimage;
+
+		/// The maximum number of tasks in [`QueuedTasks`].
+		#[pallet::constant]
+		type MaxQueuedTasks: Get<u32>;
+
+		/// The number of blocks after which the entries of [`Attempts`] and [`OverweightTasks`]
+		/// are forgotten.
+		#[pallet::constant]
+		type RecordRetention: Get<BlockNumberFor<Self>>;
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// Task execution was enabled or disabled.
+		TasksEnabledSet { enabled: bool },
+		/// Tasks were skipped because task execution is disabled.
+		TasksDisabled,
+		/// A scheduled task was skipped because it was no longer valid.
+		TaskSkipped { task: T::Task, reason: InvalidTaskReason },
+		/// A task declares more weight than a whole block, hence it can never run.
+		PermanentlyOverweightTask { task_index: u64, hash: [u8; 32] },
+	}
+
+	#[pallet::type_value]
+	pub fn DefaultTasksEnabled() -> bool {
+		true
+	}
+
+	/// Whether tasks are executed at all, see [`Pallet::set_tasks_enabled`].
+	#[pallet::storage]
+	pub type TasksEnabled<T: Config> = StorageValue<_, bool, ValueQuery, DefaultTasksEnabled>;
+
+	/// The failed attempts at executing each task, keyed by [`Task::full_hash_code`].
+	///
+	/// Entries are removed once the task succeeds or is found to be invalid, and kept once it ran
+	/// out of attempts so that it is not attempted anymore, until [`Config::RecordRetention`]
+	/// blocks have elapsed since the last attempt.
+	#[pallet::storage]
+	pub type Attempts<T: Config> =
+		StorageMap<_, Identity, [u8; 32], AttemptInfo<BlockNumberFor<T>>, OptionQuery>;
+
+	/// The block at which each task with a [`Task::cooldown`] last ran successfully, keyed by
+	/// [`Task::full_hash_code`].
+	#[pallet::storage]
+	pub type LastRun<T: Config> = StorageMap<_, Identity, [u8; 32], BlockNumberFor<T>, OptionQuery>;
+
+	/// The block at which each task was reported through [`Event::PermanentlyOverweightTask`],
+	/// keyed by [`Task::full_hash_code`], so that each of them is only reported once every
+	/// [`Config::RecordRetention`] blocks.
+	#[pallet::storage]
+	pub type OverweightTasks<T: Config> =
+		StorageMap<_, Identity, [u8; 32], BlockNumberFor<T>, OptionQuery>;
+
+	/// Where the sweep of [`Pallet::prune_expired`] resumes.
+	#[pallet::storage]
+	pub type PruneFrom<T: Config> = StorageValue<_, PruneCursor, ValueQuery>;
+
+	/// The queued tasks, in the order in which they are popped.
+	#[pallet::storage]
+	pub type QueuedTasks<T: Config> =
+		StorageValue<_, BoundedBTreeSet<QueueKey, T::MaxQueuedTasks>, ValueQuery>;
+
+	/// The body of each task in [`QueuedTasks`], keyed by [`Task::full_hash_code`].
+	#[pallet::storage]
+	#[pallet::unbounded]
+	pub type QueuedTaskOf<T: Config> = StorageMap<_, Identity, [u8; 32], T::Task, OptionQuery>;
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Enable or disable the execution of all tasks, except for [`Config::CriticalTasks`].
+		///
+		/// Can only be called by root.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::DbWeight::get().writes(1))]
+		pub fn set_tasks_enabled(origin: OriginFor<T>, enabled: bool) -> DispatchResult {
+			ensure_root(origin)?;
+			TasksEnabled::<T>::put(enabled);
+			Self::deposit_event(Event::<T>::TasksEnabledSet { enabled });
+			Ok(())
+		}
+
+		/// Execute `task` right away, typically on behalf of [`Config::Scheduler`].
+		///
+		/// An invalid task is skipped rather than failing the call, as is any task but the
+		/// [`Config::CriticalTasks`] while [`TasksEnabled`] is `false`. Failed tasks are not
+		/// retried.
+		///
+		/// Can only be called by root.
+		#[pallet::call_index(1)]
+		#[pallet::weight(task.weight().saturating_add(T::DbWeight::get().reads(1)))]
+		pub fn run_task(origin: OriginFor<T>, task: T::Task) -> DispatchResult {
+			ensure_root(origin)?;
+			if !TasksEnabled::<T>::get() && !T::CriticalTasks::contains(&task) {
+				Self::deposit_event(Event::<T>::TasksDisabled);
+				return Ok(())
+			}
+
+			match tasks::execute(&task) {
+				Ok(_) => Ok(()),
+				Err(TaskError::Invalid(reason)) => {
+					log!(debug, "scheduled task {:?} is no longer valid: {:?}", task, reason);
+					Self::deposit_event(Event::<T>::TaskSkipped { task, reason });
+					Ok(())
+				},
+				Err(TaskError::Failed(err)) => Err(err),
+				Err(TaskError::Oversized) =>
+					Err(DispatchError::Other("task exceeds its maximum encoded size")),
+			}
+		}
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_idle(_n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			let mut consumed = Self::execute_queued(remaining_weight);
+			consumed
+				.saturating_accrue(Self::execute_all(remaining_weight.saturating_sub(consumed)));
+			consumed.saturating_add(Self::prune_expired(remaining_weight.saturating_sub(consumed)))
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// The call executing `task`, see [`Pallet::run_task`].
+	pub fn as_dispatchable(task: T::Task) -> <T as Config>::RuntimeCall {
+		Call::<T>::run_task { task }.into()
+	}
+
+	/// Schedule `task` to be executed as root at the beginning of block `when`.
+	///
+	/// The task is scheduled with a hard deadline, so that it is not postponed to a later
+	/// block.
+	pub fn schedule_task(
+		task: T::Task,
+		when: BlockNumberFor<T>,
+	) -> Result<
+		<T::Scheduler as ScheduleAnon<
+			BlockNumberFor<T>,
+			<T as Config>::RuntimeCall,
+			T::PalletsOrigin,
+		>>::Address,
+		DispatchError,
+	> {
+		let call = T::Preimages::bound(Self::as_dispatchable(task))?;
+		T::Scheduler::schedule(
+			DispatchTime::At(when),
+			None,
+			schedule::HARD_DEADLINE,
+			frame_system::RawOrigin::Root.into(),
+			call,
+		)
+	}
+
+	/// Executes the tasks of [`Config::Task`] in order, as long as they fit in `max_weight`.
+	///
+	/// Tasks that do not fit in the remaining weight are skipped, lighter ones after them may
+	/// still run.
+	///
+	/// Tasks that ran out of attempts, or whose retry backoff or cooldown has not elapsed yet, are
+	/// skipped, as are all but the [`Config::CriticalTasks`] if [`TasksEnabled`] is `false`, and
+	/// tasks heavier than a whole block, see [`Self::is_permanently_overweight`]. Returns the
+	/// weight consumed.
+	pub fn execute_all(max_weight: Weight) -> Weight {
+		Self::execute_from(&StorageTaskSource, max_weight)
+	}
+
+	/// Same as [`Self::execute_all`], but executes the tasks of `source` instead of enumerating
+	/// those of [`Config::Task`].
+	pub fn execute_from<S: TaskSource<T::Task>>(source: &S, max_weight: Weight) -> Weight {
+		let now = frame_system::Pallet::<T>::block_number();
+		let db_weight = T::DbWeight::get();
+		let mut consumed = db_weight.reads(1);
+		if consumed.any_gt(max_weight) {
+			return Weight::zero()
+		}
+		let enabled = TasksEnabled::<T>::get();
+		let max_block = T::BlockWeights::get().max_block;
+		let mut skipped = false;
+
+		for task in source.tasks() {
+			if !enabled && !T::CriticalTasks::contains(&task) {
+				skipped = true;
+				continue
+			}
+
+			if Self::is_permanently_overweight(&task, max_block) {
+				// reading and updating the reported tasks.
+				let report_weight = db_weight.reads_writes(1, 1);
+				if consumed.saturating_add(report_weight).any_gt(max_weight) {
+					continue
+				}
+				consumed.saturating_accrue(report_weight);
+				Self::report_overweight(&task);
+				continue
+			}
+
+			let task_weight = task.weight();
+			let cooldown = task.cooldown();
+			if consumed.saturating_add(Self::worst_case_weight(&task)).any_gt(max_weight) {
+				continue
+			}
+
+			let key = task.full_hash_code();
+			let maybe_info = Attempts::<T>::get(key);
+			consumed.saturating_accrue(db_weight.reads(1));
+
+			if let Some(info) = &maybe_info {
+				if info.attempts >= task.max_attempts() {
+					continue
+				}
+				if now < info.last_attempt.saturating_add(task.retry_backoff().into()) {
+					continue
+				}
+			}
+
+			if cooldown > 0 {
+				consumed.saturating_accrue(db_weight.reads(1));
+				if let Some(last_run) = LastRun::<T>::get(key) {
+					if now < last_run.saturating_add(cooldown.into()) {
+						continue
+					}
+				}
+			}
+
+			match tasks::execute_transactional(&task) {
+				Ok(_) => {
+					if maybe_info.is_some() {
+						Attempts::<T>::remove(key);
+						consumed.saturating_accrue(db_weight.writes(1));
+					}
+					if cooldown > 0 {
+						LastRun::<T>::insert(key, now);
+						consumed.saturating_accrue(db_weight.writes(1));
+					}
+				},
+				Err(TaskError::Failed(_)) => {
+					let attempts = maybe_info.map_or(0, |i| i.attempts).saturating_add(1);
+					log!(
+						debug,
+						"task {:?} failed, attempt {} of {}",
+						task,
+						attempts,
+						task.max_attempts()
+					);
+					Attempts::<T>::insert(key, AttemptInfo { attempts, last_attempt: now });
+					consumed.saturating_accrue(db_weight.writes(1));
+				},
+				Err(TaskError::Invalid(_)) => {
+					if maybe_info.is_some() {
+						Attempts::<T>::remove(key);
+						consumed.saturating_accrue(db_weight.writes(1));
+					}
+					continue
+				},
+				Err(TaskError::Oversized) => continue,
+			}
+			consumed.saturating_accrue(task_weight);
+		}
+
+		if skipped {
+			log!(debug, "task execution is disabled, skipping non-critical tasks");
+			Self::deposit_event(Event::<T>::TasksDisabled);
+		}
+
+		consumed
+	}
+
+	/// The weight of running `task` through [`Self::execute_from`], at worst, except for reading
+	/// [`TasksEnabled`].
+	fn worst_case_weight(task: &T::Task) -> Weight {
+		let max_block = T::BlockWeights::get().max_block;
+		if Self::is_permanently_overweight(task, max_block) {
+			// reading and updating the reported tasks.
+			return T::DbWeight::get().reads_writes(1, 1)
+		}
+		// reading and updating the attempts, and the last run if the task has a cooldown, on top
+		// of the task itself.
+		let lookups = if task.cooldown() > 0 { 2 } else { 1 };
+		task.weight().saturating_add(T::DbWeight::get().reads_writes(lookups, lookups))
+	}
+
+	/// Whether `task` declares more weight than `max_block`, in which case it can never run.
+	pub fn is_permanently_overweight(task: &T::Task, max_block: Weight) -> bool {
+		task.weight().any_gt(max_block)
+	}
+
+	/// Reports the permanently overweight `task` through [`Event::PermanentlyOverweightTask`],
+	/// unless it was already reported.
+	fn report_overweight(task: &T::Task) {
+		let hash = task.full_hash_code();
+		if OverweightTasks::<T>::contains_key(hash) {
+			return
+		}
+		log!(warn, "task {:?} is heavier than a whole block, it can never run", task);
+		OverweightTasks::<T>::insert(hash, frame_system::Pallet::<T>::block_number());
+		Self::deposit_event(Event::<T>::PermanentlyOverweightTask {
+			task_index: task.task_index(),
+			hash,
+		});
+	}
+
+	/// Removes the entries of [`Attempts`] and [`OverweightTasks`] that were last updated at least
+	/// [`Config::RecordRetention`] blocks ago, as long as they fit in `max_weight`.
+	///
+	/// The sweep resumes where it stopped in the previous call, see [`PruneFrom`], and goes over
+	/// each map at most once per call. Returns the weight consumed.
+	pub fn prune_expired(max_weight: Weight) -> Weight {
+		// reading and updating the cursor.
+		let mut consumed = T::DbWeight::get().reads_writes(1, 1);
+		if consumed.any_gt(max_weight) {
+			return Weight::zero()
+		}
+		let now = frame_system::Pallet::<T>::block_number();
+		let retention = T::RecordRetention::get();
+		let expired = |since: BlockNumberFor<T>| now >= since.saturating_add(retention);
+
+		let mut cursor = PruneFrom::<T>::get();
+		for _ in 0..2 {
+			let swept = match cursor {
+				PruneCursor::Attempts(from) => Self::prune_map::<Attempts<T>, _>(
+					from,
+					|info| expired(info.last_attempt),
+					&mut consumed,
+					max_weight,
+				)
+				.map_err(PruneCursor::Attempts),
+				PruneCursor::OverweightTasks(from) => Self::prune_map::<OverweightTasks<T>, _>(
+					from,
+					|reported| expired(*reported),
+					&mut consumed,
+					max_weight,
+				)
+				.map_err(PruneCursor::OverweightTasks),
+			};
+			match swept {
+				Ok(()) =>
+					cursor = match cursor {
+						PruneCursor::Attempts(_) => PruneCursor::OverweightTasks(None),
+						PruneCursor::OverweightTasks(_) => PruneCursor::Attempts(None),
+					},
+				Err(stopped) => {
+					cursor = stopped;
+					break
+				},
+			}
+		}
+		PruneFrom::<T>::put(cursor);
+		consumed
+	}
+
+	/// Removes the entries of `M` after `from` for which `is_expired` holds, as long as they fit
+	/// in `max_weight`.
+	///
+	/// Returns the key after which to resume if `M` could not be swept to its end.
+	fn prune_map<M, V>(
+		from: Option<[u8; 32]>,
+		is_expired: impl Fn(&V) -> bool,
+		consumed: &mut Weight,
+		max_weight: Weight,
+	) -> Result<(), Option<[u8; 32]>>
+	where
+		M: IterableStorageMap<[u8; 32], V>,
+		V: codec::FullCodec,
+	{
+		// reading and removing each entry.
+		let entry_weight = T::DbWeight::get().reads_writes(1, 1);
+		let entries = match from {
+			Some(key) => M::iter_from(M::hashed_key_for(key)),
+			None => M::iter(),
+		};
+		let mut last = from;
+		for (key, value) in entries {
+			if consumed.saturating_add(entry_weight).any_gt(max_weight) {
+				return Err(last)
+			}
+			consumed.saturating_accrue(entry_weight);
+			if is_expired(&value) {
+				M::remove(key);
+			}
+			last = Some(key);
+		}
+		Ok(())
+	}
+
+	/// The key of `task` in [`QueuedTasks`].
+	pub fn queue_key(task: &T::Task) -> QueueKey {
+		(u8::MAX - task.priority(), task.full_hash_code())
+	}
+
+	/// Enumerates the tasks of [`Config::Task`] into [`QueuedTasks`], until it is full.
+	///
+	/// Tasks that are already queued keep their place. Returns the number of tasks queued.
+	pub fn enqueue_tasks() -> u32 {
+		let mut queue = QueuedTasks::<T>::get();
+		let mut queued = 0;
+		for task in T::Task::enumerate_capped() {
+			let key = Self::queue_key(&task);
+			if queue.contains(&key) {
+				continue
+			}
+			if queue.try_insert(key).is_err() {
+				log!(debug, "task queue is full, not queueing further tasks");
+				break
+			}
+			QueuedTaskOf::<T>::insert(key.1, task);
+			queued += 1;
+		}
+		QueuedTasks::<T>::put(queue);
+		queued
+	}
+
+	/// Executes the tasks of [`QueuedTasks`] in order, as long as they fit in `max_weight`, see
+	/// [`Self::pop_next_valid`].
+	///
+	/// The queue is refilled through [`Self::enqueue_tasks`] once it is empty. Each task runs
+	/// through [`Self::execute_from`], hence it is subject to the same attempts and cooldown as
+	/// the others. The first task that does not fit is put back in the queue, so that it runs
+	/// first in a later block. Nothing is executed while [`TasksEnabled`] is `false`, the queue is
+	/// left for when it is re-enabled. Returns the weight consumed.
+	pub fn execute_queued(max_weight: Weight) -> Weight {
+		let db_weight = T::DbWeight::get();
+		let max_queued = u64::from(T::MaxQueuedTasks::get());
+		// reading whether tasks are enabled, and the queue.
+		let mut consumed = db_weight.reads(2);
+		if consumed.any_gt(max_weight) {
+			return Weight::zero()
+		}
+		if !TasksEnabled::<T>::get() {
+			return consumed
+		}
+
+		if QueuedTasks::<T>::get().is_empty() {
+			// writing the queue, and the body of each queued task.
+			let enqueue_weight = db_weight.writes(max_queued.saturating_add(1));
+			if consumed.saturating_add(enqueue_weight).any_gt(max_weight) {
+				return consumed
+			}
+			consumed.saturating_accrue(enqueue_weight);
+			Self::enqueue_tasks();
+		}
+
+		// reading and updating the queue, taking the body of up to all queued tasks, and putting
+		// the body of the popped task back if it does not fit.
+		let pop_weight =
+			db_weight.reads_writes(max_queued.saturating_add(1), max_queued.saturating_add(2));
+		while !consumed.saturating_add(pop_weight).any_gt(max_weight) {
+			let task = match Self::pop_next_valid() {
+				Some(task) => task,
+				None => break,
+			};
+			consumed.saturating_accrue(pop_weight);
+
+			let required = db_weight.reads(1).saturating_add(Self::worst_case_weight(&task));
+			if consumed.saturating_add(required).any_gt(max_weight) {
+				Self::requeue(task);
+				break
+			}
+			consumed.saturating_accrue(Self::execute_from(
+				&VecTaskSource(sp_std::vec![task]),
+				max_weight.saturating_sub(consumed),
+			));
+		}
+		consumed
+	}
+
+	/// Puts the popped `task` back at its place in [`QueuedTasks`].
+	fn requeue(task: T::Task) {
+		let key = Self::queue_key(&task);
+		if QueuedTasks::<T>::mutate(|queue| queue.try_insert(key)).is_ok() {
+			QueuedTaskOf::<T>::insert(key.1, task);
+		}
+	}
+
+	/// Pops the first task of [`QueuedTasks`] that is still valid.
+	///
+	/// The tasks before it, which are no longer valid, are dropped from the queue.
+	pub fn pop_next_valid() -> Option<T::Task> {
+		let mut queue = QueuedTasks::<T>::get();
+		let mut next = None;
+		while let Some(key) = queue.iter().next().copied() {
+			queue.remove(&key);
+			match QueuedTaskOf::<T>::take(key.1) {
+				Some(task) if task.is_valid() => {
+					next = Some(task);
+					break
+				},
+				maybe_task => log!(debug, "dropping stale queued task {:?}", maybe_task),
+			}
+		}
+		QueuedTasks::<T>::put(queue);
+		next
+	}
+}
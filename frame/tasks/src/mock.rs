@@ -0,0 +1,247 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test utilities.
+
+use crate::{self as pallet_tasks, *};
+use frame_support::{
+	parameter_types,
+	traits::{ConstU32, ConstU64, Contains, EqualPrivilegeOnly, Everything, Hooks},
+};
+use frame_system::EnsureRoot;
+use sp_core::H256;
+use sp_runtime::{
+	traits::{BlakeTwo256, IdentityLookup},
+	BuildStorage, DispatchError,
+};
+
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime!(
+	pub enum Test
+	{
+		System: frame_system,
+		Tasks: pallet_tasks,
+		Scheduler: pallet_scheduler,
+		Preimage: pallet_preimage,
+	}
+);
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type RuntimeOrigin = RuntimeOrigin;
+	type Nonce = u64;
+	type RuntimeCall = RuntimeCall;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Block = Block;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = ();
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+/// The tasks of the mock, each of which is attempted at most 3 times, every other block.
+#[derive(Encode, Decode, TypeInfo, Clone, Debug, PartialEq, Eq)]
+pub enum MockTask {
+	/// Fails [`FlakyFailures`] times, then succeeds.
+	Flaky,
+	/// Always fails.
+	Broken,
+	/// Always succeeds, with the given priority.
+	Prioritized(u8),
+	/// Always succeeds and stays pending, with a cooldown of 3 blocks.
+	Periodic(u32),
+	/// Heavier than a whole block.
+	Heavy,
+	/// Always succeeds, but weighs as much as three of the others.
+	Bulky,
+}
+
+parameter_types! {
+	pub static Pending: Vec<MockTask> = vec![];
+	pub static FlakyFailures: u32 = 0;
+	pub static Runs: Vec<MockTask> = vec![];
+}
+
+impl Task for MockTask {
+	type Enumeration = sp_std::vec::IntoIter<Self>;
+	type Output = ();
+
+	const TASK_INDEX: u64 = 0;
+
+	fn enumerate() -> Self::Enumeration {
+		Pending::get().into_iter()
+	}
+
+	fn is_valid(&self) -> bool {
+		Pending::get().contains(self)
+	}
+
+	fn run(&self) -> Result<(), DispatchError> {
+		Runs::mutate(|r| r.push(self.clone()));
+		match self {
+			MockTask::Flaky if FlakyFailures::get() > 0 => {
+				FlakyFailures::mutate(|f| *f -= 1);
+				Err(DispatchError::Other("flaky"))
+			},
+			MockTask::Flaky => {
+				Pending::mutate(|p| p.retain(|t| t != self));
+				Ok(())
+			},
+			MockTask::Broken => Err(DispatchError::Other("broken")),
+			MockTask::Prioritized(_) | MockTask::Bulky => {
+				Pending::mutate(|p| p.retain(|t| t != self));
+				Ok(())
+			},
+			MockTask::Periodic(_) | MockTask::Heavy => Ok(()),
+		}
+	}
+
+	fn weight(&self) -> Weight {
+		match self {
+			MockTask::Heavy => Weight::MAX,
+			MockTask::Bulky => Weight::from_parts(30, 0),
+			_ => Weight::from_parts(10, 0),
+		}
+	}
+
+	fn check_postconditions(&self) -> bool {
+		matches!(self, MockTask::Periodic(_)) || !Pending::get().contains(self)
+	}
+
+	fn max_attempts(&self) -> u32 {
+		3
+	}
+
+	fn retry_backoff(&self) -> u32 {
+		2
+	}
+
+	fn cooldown(&self) -> u32 {
+		match self {
+			MockTask::Periodic(_) => 3,
+			_ => 0,
+		}
+	}
+
+	fn priority(&self) -> u8 {
+		match self {
+			MockTask::Prioritized(priority) => *priority,
+			_ => 0,
+		}
+	}
+}
+
+parameter_types! {
+	pub static CriticalFlaky: bool = false;
+}
+
+/// Considers [`MockTask::Flaky`] critical if [`CriticalFlaky`] is set.
+pub struct MockCriticalTasks;
+impl Contains<MockTask> for MockCriticalTasks {
+	fn contains(task: &MockTask) -> bool {
+		CriticalFlaky::get() && *task == MockTask::Flaky
+	}
+}
+
+impl pallet_preimage::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+	type Currency = ();
+	type ManagerOrigin = EnsureRoot<u64>;
+	type BaseDeposit = ();
+	type ByteDeposit = ();
+}
+
+parameter_types! {
+	pub MaximumSchedulerWeight: Weight = Weight::from_parts(1_000_000, 0);
+}
+
+impl pallet_scheduler::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeOrigin = RuntimeOrigin;
+	type PalletsOrigin = OriginCaller;
+	type RuntimeCall = RuntimeCall;
+	type MaximumWeight = MaximumSchedulerWeight;
+	type ScheduleOrigin = EnsureRoot<u64>;
+	type MaxScheduledPerBlock = ConstU32<10>;
+	type WeightInfo = ();
+	type OriginPrivilegeCmp = EqualPrivilegeOnly;
+	type Preimages = Preimage;
+}
+
+impl pallet_tasks::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type Task = MockTask;
+	type CriticalTasks = MockCriticalTasks;
+	type RuntimeCall = RuntimeCall;
+	type PalletsOrigin = OriginCaller;
+	type Scheduler = Scheduler;
+	type Preimages = Preimage;
+	type MaxQueuedTasks = ConstU32<4>;
+	type RecordRetention = ConstU64<20>;
+}
+
+/// All the events of this pallet deposited so far.
+pub(crate) fn tasks_events() -> Vec<Event<Test>> {
+	System::events()
+		.into_iter()
+		.map(|r| r.event)
+		.filter_map(|e| if let RuntimeEvent::Tasks(inner) = e { Some(inner) } else { None })
+		.collect()
+}
+
+/// Runs `on_idle` for the current block with unlimited weight, then moves on to the next block.
+pub(crate) fn finish_block() {
+	finish_block_within(Weight::MAX);
+}
+
+/// Same as [`finish_block`], with only `max_weight` left for `on_idle`.
+pub(crate) fn finish_block_within(max_weight: Weight) {
+	Tasks::on_idle(System::block_number(), max_weight);
+	System::set_block_number(System::block_number() + 1);
+}
+
+/// Runs the scheduler, but not `on_idle`, up to block `n`.
+pub(crate) fn run_scheduler_to_block(n: u64) {
+	while System::block_number() < n {
+		Scheduler::on_finalize(System::block_number());
+		System::set_block_number(System::block_number() + 1);
+		Scheduler::on_initialize(System::block_number());
+	}
+}
+
+pub(crate) fn new_test_ext(pending: Vec<MockTask>) -> sp_io::TestExternalities {
+	Pending::set(pending);
+	let storage = frame_system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	let mut ext = sp_io::TestExternalities::from(storage);
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
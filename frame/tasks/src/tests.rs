@@ -0,0 +1,420 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for pallet-tasks.
+
+use super::*;
+use crate::mock::*;
+use frame_support::{assert_noop, assert_ok, traits::VecTaskSource};
+use sp_runtime::traits::BadOrigin;
+
+fn attempts_of(task: MockTask) -> Option<AttemptInfo<u64>> {
+	Attempts::<Test>::get(task.full_hash_code())
+}
+
+#[test]
+fn succeeding_task_leaves_no_trace() {
+	new_test_ext(vec![MockTask::Flaky]).execute_with(|| {
+		finish_block();
+		assert_eq!(Runs::get(), vec![MockTask::Flaky]);
+		assert!(Pending::get().is_empty());
+		assert_eq!(Attempts::<Test>::iter().count(), 0);
+	});
+}
+
+#[test]
+fn flaky_task_is_retried_until_it_succeeds() {
+	FlakyFailures::set(2);
+	new_test_ext(vec![MockTask::Flaky]).execute_with(|| {
+		// first attempt fails.
+		finish_block();
+		assert_eq!(Runs::get().len(), 1);
+		assert_eq!(
+			attempts_of(MockTask::Flaky),
+			Some(AttemptInfo { attempts: 1, last_attempt: 1 })
+		);
+
+		// backoff has not elapsed yet.
+		finish_block();
+		assert_eq!(Runs::get().len(), 1);
+
+		// second attempt fails as well.
+		finish_block();
+		assert_eq!(Runs::get().len(), 2);
+		assert_eq!(
+			attempts_of(MockTask::Flaky),
+			Some(AttemptInfo { attempts: 2, last_attempt: 3 })
+		);
+
+		finish_block();
+		assert_eq!(Runs::get().len(), 2);
+
+		// third attempt succeeds, and the attempts are cleared.
+		finish_block();
+		assert_eq!(Runs::get().len(), 3);
+		assert!(Pending::get().is_empty());
+		assert_eq!(attempts_of(MockTask::Flaky), None);
+	});
+}
+
+#[test]
+fn task_is_given_up_after_max_attempts() {
+	new_test_ext(vec![MockTask::Broken]).execute_with(|| {
+		for _ in 0..10 {
+			finish_block();
+		}
+
+		// attempted at blocks 1, 3 and 5 only.
+		assert_eq!(Runs::get(), vec![MockTask::Broken; 3]);
+		assert_eq!(
+			attempts_of(MockTask::Broken),
+			Some(AttemptInfo { attempts: 3, last_attempt: 5 })
+		);
+	});
+}
+
+#[test]
+fn task_is_not_rerun_within_cooldown() {
+	new_test_ext(vec![MockTask::Periodic(0)]).execute_with(|| {
+		finish_block();
+		assert_eq!(Runs::get(), vec![MockTask::Periodic(0)]);
+		assert_eq!(LastRun::<Test>::get(MockTask::Periodic(0).full_hash_code()), Some(1));
+
+		// still valid, but cooling down at blocks 2 and 3.
+		finish_block();
+		finish_block();
+		assert_eq!(Runs::get().len(), 1);
+
+		// runs again once the cooldown has elapsed.
+		finish_block();
+		assert_eq!(Runs::get(), vec![MockTask::Periodic(0); 2]);
+		assert_eq!(LastRun::<Test>::get(MockTask::Periodic(0).full_hash_code()), Some(4));
+	});
+}
+
+#[test]
+fn task_with_new_inputs_is_not_subject_to_old_cooldown() {
+	new_test_ext(vec![MockTask::Periodic(0)]).execute_with(|| {
+		finish_block();
+
+		// the inputs of the task change, hence its hash.
+		Pending::set(vec![MockTask::Periodic(1)]);
+		finish_block();
+		assert_eq!(Runs::get(), vec![MockTask::Periodic(0), MockTask::Periodic(1)]);
+		assert_eq!(LastRun::<Test>::get(MockTask::Periodic(1).full_hash_code()), Some(2));
+	});
+}
+
+#[test]
+fn overweight_task_is_skipped_and_reported_once() {
+	new_test_ext(vec![MockTask::Heavy, MockTask::Flaky]).execute_with(|| {
+		finish_block();
+		finish_block();
+
+		assert_eq!(Runs::get(), vec![MockTask::Flaky]);
+		assert_eq!(Pending::get(), vec![MockTask::Heavy]);
+		assert_eq!(attempts_of(MockTask::Heavy), None);
+		assert_eq!(
+			tasks_events(),
+			vec![Event::PermanentlyOverweightTask {
+				task_index: 0,
+				hash: MockTask::Heavy.full_hash_code(),
+			}]
+		);
+	});
+}
+
+#[test]
+fn invalid_task_forgets_its_attempts() {
+	FlakyFailures::set(1);
+	new_test_ext(vec![MockTask::Flaky]).execute_with(|| {
+		finish_block();
+		assert!(attempts_of(MockTask::Flaky).is_some());
+
+		// the task is no longer valid by the time it is retried.
+		Pending::set(vec![]);
+		finish_block();
+		Tasks::execute_from(&VecTaskSource(vec![MockTask::Flaky]), Weight::MAX);
+		assert_eq!(Runs::get().len(), 1);
+		assert_eq!(attempts_of(MockTask::Flaky), None);
+	});
+}
+
+#[test]
+fn records_expire_after_retention() {
+	new_test_ext(vec![MockTask::Broken, MockTask::Heavy]).execute_with(|| {
+		// the broken task is last attempted at block 5, the heavy one reported at block 1.
+		for _ in 0..6 {
+			finish_block();
+		}
+		Pending::set(vec![]);
+		assert_eq!(OverweightTasks::<Test>::get(MockTask::Heavy.full_hash_code()), Some(1));
+
+		// the retention is 20 blocks.
+		System::set_block_number(21);
+		finish_block();
+		assert!(attempts_of(MockTask::Broken).is_some());
+		assert_eq!(OverweightTasks::<Test>::iter().count(), 0);
+
+		System::set_block_number(24);
+		finish_block();
+		assert!(attempts_of(MockTask::Broken).is_some());
+		finish_block();
+		assert_eq!(Attempts::<Test>::iter().count(), 0);
+	});
+}
+
+#[test]
+fn expired_overweight_task_is_reported_again() {
+	new_test_ext(vec![MockTask::Heavy]).execute_with(|| {
+		finish_block();
+		// forgotten at block 21, then reported again.
+		System::set_block_number(21);
+		finish_block();
+		finish_block();
+		assert_eq!(tasks_events().len(), 2);
+		assert_eq!(OverweightTasks::<Test>::get(MockTask::Heavy.full_hash_code()), Some(22));
+	});
+}
+
+#[test]
+fn execute_all_respects_max_weight() {
+	new_test_ext(vec![MockTask::Flaky, MockTask::Broken]).execute_with(|| {
+		assert_eq!(Tasks::execute_all(Weight::from_parts(15, 0)), Weight::from_parts(10, 0));
+		assert_eq!(Runs::get(), vec![MockTask::Flaky]);
+
+		assert_eq!(Tasks::execute_all(Weight::from_parts(15, 0)), Weight::from_parts(10, 0));
+		assert_eq!(Runs::get(), vec![MockTask::Flaky, MockTask::Broken]);
+	});
+}
+
+#[test]
+fn execute_from_budgets_weight_over_given_tasks() {
+	let pending = vec![MockTask::Flaky, MockTask::Prioritized(1), MockTask::Prioritized(2)];
+	new_test_ext(pending).execute_with(|| {
+		// only the given tasks are considered, in the given order, and only two of them fit.
+		let source = VecTaskSource(vec![
+			MockTask::Prioritized(2),
+			MockTask::Prioritized(1),
+			MockTask::Prioritized(3),
+		]);
+		assert_eq!(
+			Tasks::execute_from(&source, Weight::from_parts(25, 0)),
+			Weight::from_parts(20, 0)
+		);
+		assert_eq!(Runs::get(), vec![MockTask::Prioritized(2), MockTask::Prioritized(1)]);
+
+		// invalid tasks are skipped without counting towards the budget.
+		assert_eq!(Tasks::execute_from(&source, Weight::from_parts(25, 0)), Weight::zero());
+		assert_eq!(Runs::get().len(), 2);
+		assert_eq!(Pending::get(), vec![MockTask::Flaky]);
+	});
+}
+
+#[test]
+fn tasks_that_do_not_fit_do_not_block_lighter_ones() {
+	new_test_ext(vec![MockTask::Bulky, MockTask::Flaky, MockTask::Heavy, MockTask::Broken])
+		.execute_with(|| {
+			assert_eq!(Tasks::execute_all(Weight::from_parts(25, 0)), Weight::from_parts(20, 0));
+			assert_eq!(Runs::get(), vec![MockTask::Flaky, MockTask::Broken]);
+			assert_eq!(Pending::get(), vec![MockTask::Bulky, MockTask::Heavy, MockTask::Broken]);
+
+			// the bulky task runs once there is room for it.
+			assert_eq!(Tasks::execute_all(Weight::from_parts(30, 0)), Weight::from_parts(30, 0));
+			assert_eq!(Runs::get(), vec![MockTask::Flaky, MockTask::Broken, MockTask::Bulky]);
+		});
+}
+
+#[test]
+fn only_root_can_toggle_tasks() {
+	new_test_ext(vec![]).execute_with(|| {
+		assert_noop!(Tasks::set_tasks_enabled(RuntimeOrigin::signed(1), false), BadOrigin);
+
+		assert!(TasksEnabled::<Test>::get());
+		assert_ok!(Tasks::set_tasks_enabled(RuntimeOrigin::root(), false));
+		assert!(!TasksEnabled::<Test>::get());
+		assert_eq!(tasks_events(), vec![Event::TasksEnabledSet { enabled: false }]);
+	});
+}
+
+#[test]
+fn no_tasks_run_while_disabled() {
+	new_test_ext(vec![MockTask::Flaky]).execute_with(|| {
+		assert_ok!(Tasks::set_tasks_enabled(RuntimeOrigin::root(), false));
+		for _ in 0..3 {
+			finish_block();
+		}
+		assert!(Runs::get().is_empty());
+		assert_eq!(Pending::get(), vec![MockTask::Flaky]);
+		assert_eq!(
+			tasks_events(),
+			vec![
+				Event::TasksEnabledSet { enabled: false },
+				Event::TasksDisabled,
+				Event::TasksDisabled,
+				Event::TasksDisabled,
+			]
+		);
+
+		// execution resumes once re-enabled.
+		assert_ok!(Tasks::set_tasks_enabled(RuntimeOrigin::root(), true));
+		finish_block();
+		assert_eq!(Runs::get(), vec![MockTask::Flaky]);
+		assert!(Pending::get().is_empty());
+		assert_eq!(tasks_events().last(), Some(&Event::TasksEnabledSet { enabled: true }));
+	});
+}
+
+#[test]
+fn critical_tasks_run_while_disabled() {
+	CriticalFlaky::set(true);
+	new_test_ext(vec![MockTask::Broken, MockTask::Flaky]).execute_with(|| {
+		assert_ok!(Tasks::set_tasks_enabled(RuntimeOrigin::root(), false));
+		finish_block();
+
+		assert_eq!(Runs::get(), vec![MockTask::Flaky]);
+		assert_eq!(tasks_events().last(), Some(&Event::TasksDisabled));
+	});
+}
+
+#[test]
+fn scheduled_task_runs_at_target_block() {
+	new_test_ext(vec![MockTask::Flaky]).execute_with(|| {
+		assert_ok!(Tasks::schedule_task(MockTask::Flaky, 3));
+
+		run_scheduler_to_block(2);
+		assert!(Runs::get().is_empty());
+
+		run_scheduler_to_block(3);
+		assert_eq!(Runs::get(), vec![MockTask::Flaky]);
+		assert!(Pending::get().is_empty());
+		assert!(tasks_events().is_empty());
+	});
+}
+
+#[test]
+fn scheduled_task_is_skipped_if_invalidated() {
+	new_test_ext(vec![MockTask::Flaky]).execute_with(|| {
+		assert_ok!(Tasks::schedule_task(MockTask::Flaky, 3));
+		Pending::set(vec![]);
+
+		run_scheduler_to_block(3);
+		assert!(Runs::get().is_empty());
+		assert_eq!(
+			tasks_events(),
+			vec![Event::TaskSkipped { task: MockTask::Flaky, reason: InvalidTaskReason::Unknown }]
+		);
+	});
+}
+
+#[test]
+fn only_root_can_run_tasks() {
+	new_test_ext(vec![MockTask::Flaky]).execute_with(|| {
+		assert_noop!(Tasks::run_task(RuntimeOrigin::signed(1), MockTask::Flaky), BadOrigin);
+		assert_ok!(Tasks::run_task(RuntimeOrigin::root(), MockTask::Flaky));
+		assert_eq!(Runs::get(), vec![MockTask::Flaky]);
+	});
+}
+
+#[test]
+fn queue_pops_by_priority_then_hash() {
+	let pending =
+		vec![MockTask::Prioritized(1), MockTask::Flaky, MockTask::Prioritized(5), MockTask::Broken];
+	new_test_ext(pending).execute_with(|| {
+		assert_eq!(Tasks::enqueue_tasks(), 4);
+
+		let mut same_priority = vec![MockTask::Flaky, MockTask::Broken];
+		same_priority.sort_by_key(|t| t.full_hash_code());
+		let mut expected = vec![MockTask::Prioritized(5), MockTask::Prioritized(1)];
+		expected.extend(same_priority);
+
+		let popped = std::iter::from_fn(Tasks::pop_next_valid).collect::<Vec<_>>();
+		assert_eq!(popped, expected);
+		assert!(QueuedTasks::<Test>::get().is_empty());
+		assert_eq!(QueuedTaskOf::<Test>::iter().count(), 0);
+		// popping does not run anything.
+		assert!(Runs::get().is_empty());
+	});
+}
+
+#[test]
+fn queue_drops_stale_tasks() {
+	new_test_ext(vec![MockTask::Prioritized(5), MockTask::Prioritized(1)]).execute_with(|| {
+		assert_eq!(Tasks::enqueue_tasks(), 2);
+
+		// the highest-priority task is done in the meantime.
+		Pending::set(vec![MockTask::Prioritized(1)]);
+		assert_eq!(Tasks::pop_next_valid(), Some(MockTask::Prioritized(1)));
+		assert!(QueuedTasks::<Test>::get().is_empty());
+		assert_eq!(QueuedTaskOf::<Test>::iter().count(), 0);
+
+		assert_eq!(Tasks::pop_next_valid(), None);
+	});
+}
+
+#[test]
+fn queued_tasks_run_by_priority_across_blocks() {
+	let pending =
+		vec![MockTask::Prioritized(1), MockTask::Prioritized(5), MockTask::Prioritized(3)];
+	new_test_ext(pending).execute_with(|| {
+		// a single task fits in each block, the others stay queued.
+		finish_block_within(Weight::from_parts(10, 0));
+		assert_eq!(Runs::get(), vec![MockTask::Prioritized(5)]);
+		assert_eq!(QueuedTasks::<Test>::get().len(), 2);
+
+		// a new task waits for the queue to be drained, whatever its priority.
+		Pending::mutate(|p| p.push(MockTask::Prioritized(9)));
+		finish_block_within(Weight::from_parts(10, 0));
+		finish_block_within(Weight::from_parts(10, 0));
+		assert_eq!(
+			Runs::get(),
+			vec![MockTask::Prioritized(5), MockTask::Prioritized(3), MockTask::Prioritized(1)]
+		);
+		assert!(QueuedTasks::<Test>::get().is_empty());
+
+		finish_block_within(Weight::from_parts(10, 0));
+		assert_eq!(Runs::get().last(), Some(&MockTask::Prioritized(9)));
+		assert!(Pending::get().is_empty());
+	});
+}
+
+#[test]
+fn queued_tasks_are_left_while_disabled() {
+	new_test_ext(vec![MockTask::Prioritized(1)]).execute_with(|| {
+		assert_eq!(Tasks::enqueue_tasks(), 1);
+		assert_ok!(Tasks::set_tasks_enabled(RuntimeOrigin::root(), false));
+		finish_block();
+		assert!(Runs::get().is_empty());
+		assert_eq!(QueuedTasks::<Test>::get().len(), 1);
+	});
+}
+
+#[test]
+fn queue_is_bounded_and_keeps_tasks_once() {
+	let pending = (1..=5).map(MockTask::Prioritized).collect::<Vec<_>>();
+	new_test_ext(pending).execute_with(|| {
+		assert_eq!(Tasks::enqueue_tasks(), 4);
+		assert_eq!(Tasks::enqueue_tasks(), 0);
+
+		let task = Tasks::pop_next_valid().unwrap();
+		assert_eq!(task, MockTask::Prioritized(4));
+		assert_ok!(task.run());
+		// the freed slot goes to the task that did not fit.
+		assert_eq!(Tasks::enqueue_tasks(), 1);
+		assert_eq!(Tasks::pop_next_valid(), Some(MockTask::Prioritized(5)));
+	});
+}
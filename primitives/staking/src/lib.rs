@@ -115,6 +115,17 @@ pub trait OnStakingUpdate<AccountId, Balance> {
 	/// Fired when someone is fully unstaked.
 	fn on_unstake(_who: &AccountId) {}
 
+	/// Fired when someone withdraws all of their unbonded funds, right before they are unstaked.
+	///
+	/// The last nominations of `who`, if any, and their stake prior to withdrawing are provided as
+	/// they can no longer be fetched in any way.
+	fn on_withdraw_unbonded(
+		_who: &AccountId,
+		_nominations: Vec<AccountId>,
+		_prev_stake: Stake<Balance>,
+	) {
+	}
+
 	/// Fired when a staker is slashed.
 	///
 	/// * `stash` - The stash of the staker whom the slash was applied to.
@@ -189,6 +200,14 @@ pub trait StakingInterface {
 		Self::stake(who).map(|s| s.active)
 	}
 
+	/// The sum of the active stake of all stakers.
+	///
+	/// Implementations that cannot compute this cheaply return the default value (i.e. zero),
+	/// which callers should treat as unknown rather than as an empty staking system.
+	fn total_active_stake() -> Self::Balance {
+		Default::default()
+	}
+
 	/// Returns whether a staker is unbonding, `Err` if not a staker at all.
 	fn is_unbonding(who: &Self::AccountId) -> Result<bool, DispatchError> {
 		Self::stake(who).map(|s| s.active != s.total)
@@ -253,6 +272,13 @@ pub trait StakingInterface {
 		Self::status(who).map(|s| matches!(s, StakerStatus::Validator)).unwrap_or(false)
 	}
 
+	/// Checks whether or not this is a nominator account.
+	fn is_nominator(who: &Self::AccountId) -> bool {
+		Self::status(who)
+			.map(|s| matches!(s, StakerStatus::Nominator(_)))
+			.unwrap_or(false)
+	}
+
 	/// Get the nominations of a stash, if they are a nominator, `None` otherwise.
 	fn nominations(who: &Self::AccountId) -> Option<Vec<Self::AccountId>> {
 		match Self::status(who) {
@@ -261,6 +287,12 @@ pub trait StakingInterface {
 		}
 	}
 
+	/// The total stake exposed by `validator` in the active era, `None` if it is not exposed in
+	/// it, or if the implementation does not keep track of exposures.
+	fn active_era_exposure(_validator: &Self::AccountId) -> Option<Self::Balance> {
+		None
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	fn add_era_stakers(
 		current_era: &EraIndex,